@@ -0,0 +1,112 @@
+use crate::errors::CrawlerError::ParseError;
+use crate::errors::CrawlerResult;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Quotas are counted per hour, the same period the crawler's rate budget uses
+pub const QUOTA_WINDOW: Duration = Duration::from_secs(3600);
+
+/// A client allowed to use the HTTP server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiKey {
+    pub name: String,
+    pub key: String,
+    /// Profiles and search pages the client may request per [`QUOTA_WINDOW`]
+    pub quota: u32,
+}
+
+/// Parses `name:key[:quota]` entries separated by commas. Keys without a quota get `default_quota`.
+pub fn parse_api_keys(value: &str, default_quota: u32) -> CrawlerResult<Vec<ApiKey>> {
+    let mut keys = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let parts: Vec<&str> = entry.split(':').collect();
+        let quota = match parts.as_slice() {
+            [_, _] => default_quota,
+            [_, _, quota] => match quota.parse() {
+                Ok(quota) => quota,
+                Err(e) => return Err(ParseError(format!("Invalid quota for api key {} {}", parts[0], e))),
+            },
+            _ => return Err(ParseError(format!("Invalid api key entry {}, expected name:key[:quota]", parts[0]))),
+        };
+        if parts[0].is_empty() || parts[1].is_empty() {
+            return Err(ParseError("Api key entry with an empty name or key".to_string()));
+        }
+        keys.push(ApiKey {
+            name: parts[0].to_string(),
+            key: parts[1].to_string(),
+            quota,
+        });
+    }
+    Ok(keys)
+}
+
+struct Usage {
+    window_start: Instant,
+    used: u32,
+}
+
+/// Authenticates clients by key and tracks how much of its quota each one has used.
+pub struct ApiKeys {
+    keys: HashMap<String, ApiKey>,
+    /// Names of the clients allowed to change the running crawler
+    admins: HashSet<String>,
+    usage: Mutex<HashMap<String, Usage>>,
+}
+
+impl ApiKeys {
+    pub fn new(keys: Vec<ApiKey>) -> Self {
+        Self {
+            keys: keys.into_iter().map(|key| (key.key.clone(), key)).collect(),
+            admins: HashSet::new(),
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_admins(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.admins.extend(names);
+        self
+    }
+
+    pub fn is_admin(&self, client: &ApiKey) -> bool {
+        self.admins.contains(&client.name)
+    }
+
+    /// Admin endpoints stay closed until keys and ADMIN_CLIENTS are configured, even on a server that
+    /// is otherwise open to anyone.
+    pub fn may_administer(&self, client: Option<&ApiKey>) -> bool {
+        self.is_enabled() && client.is_some_and(|client| self.is_admin(client))
+    }
+
+    /// No keys configured means the server is open to anyone.
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    pub fn authenticate(&self, key: &str) -> Option<&ApiKey> {
+        self.keys.get(key)
+    }
+
+    pub fn charge(&self, client: &ApiKey, cost: u32) -> Result<(), Duration> {
+        self.charge_at(client, cost, Instant::now())
+    }
+
+    /// Takes `cost` from the client's quota, or returns how long until the quota renews when it would
+    /// be exceeded. A refused request takes nothing.
+    pub fn charge_at(&self, client: &ApiKey, cost: u32, now: Instant) -> Result<(), Duration> {
+        let mut usage = self.usage.lock().unwrap();
+        let usage = usage.entry(client.name.clone()).or_insert(Usage {
+            window_start: now,
+            used: 0,
+        });
+        if now.duration_since(usage.window_start) >= QUOTA_WINDOW {
+            usage.window_start = now;
+            usage.used = 0;
+        }
+        if usage.used.saturating_add(cost) > client.quota {
+            return Err(QUOTA_WINDOW.saturating_sub(now.duration_since(usage.window_start)));
+        }
+        usage.used += cost;
+        Ok(())
+    }
+}
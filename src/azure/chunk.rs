@@ -0,0 +1,191 @@
+use crate::azure::json::{ProfileIds, ProfileJob};
+use crate::errors::CrawlerError::ParseError;
+use crate::errors::CrawlerResult;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Service Bus standard tier allows 256 KB per message, headers and properties included
+pub const MAX_MESSAGE_BYTES: usize = 192 * 1024;
+
+/// Room left in a chunk message for everything but its data
+const CHUNK_OVERHEAD: usize = 256;
+
+/// How long the chunks of a message are kept while its other chunks are missing
+pub const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// One part of a message too large to be sent whole. `data` holds a base64 slice of the original
+/// body, so slices never split a character or need escaping.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Chunk {
+    /// Hash of the whole body, resending the same message yields the same chunks
+    pub chunk_id: String,
+    /// Starts at 0
+    pub sequence: u32,
+    pub total: u32,
+    pub data: String,
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+/// Splits a serialized message into bodies of at most `max_bytes`. A message that already fits is
+/// returned untouched, so consumers only see chunks for large messages.
+pub fn split(body: String, max_bytes: usize) -> Vec<String> {
+    if body.len() <= max_bytes {
+        return vec![body];
+    }
+    let chunk_id = sha256_hex(body.as_bytes());
+    let slice_len = (max_bytes.saturating_sub(CHUNK_OVERHEAD) / 4 * 3).max(3);
+    let slices: Vec<&[u8]> = body.as_bytes().chunks(slice_len).collect();
+    let total = slices.len() as u32;
+    slices
+        .into_iter()
+        .enumerate()
+        .map(|(sequence, slice)| {
+            let chunk = Chunk {
+                chunk_id: chunk_id.clone(),
+                sequence: sequence as u32,
+                total,
+                data: BASE64_STANDARD.encode(slice),
+            };
+            serde_json::to_string(&chunk).unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Splits a profile job whose message would not fit into `max_bytes` into several jobs, halving its
+/// ids until every part fits. Each part is a complete job, so workers need no reassembly.
+pub fn split_profile_job(job: ProfileJob, max_bytes: usize) -> Vec<ProfileJob> {
+    let size = serde_json::to_string(&job).map(|body| body.len()).unwrap_or_default();
+    if size <= max_bytes || job.payload.ids.len() < 2 {
+        return vec![job];
+    }
+    let mut first = job;
+    let rest = first.payload.ids.split_off(first.payload.ids.len() / 2);
+    let second = first.clone().map(|ids| ProfileIds { ids: rest, ..ids });
+    let mut parts = split_profile_job(first, max_bytes);
+    parts.extend(split_profile_job(second, max_bytes));
+    parts
+}
+
+struct Pending {
+    parts: Vec<Option<Vec<u8>>>,
+    first_seen: Instant,
+}
+
+/// Collects chunks on the consumer side until their message is complete. Chunks may arrive in any
+/// order and interleaved with other messages. Messages still incomplete after the timeout are dropped,
+/// their missing chunks are not coming.
+pub struct Reassembler {
+    pending: HashMap<String, Pending>,
+    timeout: Duration,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::with_timeout(REASSEMBLY_TIMEOUT)
+    }
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            pending: HashMap::new(),
+            timeout,
+        }
+    }
+
+    /// Drops incomplete messages whose first chunk arrived longer than the timeout ago.
+    pub fn evict_expired(&mut self) -> usize {
+        let before = self.pending.len();
+        let timeout = self.timeout;
+        self.pending.retain(|chunk_id, pending| {
+            let expired = pending.first_seen.elapsed() > timeout;
+            if expired {
+                warn!("Dropping incomplete chunked message {} after {:?}", chunk_id, timeout);
+            }
+            !expired
+        });
+        before - self.pending.len()
+    }
+
+    /// Returns the original body once the last missing chunk of its message arrives.
+    pub fn push(&mut self, chunk: Chunk) -> CrawlerResult<Option<String>> {
+        if chunk.sequence >= chunk.total {
+            return Err(ParseError(format!(
+                "Chunk {} has sequence {} out of {}",
+                chunk.chunk_id, chunk.sequence, chunk.total
+            )));
+        }
+        let data = match BASE64_STANDARD.decode(&chunk.data) {
+            Ok(data) => data,
+            Err(e) => return Err(ParseError(format!("Invalid data in chunk {} {}", chunk.chunk_id, e))),
+        };
+        self.evict_expired();
+        let parts = &mut self
+            .pending
+            .entry(chunk.chunk_id.clone())
+            .or_insert_with(|| Pending {
+                parts: vec![None; chunk.total as usize],
+                first_seen: Instant::now(),
+            })
+            .parts;
+        if parts.len() != chunk.total as usize {
+            return Err(ParseError(format!(
+                "Chunk {} announced {} parts, expected {}",
+                chunk.chunk_id,
+                chunk.total,
+                parts.len()
+            )));
+        }
+        parts[chunk.sequence as usize] = Some(data);
+        if parts.iter().any(Option::is_none) {
+            return Ok(None);
+        }
+        let parts = self
+            .pending
+            .remove(&chunk.chunk_id)
+            .map(|pending| pending.parts)
+            .unwrap_or_default();
+        let body = parts.into_iter().flatten().flatten().collect();
+        match String::from_utf8(body) {
+            Ok(body) => Ok(Some(body)),
+            Err(e) => Err(ParseError(format!("Reassembled message {} is not utf-8 {}", chunk.chunk_id, e))),
+        }
+    }
+
+    /// Takes any received body, returning the message it completes. Bodies that are not chunks are
+    /// parsed right away.
+    pub fn receive<T: DeserializeOwned>(&mut self, body: &str) -> CrawlerResult<Option<T>> {
+        let body = match serde_json::from_str::<Chunk>(body) {
+            Ok(chunk) => match self.push(chunk)? {
+                Some(body) => body,
+                None => return Ok(None),
+            },
+            Err(_) => body.to_string(),
+        };
+        match serde_json::from_str(&body) {
+            Ok(message) => Ok(Some(message)),
+            Err(e) => Err(ParseError(format!("Invalid message {}", e))),
+        }
+    }
+
+    /// Messages still missing chunks.
+    pub fn incomplete(&self) -> usize {
+        self.pending.len()
+    }
+}
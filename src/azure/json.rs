@@ -1,12 +1,158 @@
-use crate::linkedin::api::json::Profile;
+use crate::linkedin::api::json::SearchParams;
+use crate::linkedin::profile::Profile;
+use chrono::{DateTime, Utc};
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
 //Move this whole crate under linkedin
-#[derive(serde::Deserialize, serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize, Clone, utoipa::ToSchema)]
 pub struct ProfileIds {
     pub ids: Vec<String>,
     pub request_metadata: Option<String>,
 }
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 pub struct CrawledProfiles {
     pub profiles: Vec<Profile>,
     pub request_metadata: Option<String>,
 }
+
+/// Service Bus metadata of a peek-locked message, sent in the `BrokerProperties` header
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrokerProperties {
+    pub delivery_count: u32,
+    pub lock_token: String,
+    pub message_id: String,
+}
+
+pub type SearchJob = Envelope<SearchParams>;
+pub type ProfileJob = Envelope<ProfileIds>;
+
+/// Bumped whenever an envelope field is removed or changes meaning.
+pub const ENVELOPE_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Every message on the queues and the manager bus travels in an envelope. Results reply to the job
+/// they came from through [`Envelope::reply`], keeping its job, correlation id and tenant.
+#[derive(Serialize, Clone, Debug)]
+pub struct Envelope<T> {
+    pub schema_version: u32,
+    pub job_id: String,
+    pub correlation_id: Option<String>,
+    pub tenant: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub priority: Priority,
+    pub payload: T,
+}
+
+#[derive(Deserialize)]
+struct EnvelopeWire<T> {
+    schema_version: u32,
+    job_id: String,
+    correlation_id: Option<String>,
+    tenant: Option<String>,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    priority: Priority,
+    payload: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(payload: T) -> Self {
+        Self {
+            schema_version: ENVELOPE_SCHEMA_VERSION,
+            job_id: uuid::Uuid::new_v4().to_string(),
+            correlation_id: None,
+            tenant: None,
+            created_at: Utc::now(),
+            priority: Priority::default(),
+            payload,
+        }
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Envelope for a message produced while handling this one.
+    pub fn reply<R>(&self, payload: R) -> Envelope<R> {
+        Envelope {
+            schema_version: ENVELOPE_SCHEMA_VERSION,
+            job_id: self.job_id.clone(),
+            correlation_id: self.correlation_id.clone(),
+            tenant: self.tenant.clone(),
+            created_at: Utc::now(),
+            priority: self.priority,
+            payload,
+        }
+    }
+
+    /// Same envelope around a different payload, e.g. the unfinished part of a job being requeued.
+    pub fn map<R>(self, f: impl FnOnce(T) -> R) -> Envelope<R> {
+        Envelope {
+            schema_version: self.schema_version,
+            job_id: self.job_id,
+            correlation_id: self.correlation_id,
+            tenant: self.tenant,
+            created_at: self.created_at,
+            priority: self.priority,
+            payload: f(self.payload),
+        }
+    }
+}
+
+/// Job id for a bare payload, taken from a hash of the message so every delivery of it gets the same id.
+fn bare_job_id(value: &serde_json::Value) -> String {
+    let digest = Sha256::digest(value.to_string().as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    uuid::Builder::from_custom_bytes(bytes).into_uuid().to_string()
+}
+
+/// Accepts both enveloped messages and the bare payloads producers sent before envelopes existed.
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Envelope<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let is_envelope = value.get("schema_version").is_some() && value.get("payload").is_some();
+        if !is_envelope {
+            let job_id = bare_job_id(&value);
+            let envelope = T::deserialize(value).map(Envelope::new).map_err(D::Error::custom)?;
+            return Ok(Envelope { job_id, ..envelope });
+        }
+        let wire = EnvelopeWire::<T>::deserialize(value).map_err(D::Error::custom)?;
+        if wire.schema_version > ENVELOPE_SCHEMA_VERSION {
+            return Err(D::Error::custom(format!("Unsupported envelope version {}", wire.schema_version)));
+        }
+        Ok(Self {
+            schema_version: wire.schema_version,
+            job_id: wire.job_id,
+            correlation_id: wire.correlation_id,
+            tenant: wire.tenant,
+            created_at: wire.created_at,
+            priority: wire.priority,
+            payload: wire.payload,
+        })
+    }
+}
@@ -0,0 +1,162 @@
+use crate::azure::chunk::{hex, sha256_hex};
+use crate::azure::Label;
+use crate::env::ManagerAuth;
+use crate::errors::CrawlerError;
+use crate::metrics::MANAGER_PUSH_DURATION;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+use sha2::Sha256;
+use std::fmt::{Display, Formatter};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Hash of the body, so a retried submission carries the same id and the manager can drop it
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+pub const TIMESTAMP_HEADER: &str = "X-Signature-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+#[derive(Debug)]
+pub enum ManagerError {
+    Serialize(String),
+    /// The request never got a response
+    Transport(String),
+    Unauthorized {
+        status: u16,
+    },
+    /// The manager refused the submission, sending it again will not help
+    Rejected {
+        status: u16,
+        body: String,
+    },
+    Unavailable {
+        status: u16,
+        body: String,
+    },
+}
+
+impl ManagerError {
+    /// Refused credentials are retried too, they are usually a token being rotated rather than a bad result.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ManagerError::Transport(_) | ManagerError::Unauthorized { .. } | ManagerError::Unavailable { .. }
+        )
+    }
+}
+
+impl Display for ManagerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManagerError::Serialize(e) => write!(f, "Failed to serialize result {}", e),
+            ManagerError::Transport(e) => write!(f, "Failed to reach manager {}", e),
+            ManagerError::Unauthorized { status } => write!(f, "Manager refused credentials with status {}", status),
+            ManagerError::Rejected { status, body } => write!(f, "Manager rejected result with status {} body {}", status, body),
+            ManagerError::Unavailable { status, body } => write!(f, "Manager unavailable with status {} body {}", status, body),
+        }
+    }
+}
+
+impl From<ManagerError> for CrawlerError {
+    fn from(e: ManagerError) -> Self {
+        CrawlerError::ManagerError(e)
+    }
+}
+
+/// A message accepted by the manager.
+#[derive(Debug)]
+pub struct Submission {
+    pub request_id: String,
+    /// The manager already had a message with this request id
+    pub duplicate: bool,
+}
+
+/// Hex HMAC-SHA256 over the timestamp, request id and body, each on its own line.
+pub fn signature(secret: &str, timestamp: u64, request_id: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\n{}\n{}", timestamp, request_id, body).as_bytes());
+    hex(&mac.finalize().into_bytes())
+}
+
+pub struct ManagerClient {
+    search_api: String,
+    profile_api: String,
+    auth: ManagerAuth,
+    client: Client,
+}
+
+impl ManagerClient {
+    pub fn new(search_api: impl Into<String>, profile_api: impl Into<String>, auth: ManagerAuth) -> Self {
+        Self {
+            search_api: search_api.into(),
+            profile_api: profile_api.into(),
+            auth,
+            client: Client::new(),
+        }
+    }
+
+    /// Sends a result to the endpoint for its label. The Service Bus message size limit does not apply
+    /// to these requests, so results are always sent whole.
+    pub async fn submit<T: Serialize>(&self, data: &T, label: Label) -> Result<Submission, ManagerError> {
+        let api = match label {
+            Label::SearchComplete => self.search_api.as_str(),
+            Label::ProfilesComplete => self.profile_api.as_str(),
+        };
+        let body = match serde_json::to_string(data) {
+            Ok(body) => body,
+            Err(e) => return Err(ManagerError::Serialize(e.to_string())),
+        };
+        let start = Instant::now();
+        let submission = self.send(api, body).await;
+        let outcome = match submission {
+            Ok(_) => "success",
+            Err(_) => "error",
+        };
+        Self::observe(label, outcome, start);
+        submission
+    }
+
+    fn observe(label: Label, outcome: &str, start: Instant) {
+        MANAGER_PUSH_DURATION
+            .with_label_values(&[label.to_string().as_str(), outcome])
+            .observe(start.elapsed().as_secs_f64());
+    }
+
+    async fn send(&self, api: &str, body: String) -> Result<Submission, ManagerError> {
+        let request_id = sha256_hex(body.as_bytes());
+        let mut request = self
+            .client
+            .post(api)
+            .header("Content-Type", "application/json")
+            .header(REQUEST_ID_HEADER, request_id.as_str());
+        request = match &self.auth {
+            ManagerAuth::None => request,
+            ManagerAuth::Bearer(token) => request.bearer_auth(token),
+            ManagerAuth::Hmac(secret) => {
+                let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                request
+                    .header(TIMESTAMP_HEADER, timestamp)
+                    .header(SIGNATURE_HEADER, signature(secret, timestamp, &request_id, &body))
+            }
+        };
+        let response = match request.body(body).send().await {
+            Ok(response) => response,
+            Err(e) => return Err(ManagerError::Transport(e.to_string())),
+        };
+        let status = response.status();
+        if status.is_success() || status == StatusCode::CONFLICT {
+            return Ok(Submission {
+                request_id,
+                duplicate: status == StatusCode::CONFLICT,
+            });
+        }
+        let code = status.as_u16();
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            return Err(ManagerError::Unauthorized { status: code });
+        }
+        let body = response.text().await.unwrap_or_default();
+        match status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::REQUEST_TIMEOUT {
+            true => Err(ManagerError::Unavailable { status: code, body }),
+            false => Err(ManagerError::Rejected { status: code, body }),
+        }
+    }
+}
@@ -1,61 +1,44 @@
+pub mod chunk;
 pub mod json;
+pub mod manager;
+pub mod sas;
 
 // TODO Refactor services into another crate
-use crate::azure::json::ProfileIds;
+use crate::azure::chunk::MAX_MESSAGE_BYTES;
+use crate::azure::json::{BrokerProperties, ProfileJob, SearchJob};
+use crate::azure::manager::ManagerClient;
+use crate::azure::sas::{SasKey, SasResource, SasSigner};
+use crate::control::ControlCommand;
 use crate::env::get_env;
+use crate::errors::CrawlerError;
 use crate::errors::CrawlerError::{BusError, QueueError};
 use crate::errors::CrawlerResult;
-use crate::linkedin::api::json::SearchParams;
-use base64::prelude::BASE64_STANDARD;
-use base64::Engine;
-use hmac::{Hmac, Mac};
-use reqwest::Client;
+use crate::queue::{parse_message, Delivery, QueueKind, Receipt};
+use reqwest::{Client, Method};
+use serde::de::DeserializeOwned;
 use serde::Serialize;
-use sha2::Sha256;
 use std::fmt::{Display, Formatter};
-use std::time::{SystemTime, UNIX_EPOCH};
-use urlencoding::encode;
-fn create_sas_token(resource_uri: &str, sas_key_name: &str, sas_key: &str) -> Result<String, &'static str> {
-    let encoded_uri = encode(resource_uri);
+use std::time::Duration;
 
-    let ttl = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 60;
-
-    let signature_str = format!("{}\n{}", encoded_uri, ttl);
-    let key_bytes = sas_key.as_bytes();
-
-    let mut mac = match Hmac::<Sha256>::new_from_slice(&key_bytes) {
-        Ok(mac) => mac,
-        Err(_) => return Err("HMAC on SAS key failed"),
-    };
-    mac.update(signature_str.as_bytes());
-    let hash = BASE64_STANDARD.encode(mac.finalize().into_bytes());
-
-    Ok(format!(
-        "SharedAccessSignature sr={}&sig={}&se={}&skn={}",
-        encoded_uri,
-        encode(&hash),
-        ttl,
-        sas_key_name
-    ))
-}
+/// Azure tokens can live much longer, an hour keeps a leaked token short lived
+const SAS_TTL: Duration = Duration::from_secs(60 * 60);
+const SAS_REFRESH_BEFORE: Duration = Duration::from_secs(5 * 60);
 
 pub struct AzureClient {
-    manager_bus_uri: &'static str,
     manager_bus_api: &'static str,
-    manager_bus_key: &'static str,
-    sas_key_name: &'static str,
-    sas_profile_key: &'static str,
-    sas_search_key: &'static str,
-    search_uri: &'static str,
+    signer: SasSigner,
     search_dequeue_api: &'static str,
     search_queue_api: &'static str,
-    profile_uri: &'static str,
     profile_dequeue_api: &'static str,
-    manager_search_api: &'static str,
-    manager_profile_api: &'static str,
+    profile_queue_api: &'static str,
+    control_dequeue_api: &'static str,
+    control_queue_api: &'static str,
+    manager: ManagerClient,
     client: Client,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum Label {
     ProfilesComplete,
     SearchComplete,
@@ -80,94 +63,206 @@ impl AzureClient {
         let sas_search_key = get_env().await.azure_sas_search_key.as_str();
         let search_uri = get_env().await.azure_search_uri.as_str();
         let search_dequeue_api = get_env().await.azure_search_dequeue_api.as_str();
-        let search_queue_api = get_env().await.azure_profile_queue_api.as_str();
+        let search_queue_api = get_env().await.azure_search_queue_api.as_str();
         let profile_uri = get_env().await.azure_profile_uri.as_str();
         let profile_dequeue_api = get_env().await.azure_profile_dequeue_api.as_str();
-        let manager_profile_api = get_env().await.manager_profile_api.as_str();
-        let manager_search_api = get_env().await.manager_search_api.as_str();
+        let profile_queue_api = get_env().await.azure_profile_queue_api.as_str();
+        let control_uri = get_env().await.azure_control_uri.as_str();
+        let control_dequeue_api = get_env().await.azure_control_dequeue_api.as_str();
+        let control_queue_api = get_env().await.azure_control_queue_api.as_str();
+        let sas_control_key = get_env().await.azure_sas_control_key.as_str();
+        let manager = ManagerClient::new(
+            get_env().await.manager_search_api.as_str(),
+            get_env().await.manager_profile_api.as_str(),
+            get_env().await.manager_auth.clone(),
+        );
+
+        let key = |resource_uri: &str, key: &str| SasKey {
+            resource_uri: resource_uri.to_string(),
+            key_name: sas_key_name.to_string(),
+            key: key.to_string(),
+        };
+        let signer = SasSigner::new(SAS_TTL, SAS_REFRESH_BEFORE)
+            .with_key(SasResource::ManagerBus, key(manager_bus_uri, manager_bus_key))
+            .with_key(SasResource::SearchQueue, key(search_uri, sas_search_key))
+            .with_key(SasResource::ProfileQueue, key(profile_uri, sas_profile_key))
+            .with_key(SasResource::ControlQueue, key(control_uri, sas_control_key));
 
         Self {
-            manager_bus_uri,
             manager_bus_api,
-            manager_bus_key,
-            sas_key_name,
-            sas_profile_key,
-            sas_search_key,
-            search_uri,
+            signer,
             search_dequeue_api,
             search_queue_api,
-            profile_uri,
             profile_dequeue_api,
-            manager_search_api,
-            manager_profile_api,
+            profile_queue_api,
+            control_dequeue_api,
+            control_queue_api,
+            manager,
             client: Client::new(),
         }
     }
-    pub async fn dequeue_profile(&self) -> CrawlerResult<Option<ProfileIds>> {
-        let sas_token = match create_sas_token(self.profile_uri, self.sas_key_name, self.sas_profile_key) {
-            Ok(token) => token,
-            Err(e) => return Err(QueueError(e.to_string())),
-        };
-        match self
+    fn queue_sas_token(&self, kind: QueueKind) -> CrawlerResult<String> {
+        self.signer.token(kind.into())
+    }
+
+    /// Peek-lock receive, the message stays in the queue until completed. Service Bus holds the request
+    /// open for up to `wait` while the queue is empty.
+    async fn receive<T>(&self, kind: QueueKind, api: &str, wait: Duration) -> CrawlerResult<Option<Delivery<T>>>
+    where
+        T: DeserializeOwned,
+    {
+        let sas_token = self.queue_sas_token(kind)?;
+        let request = self
             .client
-            .delete(self.profile_dequeue_api)
-            .header("Authorization", sas_token)
-            .send()
-            .await
+            .post(api)
+            .query(&[("timeout", wait.as_secs().max(1))])
+            .header("Authorization", sas_token);
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => return Err(QueueError(format!("Failed to receive {} {}", kind, e))),
+        };
+        if response.status() == 204 {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(QueueError(format!(
+                "Failed to receive {} with status {}",
+                kind,
+                response.status().as_u16()
+            )));
+        }
+        let properties = match response
+            .headers()
+            .get("BrokerProperties")
+            .and_then(|header| header.to_str().ok())
+            .map(serde_json::from_str::<BrokerProperties>)
         {
-            Ok(response) => {
-                if response.status() == 204 {
-                    return Ok(None);
-                }
-                match response.json::<ProfileIds>().await {
-                    Ok(profile) => Ok(Some(profile)),
-                    Err(e) => Err(QueueError(format!("Failed to dequeue profile {:?}", e))),
+            Some(Ok(properties)) => properties,
+            Some(Err(e)) => return Err(QueueError(format!("Invalid BrokerProperties on {} message {}", kind, e))),
+            None => return Err(QueueError(format!("Missing BrokerProperties on {} message", kind))),
+        };
+        // Location points at the locked message, build it by hand if a proxy dropped the header
+        let lock_id = match response.headers().get("Location").and_then(|header| header.to_str().ok()) {
+            Some(location) => location.to_string(),
+            None => format!(
+                "{}/{}/{}",
+                api.trim_end_matches("/head"),
+                properties.message_id,
+                properties.lock_token
+            ),
+        };
+        let receipt = Receipt {
+            queue: kind,
+            lock_id,
+            delivery_count: properties.delivery_count,
+        };
+        match response.text().await {
+            Ok(body) => Ok(Some(Delivery {
+                message: parse_message(&body),
+                receipt,
+            })),
+            Err(e) => {
+                if let Err(e) = self.abandon_message(&receipt).await {
+                    error!("Failed to abandon unreadable {} message {}", kind, e);
                 }
+                Err(QueueError(format!("Failed to read {} message {:?}", kind, e)))
             }
-            Err(e) => Err(QueueError(format!("Failed to dequeue profile {}", e))),
         }
     }
-    pub async fn dequeue_search(&self) -> CrawlerResult<Option<SearchParams>> {
-        let sas_token = match create_sas_token(self.search_uri, self.sas_key_name, self.sas_search_key) {
-            Ok(token) => token,
-            Err(e) => return Err(QueueError(e.to_string())),
-        };
 
-        match self
+    pub async fn dequeue_profile(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ProfileJob>>> {
+        self.receive(QueueKind::Profiles, self.profile_dequeue_api, wait).await
+    }
+
+    pub async fn dequeue_search(&self, wait: Duration) -> CrawlerResult<Option<Delivery<SearchJob>>> {
+        self.receive(QueueKind::Search, self.search_dequeue_api, wait).await
+    }
+
+    /// Waits out `wait` without receiving anything when no control queue is configured.
+    pub async fn dequeue_control(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ControlCommand>>> {
+        if self.control_dequeue_api.is_empty() {
+            tokio::time::sleep(wait).await;
+            return Ok(None);
+        }
+        self.receive(QueueKind::Control, self.control_dequeue_api, wait).await
+    }
+
+    async fn send_lock_request(&self, method: Method, receipt: &Receipt) -> CrawlerResult<()> {
+        let sas_token = self.queue_sas_token(receipt.queue)?;
+        let response = match self
             .client
-            .delete(self.search_dequeue_api)
+            .request(method.clone(), receipt.lock_id.as_str())
             .header("Authorization", sas_token)
+            .header("Content-Length", "0")
             .send()
             .await
         {
-            Ok(response) => {
-                if response.status() == 204 {
-                    return Ok(None);
-                }
-                match response.json::<SearchParams>().await {
-                    Ok(params) => Ok(Some(params)),
-                    Err(e) => Err(QueueError(format!("Failed to dequeue profile {}", e))),
-                }
-            }
-            Err(e) => Err(QueueError(format!("Failed to dequeue profile {}", e))),
+            Ok(response) => response,
+            Err(e) => return Err(QueueError(format!("{} {} message failed {}", method, receipt.queue, e))),
+        };
+        if !response.status().is_success() {
+            return Err(QueueError(format!(
+                "{} {} message failed with status {} body {}",
+                method,
+                receipt.queue,
+                response.status().as_u16(),
+                response.text().await.unwrap_or_default()
+            )));
         }
+        Ok(())
+    }
+
+    pub async fn complete_message(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.send_lock_request(Method::DELETE, receipt).await
+    }
+
+    pub async fn abandon_message(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.send_lock_request(Method::PUT, receipt).await
+    }
+
+    pub async fn renew_message_lock(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.send_lock_request(Method::POST, receipt).await
     }
 
     pub async fn push_to_queue<T>(&self, data: &T) -> CrawlerResult<()>
     where
         T: Serialize,
     {
-        let sas_token = match create_sas_token(self.profile_uri, self.sas_key_name, self.sas_profile_key) {
-            Ok(sas) => sas,
-            Err(e) => return Err(BusError(format!("Failed to push search result {}", e))),
-        };
+        self.send_to_queue(QueueKind::Profiles, self.profile_queue_api, data).await
+    }
+
+    pub async fn push_to_search_queue<T>(&self, data: &T) -> CrawlerResult<()>
+    where
+        T: Serialize,
+    {
+        self.send_to_queue(QueueKind::Search, self.search_queue_api, data).await
+    }
+
+    pub async fn push_to_control_queue(&self, command: &ControlCommand) -> CrawlerResult<()> {
+        self.send_to_queue(QueueKind::Control, self.control_queue_api, command).await
+    }
+
+    async fn send_to_queue<T>(&self, kind: QueueKind, api: &str, data: &T) -> CrawlerResult<()>
+    where
+        T: Serialize,
+    {
+        let sas_token = self.queue_sas_token(kind)?;
         let json_body = match serde_json::to_string(data) {
             Ok(json_body) => json_body,
-            Err(e) => return Err(BusError(format!("Failed to push search result {}", e))),
+            Err(e) => return Err(QueueError(format!("Failed to push to queue {}", e))),
         };
+        // Profile jobs are split into smaller jobs before they get here, see chunk::split_profile_job
+        if json_body.len() > MAX_MESSAGE_BYTES {
+            return Err(QueueError(format!(
+                "Message of {} bytes is over the {} byte limit of the {} queue",
+                json_body.len(),
+                MAX_MESSAGE_BYTES,
+                kind
+            )));
+        }
         match self
             .client
-            .post(self.search_queue_api)
+            .post(api)
             .header("Authorization", sas_token)
             .header("Content-Type", "application/json")
             .body(json_body)
@@ -179,78 +274,58 @@ impl AzureClient {
                     return Err(QueueError(format!(
                         "Failed to push to queue with status {} body {}",
                         request.status().as_u16(),
-                        request.text().await.unwrap()
+                        request.text().await.unwrap_or_default()
                     )));
                 }
             }
             Err(e) => {
-                return Err(QueueError(format!("Failed to push to bus {:?}", e)));
+                return Err(QueueError(format!("Failed to push to queue {:?}", e)));
             }
         }
         Ok(())
     }
 
+    /// Large results are split into chunks, see [`chunk::Reassembler`] on the receiving side.
     pub async fn push_to_bus<T>(&self, data: &T, label: Label) -> CrawlerResult<()>
     where
         T: Serialize,
     {
-        let sas = match create_sas_token(self.manager_bus_uri, self.sas_key_name, self.manager_bus_key) {
-            Ok(sas) => sas,
-            Err(e) => return Err(BusError(format!("Failed to push search result {}", e))),
-        };
+        let sas = self.signer.token(SasResource::ManagerBus)?;
         let json_body = match serde_json::to_string(data) {
             Ok(json_body) => json_body,
             Err(e) => return Err(BusError(format!("Failed to push search result {}", e))),
         };
 
-        let request = match self
-            .client
-            .post(self.manager_bus_api)
-            .header("Authorization", sas)
-            .header("Content-Type", "application/json")
-            .header("BrokerProperties", format!("{{\"Label\":\"{}\"}}", label))
-            .body(json_body)
-            .send()
-            .await
-        {
-            Ok(request) => request,
-            Err(e) => return Err(BusError(format!("Failed to push search result {}", e))),
-        };
-        if !request.status().is_success() {
-            return Err(BusError(format!("Failed to push search result {}", request.text().await.unwrap())));
+        for body in chunk::split(json_body, MAX_MESSAGE_BYTES) {
+            let request = match self
+                .client
+                .post(self.manager_bus_api)
+                .header("Authorization", sas.as_str())
+                .header("Content-Type", "application/json")
+                .header("BrokerProperties", format!("{{\"Label\":\"{}\"}}", label))
+                .body(body)
+                .send()
+                .await
+            {
+                Ok(request) => request,
+                Err(e) => return Err(BusError(format!("Failed to push search result {}", e))),
+            };
+            if !request.status().is_success() {
+                let body = request.text().await.unwrap_or_default();
+                return Err(BusError(format!("Failed to push search result {}", body)));
+            }
         }
         Ok(())
     }
 
-    pub async fn push_to_manager<T>(&self, data: T, label: Label)
+    /// Fails with `CrawlerError::ManagerError`, which tells whether the result is worth sending again.
+    pub async fn push_to_manager<T>(&self, data: &T, label: Label) -> CrawlerResult<()>
     where
-        T: Serialize + Sized + Send + Sync,
+        T: Serialize + Send + Sync,
     {
-        let api = match label {
-            Label::SearchComplete => self.manager_search_api,
-            Label::ProfilesComplete => self.manager_profile_api,
-        };
-        let json_body = match serde_json::to_string(&data) {
-            Ok(json_body) => json_body,
-            Err(e) => {
-                error!("Failed to serialize data: {}", e);
-                return;
-            }
-        };
-
-        match self.client.post(api).body(json_body).send().await {
-            Ok(request) => {
-                if !request.status().is_success() {
-                    error!(
-                        "Failed to push to manager with status {} body {}",
-                        request.status().as_u16(),
-                        request.text().await.unwrap()
-                    );
-                }
-            }
-            Err(e) => {
-                error!("Failed to push to manager {:?}", e);
-            }
+        match self.manager.submit(data, label).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(CrawlerError::ManagerError(e)),
         }
     }
 }
@@ -0,0 +1,115 @@
+use crate::errors::CrawlerError::BusError;
+use crate::errors::CrawlerResult;
+use crate::queue::QueueKind;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use urlencoding::encode;
+
+/// Service Bus entities the crawler talks to, each signed with its own key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SasResource {
+    ManagerBus,
+    SearchQueue,
+    ProfileQueue,
+    ControlQueue,
+}
+
+impl From<QueueKind> for SasResource {
+    fn from(kind: QueueKind) -> Self {
+        match kind {
+            QueueKind::Search => SasResource::SearchQueue,
+            QueueKind::Profiles => SasResource::ProfileQueue,
+            QueueKind::Control => SasResource::ControlQueue,
+            QueueKind::Results => SasResource::ManagerBus,
+        }
+    }
+}
+
+pub struct SasKey {
+    pub resource_uri: String,
+    pub key_name: String,
+    pub key: String,
+}
+
+/// Shared access signature for `resource_uri` valid until `expiry`, in unix seconds.
+pub fn sign(resource_uri: &str, key_name: &str, key: &str, expiry: u64) -> CrawlerResult<String> {
+    let encoded_uri = encode(resource_uri);
+    let mut mac = match Hmac::<Sha256>::new_from_slice(key.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return Err(BusError("HMAC on SAS key failed".to_string())),
+    };
+    mac.update(format!("{}\n{}", encoded_uri, expiry).as_bytes());
+    let signature = BASE64_STANDARD.encode(mac.finalize().into_bytes());
+    Ok(format!(
+        "SharedAccessSignature sr={}&sig={}&se={}&skn={}",
+        encoded_uri,
+        encode(&signature),
+        expiry,
+        key_name
+    ))
+}
+
+struct CachedToken {
+    token: String,
+    expiry: u64,
+}
+
+/// Signs requests with the key registered for each resource and reuses a token until it is about
+/// to expire.
+pub struct SasSigner {
+    keys: HashMap<SasResource, SasKey>,
+    ttl: Duration,
+    /// A cached token is replaced once it has less than this left
+    refresh_before: Duration,
+    cache: Mutex<HashMap<SasResource, CachedToken>>,
+}
+
+impl SasSigner {
+    pub fn new(ttl: Duration, refresh_before: Duration) -> Self {
+        Self {
+            keys: HashMap::new(),
+            ttl,
+            refresh_before,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_key(mut self, resource: SasResource, key: SasKey) -> Self {
+        self.keys.insert(resource, key);
+        self
+    }
+
+    pub fn token(&self, resource: SasResource) -> CrawlerResult<String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.token_at(resource, now)
+    }
+
+    /// Token for the resource as of `now`, in unix seconds.
+    pub fn token_at(&self, resource: SasResource, now: u64) -> CrawlerResult<String> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(cached) = cache.get(&resource) {
+            if cached.expiry > now + self.refresh_before.as_secs() {
+                return Ok(cached.token.clone());
+            }
+        }
+        let key = match self.keys.get(&resource) {
+            Some(key) => key,
+            None => return Err(BusError(format!("No SAS key configured for {:?}", resource))),
+        };
+        let expiry = now + self.ttl.as_secs();
+        let token = sign(&key.resource_uri, &key.key_name, &key.key, expiry)?;
+        cache.insert(
+            resource,
+            CachedToken {
+                token: token.clone(),
+                expiry,
+            },
+        );
+        Ok(token)
+    }
+}
@@ -1,87 +1,545 @@
 use actix_web::web::get;
-use log::{debug, error, info};
-use omicron_crawler::azure::json::{CrawledProfiles, ProfileIds};
+use actix_web::{web, App, HttpServer};
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+use omicron_crawler::azure::json::{CrawledProfiles, ProfileIds, ProfileJob, SearchJob};
 use omicron_crawler::azure::{AzureClient, Label};
-use omicron_crawler::env::{get_env, load_env};
+use omicron_crawler::control::{ControlCommand, PAUSE_POLL};
+use omicron_crawler::env::{get_env, load_env, QueueBackend};
 use omicron_crawler::errors::CrawlerResult;
+use omicron_crawler::health::{self, QueueHealth, Status, StatusProvider};
 use omicron_crawler::linkedin::api::crawler::Crawler;
 use omicron_crawler::linkedin::api::json::{SearchParams, SearchResult};
-use omicron_crawler::linkedin::api::rate_limits::RateLimiter;
+use omicron_crawler::linkedin::api::rate_limits::{RateLimiter, AVG_RESPONSE_TIME_MS};
 use omicron_crawler::linkedin::api::LinkedinSession;
+use omicron_crawler::linkedin::profile::Profile;
 use omicron_crawler::logger::Logger;
+use omicron_crawler::metrics;
+use omicron_crawler::queue::dead_letter::{dead_letter, DeadLetter, DeadLetterPolicy, DeadLetterStore};
+use omicron_crawler::queue::file::FileQueue;
+use omicron_crawler::queue::outbox::{Backoff, Outbox};
+use omicron_crawler::queue::{spawn_lock_renewal, Delivery, QueueKind, Receipt, WorkQueue, LOCK_RENEW_INTERVAL};
+use omicron_crawler::validation::{Validate, ValidationErrors};
 use omicron_crawler::{fatal_assert, fatal_unwrap, fatal_unwrap_e};
+use serde::Serialize;
 use std::collections::VecDeque;
 use std::process::exit;
 use std::sync::atomic::Ordering::Relaxed;
-use std::sync::atomic::{AtomicBool, AtomicU8};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal;
+use tokio::sync::Notify;
 
-async fn obtain_profiles(params: SearchParams, crawler: &Crawler, azure_client: Arc<AzureClient>) {
-    let profiles = match crawler.search_people(params).await {
+/// Completes the message when its job succeeded, otherwise abandons it so it is delivered again.
+async fn settle<Q: WorkQueue>(queue: &Q, receipt: &Receipt, success: bool) {
+    let result = match success {
+        true => queue.complete(receipt).await,
+        false => queue.abandon(receipt).await,
+    };
+    if let Err(e) = result {
+        error!("Failed to settle {} message {}", receipt.queue, e);
+    }
+}
+
+/// Unwraps the message, dead-lettering it instead when it is malformed or has failed too many times.
+async fn accept<Q: WorkQueue, T: Serialize>(
+    delivery: Delivery<T>,
+    queue: &Q,
+    dead_letters: &DeadLetterStore,
+    policy: DeadLetterPolicy,
+) -> Option<(T, Receipt)> {
+    let receipt = delivery.receipt;
+    let (payload, reason) = match delivery.message {
+        Err(malformed) => (malformed.raw, format!("Malformed message {}", malformed.error)),
+        Ok(message) if policy.exceeded(&receipt) => (
+            serde_json::to_string(&message).unwrap_or_default(),
+            format!("Failed {} deliveries", receipt.delivery_count - 1),
+        ),
+        Ok(message) => return Some((message, receipt)),
+    };
+    if let Err(e) = dead_letter(queue, dead_letters, &receipt, payload, &reason).await {
+        error!("Failed to dead-letter {} message {}", receipt.queue, e);
+    }
+    None
+}
+
+async fn obtain_profiles<Q: WorkQueue + 'static>(job: SearchJob, receipt: Receipt, worker: &Worker<Q>) {
+    let queue = worker.queue.as_ref();
+    let renewal = spawn_lock_renewal(worker.queue.clone(), receipt.clone(), LOCK_RENEW_INTERVAL);
+    info!("Searching for job {}", job.job_id);
+    let result = worker.crawler.search_people(job.payload.clone(), Some(&SHUTDOWN_SIGNAL)).await;
+    renewal.abort();
+    let profiles = match result {
         Ok(profiles) => profiles,
         Err(e) => {
             error!("Failed to search people {}", e);
+            settle(queue, &receipt, false).await;
             return;
         }
     };
-    //TODO Retry in case of failure
-    tokio::spawn(async move {
+    let resume_page = profiles.resume_page;
+    let mut success = true;
+    if !profiles.elements.is_empty() || resume_page.is_none() {
         info!("Pushing {} search result to manager", profiles.elements.len());
-        azure_client.push_to_manager(&profiles, Label::SearchComplete).await;
-    });
+        // Once journaled the outbox owns delivery to the manager and the job is done
+        if let Err(e) = worker.outbox.enqueue(&job.reply(profiles), Label::SearchComplete).await {
+            error!("Failed to journal search result {}", e);
+            success = false;
+        }
+    }
+
+    if let (true, Some(page)) = (success, resume_page) {
+        let remaining = job.map(|params| SearchParams { page, ..params });
+        info!(
+            "Pushing unfinished search from page {} to {} to queue...",
+            page, remaining.payload.end
+        );
+        match queue.requeue_search(&remaining).await {
+            Ok(_) => {
+                worker.handed_back.searches.fetch_add(1, Relaxed);
+            }
+            Err(e) => {
+                error!("Failed to push search to queue! {}", e);
+                success = false;
+            }
+        }
+    }
+    settle(queue, &receipt, success).await;
 }
 
-async fn crawl_profile(mut ids: ProfileIds, crawler: &mut Crawler, azure_client: Arc<AzureClient>) {
-    info!("Crawling {} profiles", ids.ids.len());
+async fn crawl_profile<Q: WorkQueue + 'static>(mut job: ProfileJob, receipt: Receipt, worker: &Worker<Q>) {
+    let queue = worker.queue.as_ref();
+    info!(
+        "Crawling {} profiles for job {}, delivery {}",
+        job.payload.ids.len(),
+        job.job_id,
+        receipt.delivery_count
+    );
+    let renewal = spawn_lock_renewal(worker.queue.clone(), receipt.clone(), LOCK_RENEW_INTERVAL);
     const PROFILES_PER_REQUEST: usize = 10;
-    let chunks = ids.ids.chunks(PROFILES_PER_REQUEST);
-    let request_metadata = ids.request_metadata.take();
-    let mut current_profile = 0;
-    for chunk in chunks {
-        let crawled_profiles = match crawler.profiles(chunk, Some(&SHUTDOWN_SIGNAL)).await {
-            Ok(profiles) => profiles,
-            Err(e) => {
-                error!("Failed to crawl profiles {}", e);
-                return;
-            }
-        };
-        current_profile += crawled_profiles.len();
-        let metadata = request_metadata.clone();
-        let azure_client_clone = azure_client.clone();
-        tokio::task::spawn(async move {
-            info!("Pushing {} profiles to manager", crawled_profiles.len());
-            let crawled_profiles = CrawledProfiles {
-                profiles: crawled_profiles,
-                request_metadata: metadata,
+    let request_metadata = job.payload.request_metadata.take();
+    let ids = &job.payload.ids;
+    // Ids whose profiles are journaled, or that were tried and failed
+    let mut done = 0;
+    let mut failed = Vec::new();
+    let mut journaled = true;
+    while done < ids.len() && journaled && !SHUTDOWN_SIGNAL.load(Relaxed) {
+        let chunk = &ids[done..ids.len().min(done + PROFILES_PER_REQUEST)];
+        let mut crawled_profiles = Vec::with_capacity(chunk.len());
+        let mut attempted = 0;
+        for id in chunk {
+            let batch = match worker.crawler.profiles(std::slice::from_ref(id), Some(&SHUTDOWN_SIGNAL)).await {
+                Ok(batch) => batch,
+                Err(e) => {
+                    error!("Failed to crawl profiles {}", e);
+                    break;
+                }
             };
-            //TODO Retry in case of failure
-            azure_client_clone.push_to_manager(crawled_profiles, Label::ProfilesComplete).await
+            // Interrupted before the crawl started, the id is handed back with the rest
+            if batch.profiles.is_empty() && batch.failed.is_empty() && SHUTDOWN_SIGNAL.load(Relaxed) {
+                break;
+            }
+            attempted += 1;
+            crawled_profiles.extend(batch.profiles);
+            failed.extend(batch.failed);
+        }
+        if attempted == 0 {
+            break;
+        }
+        info!("Pushing {} profiles to manager", crawled_profiles.len());
+        let crawled_profiles = job.reply(CrawledProfiles {
+            profiles: crawled_profiles.into_iter().map(Profile::from).collect(),
+            request_metadata: request_metadata.clone(),
         });
-        if SHUTDOWN_SIGNAL.load(Relaxed) == true {
+        match worker.outbox.enqueue(&crawled_profiles, Label::ProfilesComplete).await {
+            Ok(_) => done += attempted,
+            Err(e) => {
+                error!("Failed to journal profiles {}", e);
+                journaled = false;
+            }
+        }
+        if attempted < chunk.len() {
             break;
         }
     }
 
-    if SHUTDOWN_SIGNAL.load(Relaxed) == true {
-        let remaining_profiles: Vec<String> = ids.ids.iter().skip(current_profile).map(|p| p.to_string()).collect();
+    // Profiles already journaled are not crawled again, only the rest goes back on the queue
+    let mut success = failed.is_empty() || dead_letter_failed_ids(&job, &failed, request_metadata.clone(), &receipt, worker).await;
+    if done < ids.len() {
+        let total = ids.len();
+        let remaining = job.map(|ids| ProfileIds {
+            ids: ids.ids.into_iter().skip(done).collect(),
+            request_metadata,
+        });
         info!(
             "Pushing {} unfinished profiles from {} to queue...",
-            remaining_profiles.len(),
-            ids.ids.len()
+            remaining.payload.ids.len(),
+            total
         );
-        let profile_ids = ProfileIds {
-            ids: remaining_profiles,
-            request_metadata,
-        };
-        if let Err(e) = azure_client.push_to_queue(&profile_ids).await {
-            error!("Failed to push profiles to queue! {}", e);
+        match queue.requeue_profiles(&remaining).await {
+            Ok(_) if SHUTDOWN_SIGNAL.load(Relaxed) => {
+                worker.handed_back.profile_ids.fetch_add(remaining.payload.ids.len(), Relaxed);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to push profiles to queue! {}", e);
+                success = false;
+            }
+        }
+    }
+
+    renewal.abort();
+    settle(queue, &receipt, success).await;
+}
+
+/// Keeps the ids that failed to crawl in the dead-letter store as a job of their own, ready to be
+/// replayed, rather than completing the message as if they had been crawled.
+async fn dead_letter_failed_ids<Q: WorkQueue>(
+    job: &ProfileJob,
+    failed: &[(String, String)],
+    request_metadata: Option<String>,
+    receipt: &Receipt,
+    worker: &Worker<Q>,
+) -> bool {
+    let failed_job = job.reply(ProfileIds {
+        ids: failed.iter().map(|(id, _)| id.clone()).collect(),
+        request_metadata,
+    });
+    let reasons: Vec<String> = failed.iter().map(|(id, reason)| format!("{} {}", id, reason)).collect();
+    let reason = format!("Failed to crawl {} profiles: {}", failed.len(), reasons.join(", "));
+    let letter = DeadLetter::new(receipt, serde_json::to_string(&failed_job).unwrap_or_default(), &reason);
+    match worker.dead_letters.store(&letter).await {
+        Ok(_) => {
+            warn!("Dead-lettered {} profiles of job {} as {}", failed.len(), job.job_id, letter.id);
+            true
+        }
+        Err(e) => {
+            error!("Failed to dead-letter profiles of job {} {}", job.job_id, e);
+            false
         }
     }
 }
 
+/// Work given back to the queues during shutdown, reported on exit.
+#[derive(Default)]
+struct HandedBack {
+    searches: AtomicUsize,
+    profile_ids: AtomicUsize,
+    /// Messages received after shutdown was requested
+    abandoned: AtomicUsize,
+}
+
+/// Everything the per-queue tasks share.
+struct Worker<Q: WorkQueue> {
+    queue: Arc<Q>,
+    crawler: Crawler,
+    outbox: Arc<Outbox<Q>>,
+    dead_letters: DeadLetterStore,
+    policy: DeadLetterPolicy,
+    receive_wait: Duration,
+    handed_back: HandedBack,
+    started_at: DateTime<Utc>,
+    /// Messages being processed
+    in_flight: AtomicUsize,
+    queue_health: QueueHealth,
+}
+
+impl<Q: WorkQueue + 'static> StatusProvider for Worker<Q> {
+    fn status(&self) -> Status {
+        Status::new(
+            "bus_worker",
+            self.started_at,
+            &self.crawler,
+            self.in_flight.load(Relaxed),
+            Some(self.queue_health.status()),
+        )
+    }
+}
+
+/// Serves the health, status and metrics endpoints, without signal handling so Ctrl+C stays with the worker.
+async fn spawn_status_server<Q: WorkQueue + 'static>(worker: Arc<Worker<Q>>) -> Option<actix_web::dev::ServerHandle> {
+    let env = get_env().await;
+    let status: Arc<dyn StatusProvider> = worker;
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::from(status.clone()))
+            .configure(health::configure)
+            .configure(metrics::configure)
+    })
+    .workers(1)
+    .disable_signals()
+    .bind((env.host.as_str(), env.status_port));
+    let server = match server {
+        Ok(server) => server.run(),
+        Err(e) => {
+            error!("Failed to bind status server to port {} {}", env.status_port, e);
+            return None;
+        }
+    };
+    info!("Serving status on {}:{}", env.host, env.status_port);
+    let handle = server.handle();
+    tokio::spawn(server);
+    Some(handle)
+}
+
+/// Pause between empty receives, on top of the long-poll wait
+const IDLE_BACKOFF: Backoff = Backoff {
+    initial: Duration::from_secs(1),
+    max: Duration::from_secs(60),
+};
+
+/// Sleeps unless shutdown is requested first, returns whether the worker should keep going.
+async fn idle(duration: Duration) -> bool {
+    let shutdown = SHUTDOWN.notified();
+    tokio::pin!(shutdown);
+    shutdown.as_mut().enable();
+    if SHUTDOWN_SIGNAL.load(Relaxed) {
+        return false;
+    }
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => true,
+        _ = shutdown => false,
+    }
+}
+
+/// A receive is never cancelled, an Azure receive may already hold a lock and the file queue is not
+/// cancel safe. Anything received after shutdown was requested goes straight back to the queue.
+async fn received_during_shutdown<Q: WorkQueue>(worker: &Worker<Q>, receipt: &Receipt) -> bool {
+    if !SHUTDOWN_SIGNAL.load(Relaxed) {
+        return false;
+    }
+    settle(worker.queue.as_ref(), receipt, false).await;
+    worker.handed_back.abandoned.fetch_add(1, Relaxed);
+    true
+}
+
+async fn search_loop<Q: WorkQueue + 'static>(worker: Arc<Worker<Q>>) {
+    let mut empty_receives = 0;
+    while !SHUTDOWN_SIGNAL.load(Relaxed) {
+        if !worker.crawler.control_state().is_active() {
+            // Messages stay in the queue for other workers while this one is paused
+            if !idle(PAUSE_POLL).await {
+                break;
+            }
+            continue;
+        }
+        let received = worker.queue.dequeue_search(worker.receive_wait).await;
+        metrics::queue_receive(QueueKind::Search, &received);
+        match &received {
+            Ok(_) => worker.queue_health.received(),
+            Err(e) => worker.queue_health.failed(e.to_string()),
+        }
+        match received {
+            Ok(Some(delivery)) => {
+                empty_receives = 0;
+                if received_during_shutdown(&worker, &delivery.receipt).await {
+                    break;
+                }
+                if let Some((job, receipt)) = accept(delivery, worker.queue.as_ref(), &worker.dead_letters, worker.policy).await {
+                    worker.in_flight.fetch_add(1, Relaxed);
+                    obtain_profiles(job, receipt, &worker).await;
+                    worker.in_flight.fetch_sub(1, Relaxed);
+                }
+            }
+            Ok(None) => {
+                empty_receives += 1;
+                debug!("Search queue is empty");
+            }
+            Err(e) => {
+                empty_receives += 1;
+                error!("Failed to dequeue search! {}", e);
+            }
+        }
+        if empty_receives > 0 && !idle(IDLE_BACKOFF.delay(empty_receives)).await {
+            break;
+        }
+    }
+    info!("Search worker stopped");
+}
+
+async fn profile_loop<Q: WorkQueue + 'static>(worker: Arc<Worker<Q>>) {
+    let mut empty_receives = 0;
+    while !SHUTDOWN_SIGNAL.load(Relaxed) {
+        if !worker.crawler.control_state().is_active() {
+            // Messages stay in the queue for other workers while this one is paused
+            if !idle(PAUSE_POLL).await {
+                break;
+            }
+            continue;
+        }
+        let received = worker.queue.dequeue_profiles(worker.receive_wait).await;
+        metrics::queue_receive(QueueKind::Profiles, &received);
+        match &received {
+            Ok(_) => worker.queue_health.received(),
+            Err(e) => worker.queue_health.failed(e.to_string()),
+        }
+        match received {
+            Ok(Some(delivery)) => {
+                empty_receives = 0;
+                if received_during_shutdown(&worker, &delivery.receipt).await {
+                    break;
+                }
+                if let Some((job, receipt)) = accept(delivery, worker.queue.as_ref(), &worker.dead_letters, worker.policy).await {
+                    worker.in_flight.fetch_add(1, Relaxed);
+                    crawl_profile(job, receipt, &worker).await;
+                    worker.in_flight.fetch_sub(1, Relaxed);
+                }
+            }
+            Ok(None) => {
+                empty_receives += 1;
+                debug!("Profile queue is empty");
+            }
+            Err(e) => {
+                empty_receives += 1;
+                error!("Failed to dequeue profile! {}", e);
+            }
+        }
+        if empty_receives > 0 && !idle(IDLE_BACKOFF.delay(empty_receives)).await {
+            break;
+        }
+    }
+    info!("Profile worker stopped");
+}
+
+/// Commands for an account another worker logs in with are completed without applying them. Invalid
+/// commands cannot succeed on another delivery and are dead-lettered right away, any other failure is
+/// abandoned and retried until the dead-letter policy gives up on it.
+async fn apply_control<Q: WorkQueue>(command: ControlCommand, receipt: Receipt, worker: &Worker<Q>) {
+    let queue = worker.queue.as_ref();
+    let name = command.name();
+    let own_account = worker.crawler.control_state().account;
+    if let Some(account) = command.account().filter(|account| *account != own_account) {
+        info!("Skipping {} for account {}, another worker logs in with it", name, account);
+        settle(queue, &receipt, true).await;
+        return;
+    }
+    let errors = command.validate();
+    if !errors.is_empty() {
+        let reason = ValidationErrors { errors }.to_string();
+        error!("Dead-lettering {} from control queue {}", name, reason);
+        let payload = serde_json::to_string(&command).unwrap_or_default();
+        if let Err(e) = dead_letter(queue, &worker.dead_letters, &receipt, payload, &reason).await {
+            error!("Failed to dead-letter {} message {}", receipt.queue, e);
+        }
+        return;
+    }
+    let success = match worker.crawler.apply(command).await {
+        Ok(state) => {
+            info!("Applied {} from control queue, now {:?}", name, state);
+            true
+        }
+        Err(e) => {
+            error!("Failed to apply {} from control queue {}", name, e);
+            false
+        }
+    };
+    settle(queue, &receipt, success).await;
+}
+
+/// Applies commands from the control queue to the crawler.
+async fn control_loop<Q: WorkQueue + 'static>(worker: Arc<Worker<Q>>) {
+    while !SHUTDOWN_SIGNAL.load(Relaxed) {
+        let received = worker.queue.dequeue_control(worker.receive_wait).await;
+        metrics::queue_receive(QueueKind::Control, &received);
+        match &received {
+            Ok(_) => worker.queue_health.received(),
+            Err(e) => worker.queue_health.failed(e.to_string()),
+        }
+        match received {
+            Ok(Some(delivery)) => {
+                if received_during_shutdown(&worker, &delivery.receipt).await {
+                    break;
+                }
+                if let Some((command, receipt)) = accept(delivery, worker.queue.as_ref(), &worker.dead_letters, worker.policy).await {
+                    apply_control(command, receipt, &worker).await;
+                }
+            }
+            Ok(None) => debug!("Control queue is empty"),
+            Err(e) => {
+                error!("Failed to dequeue control message! {}", e);
+                if !idle(IDLE_BACKOFF.initial).await {
+                    break;
+                }
+            }
+        }
+    }
+    info!("Control worker stopped");
+}
+
+async fn run_worker<Q: WorkQueue + 'static>(queue: Arc<Q>, crawler: Crawler) {
+    let env = get_env().await;
+    let open_dead_letters = || DeadLetterStore::open(&env.dead_letter_dir);
+    let outbox_policy = DeadLetterPolicy {
+        max_attempts: env.outbox_max_attempts,
+    };
+    let outbox_dead_letters = fatal_unwrap_e!(open_dead_letters().await, "Failed to open dead-letter store {}");
+    let outbox = Outbox::open(
+        &env.outbox_dir,
+        queue.clone(),
+        outbox_dead_letters,
+        outbox_policy,
+        Backoff::default(),
+    )
+    .await;
+    let outbox = Arc::new(fatal_unwrap_e!(outbox, "Failed to open outbox {}"));
+    match outbox.pending().await {
+        Ok(pending) if !pending.is_empty() => info!("Resending {} results left in outbox", pending.len()),
+        Ok(_) => {}
+        Err(e) => error!("Failed to read outbox {}", e),
+    }
+    outbox.clone().spawn();
+    let dead_letters = fatal_unwrap_e!(open_dead_letters().await, "Failed to open dead-letter store {}");
+    let worker = Arc::new(Worker {
+        queue,
+        crawler,
+        outbox,
+        dead_letters,
+        policy: DeadLetterPolicy {
+            max_attempts: get_env().await.max_delivery_attempts,
+        },
+        receive_wait: get_env().await.receive_wait,
+        handed_back: HandedBack::default(),
+        started_at: Utc::now(),
+        in_flight: AtomicUsize::new(0),
+        queue_health: QueueHealth::new(),
+    });
+    let status_server = spawn_status_server(worker.clone()).await;
+
+    // Each queue gets its own task so a long profile crawl does not hold up searches
+    let searches = tokio::spawn(search_loop(worker.clone()));
+    let profiles = tokio::spawn(profile_loop(worker.clone()));
+    let control = tokio::spawn(control_loop(worker.clone()));
+    for (name, task) in [("Search", searches), ("Profile", profiles), ("Control", control)] {
+        if let Err(e) = task.await {
+            error!("{} worker failed {}", name, e);
+        }
+    }
+
+    // Results of the interrupted jobs are journaled, give the manager a chance to receive them
+    let shutdown_timeout = get_env().await.shutdown_timeout;
+    info!("Flushing outbox, waiting up to {}s", shutdown_timeout.as_secs());
+    let unsent = match worker.outbox.flush(shutdown_timeout).await {
+        Ok(unsent) => unsent.to_string(),
+        Err(e) => {
+            error!("Failed to flush outbox {}", e);
+            "unknown".to_string()
+        }
+    };
+    info!(
+        "Shutdown complete: requeued {} searches and {} profile ids, abandoned {} messages, {} results left in outbox",
+        worker.handed_back.searches.load(Relaxed),
+        worker.handed_back.profile_ids.load(Relaxed),
+        worker.handed_back.abandoned.load(Relaxed),
+        unsent
+    );
+    if let Some(status_server) = status_server {
+        status_server.stop(true).await;
+    }
+}
+
 static SHUTDOWN_SIGNAL: AtomicBool = AtomicBool::new(false);
+static SHUTDOWN: Notify = Notify::const_new();
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> std::io::Result<()> {
     load_env();
@@ -89,41 +547,29 @@ async fn main() -> std::io::Result<()> {
     Logger::init(env.log_level);
     let username = get_env().await.linkedin_username.as_str();
     let password = get_env().await.linkedin_password.as_str();
-    let mut crawler = Crawler::new(RateLimiter::new(100, 800), username, password).await;
-    let azure_client = Arc::new(AzureClient::new().await);
+    let crawler = Crawler::new(RateLimiter::new(env.profiles_per_hour, AVG_RESPONSE_TIME_MS), username, password).await;
 
     // Spawn CTRL+C handler task
     tokio::spawn(async move {
         fatal_unwrap_e!(signal::ctrl_c().await, "Failed to listen for CTRL+C {}");
         info!("Received CTRL+C, initiating shutdown...");
         SHUTDOWN_SIGNAL.store(true, Relaxed);
+        SHUTDOWN.notify_waiters();
     });
 
-    loop {
-        let azure_client_clone = azure_client.clone();
-        match azure_client_clone.dequeue_search().await {
-            Ok(search_params) => match search_params {
-                /// TODO log search params
-                Some(search_params) => obtain_profiles(search_params, &crawler, azure_client_clone).await,
-                None => debug!("Search queue is empty"),
-            },
-            Err(e) => {
-                error!("Failed to dequeue search! {}", e);
-            }
-        }
-
-        let azure_client_clone = azure_client.clone();
-        match azure_client_clone.dequeue_profile().await {
-            Ok(profiles) => match profiles {
-                Some(profiles) => crawl_profile(profiles, &mut crawler, azure_client_clone).await,
-                None => debug!("profile queue is empty"),
-            },
-            Err(e) => {
-                error!("Failed to dequeue profile! {}", e);
+    match env.queue_backend {
+        QueueBackend::Azure => run_worker(Arc::new(AzureClient::new().await), crawler).await,
+        QueueBackend::File => {
+            info!("Using file queue in {}", env.queue_dir);
+            let mut queue = fatal_unwrap_e!(FileQueue::new(&env.queue_dir).await, "Failed to open file queue {}");
+            if !env.control_subscription.is_empty() {
+                info!("Receiving control commands as {}", env.control_subscription);
+                queue = fatal_unwrap_e!(
+                    queue.subscribe(&env.control_subscription).await,
+                    "Failed to subscribe to control commands {}"
+                );
             }
-        }
-        if SHUTDOWN_SIGNAL.load(Relaxed) {
-            break;
+            run_worker(Arc::new(queue), crawler).await
         }
     }
     Ok(())
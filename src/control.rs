@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// How often paused work checks whether it may continue
+pub const PAUSE_POLL: Duration = Duration::from_secs(1);
+
+/// Changes to a running crawler, sent to the server's admin endpoints or to the bus worker's control
+/// queue as `{"command": "set_budget", "profiles_per_hour": 50}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// Stops before the next profile or search, work in progress waits instead of failing
+    Pause,
+    Resume,
+    SetBudget {
+        profiles_per_hour: u32,
+    },
+    /// Stops using the LinkedIn account until it is enabled again
+    DisableAccount {
+        account: String,
+    },
+    EnableAccount {
+        account: String,
+    },
+    /// Logs in again with the configured credentials, replacing the saved cookies
+    Reauthenticate,
+}
+
+impl ControlCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ControlCommand::Pause => "pause",
+            ControlCommand::Resume => "resume",
+            ControlCommand::SetBudget { .. } => "set_budget",
+            ControlCommand::DisableAccount { .. } => "disable_account",
+            ControlCommand::EnableAccount { .. } => "enable_account",
+            ControlCommand::Reauthenticate => "reauthenticate",
+        }
+    }
+
+    /// LinkedIn account the command is meant for, other commands apply to every crawler.
+    pub fn account(&self) -> Option<&str> {
+        match self {
+            ControlCommand::DisableAccount { account } | ControlCommand::EnableAccount { account } => Some(account),
+            _ => None,
+        }
+    }
+}
+
+/// Body of `PUT /admin/budget`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+pub struct Budget {
+    pub profiles_per_hour: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct ControlState {
+    pub paused: bool,
+    pub profiles_per_hour: u32,
+    /// LinkedIn account the crawler logs in with
+    pub account: String,
+    pub account_disabled: bool,
+}
+
+impl ControlState {
+    /// Whether the crawler may talk to LinkedIn.
+    pub fn is_active(&self) -> bool {
+        !self.paused && !self.account_disabled
+    }
+}
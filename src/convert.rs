@@ -0,0 +1,155 @@
+use crate::azure::json::ProfileIds;
+use crate::errors::CrawlerError::{ParseError, QueueError};
+use crate::errors::CrawlerResult;
+use crate::linkedin::api::json::SearchItem;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::io::AsyncWriteExt;
+
+/// Search results as written by the API search (`{"elements": [...]}`, one page or an array of them),
+/// by the web driver search (`[{"name", "title", "url"}]`) or by the old convert script input
+/// (`[{"sales_url"}]`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SearchDump {
+    Api(ApiSearch),
+    ApiPages(Vec<ApiSearch>),
+    WebDriver(Vec<WebDriverItem>),
+}
+
+#[derive(Deserialize)]
+struct ApiSearch {
+    elements: Vec<SearchItem>,
+    request_metadata: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WebDriverItem {
+    #[serde(alias = "sales_url")]
+    url: String,
+}
+
+/// Profile ids found in a search dump, in the order the search returned them.
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchIds {
+    pub ids: Vec<String>,
+    /// Request metadata of the first API search page that had one
+    pub request_metadata: Option<String>,
+    /// Sales Navigator lead urls, their ids are not profile ids the API crawler accepts
+    pub sales_urls: Vec<String>,
+    /// Web driver urls no profile id could be read from
+    pub unrecognized: Vec<String>,
+}
+
+pub fn read_search_dump(input: &str) -> CrawlerResult<SearchIds> {
+    let dump: SearchDump = serde_json::from_str(input)
+        .map_err(|_| ParseError("Expected an API search result, an array of them or an array of web driver search results".to_string()))?;
+    let mut found = SearchIds::default();
+    let pages = match dump {
+        SearchDump::Api(search) => vec![search],
+        SearchDump::ApiPages(pages) => pages,
+        SearchDump::WebDriver(items) => {
+            for item in items {
+                match profile_id_from_url(&item.url) {
+                    Some(id) => found.ids.push(id),
+                    None if is_sales_url(&item.url) => found.sales_urls.push(item.url),
+                    None => found.unrecognized.push(item.url),
+                }
+            }
+            return Ok(found);
+        }
+    };
+    for page in pages {
+        found.request_metadata = found.request_metadata.or(page.request_metadata);
+        found.ids.extend(page.elements.into_iter().map(|item| item.profile_urn));
+    }
+    Ok(found)
+}
+
+/// Id the API crawler accepts, from a public profile url (`/in/<id>`).
+pub fn profile_id_from_url(url: &str) -> Option<String> {
+    static PROFILE_URL: OnceLock<Regex> = OnceLock::new();
+    let regex = PROFILE_URL.get_or_init(|| Regex::new(r"linkedin\.com/in/([^/,?#]+)").unwrap());
+    regex.captures(url).map(|capture| capture[1].to_string())
+}
+
+/// Sales Navigator lead url (`/sales/lead/<id>,NAME_SEARCH,<x>`), only the web driver can open it.
+pub fn is_sales_url(url: &str) -> bool {
+    url.contains("linkedin.com/sales/lead/") || url.contains("linkedin.com/sales/people/")
+}
+
+/// Drops repeated ids, keeping the first occurrence.
+pub fn dedupe(ids: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::with_capacity(ids.len());
+    ids.into_iter().filter(|id| seen.insert(id.clone())).collect()
+}
+
+/// Splits ids into profile jobs of at most `chunk_size` ids, each carrying the request metadata.
+pub fn batches(ids: &[String], chunk_size: usize, request_metadata: Option<&str>) -> Vec<ProfileIds> {
+    ids.chunks(chunk_size.max(1))
+        .map(|chunk| ProfileIds {
+            ids: chunk.to_vec(),
+            request_metadata: request_metadata.map(str::to_string),
+        })
+        .collect()
+}
+
+/// Ids already pushed as profile jobs, kept one per line in a local file so the same person found by
+/// overlapping searches is only crawled once.
+pub struct SeenProfiles {
+    path: PathBuf,
+    ids: HashSet<String>,
+}
+
+impl SeenProfiles {
+    pub async fn open(path: impl AsRef<Path>) -> CrawlerResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let ids = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content
+                .lines()
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string)
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(QueueError(format!("Failed to read seen profiles {} {}", path.display(), e))),
+        };
+        Ok(Self { path, ids })
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Ids not seen before, in their original order.
+    pub fn unseen(&self, ids: Vec<String>) -> Vec<String> {
+        ids.into_iter().filter(|id| !self.contains(id)).collect()
+    }
+
+    /// Appends the ids to the store, ids already in it are not written again.
+    pub async fn record(&mut self, ids: &[String]) -> CrawlerResult<()> {
+        let mut lines = String::new();
+        for id in ids {
+            if self.ids.insert(id.clone()) {
+                lines.push_str(id);
+                lines.push('\n');
+            }
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let file = tokio::fs::OpenOptions::new().create(true).append(true).open(&self.path).await;
+        // Tokio finishes a write in the background, flushing waits for it to reach the file
+        let result = match file {
+            Ok(mut file) => match file.write_all(lines.as_bytes()).await {
+                Ok(()) => file.flush().await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| QueueError(format!("Failed to write seen profiles {} {}", self.path.display(), e)))
+    }
+}
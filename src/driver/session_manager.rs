@@ -1,6 +1,7 @@
 use crate::driver::session::DriverSession;
 use crate::driver::traits::{BrowserConfig, DriverService, SessionInitializer};
 use crate::env::get_env;
+use crate::metrics::webdriver_sessions;
 use crossbeam::queue::ArrayQueue;
 use crossbeam::thread;
 use fs_extra::dir::CopyOptions;
@@ -51,12 +52,14 @@ impl SessionPool {
             sessions_available_signal_lock: Mutex::new(()),
             available_sessions,
         };
+        session_pool.update_metrics();
         session_pool
     }
     pub fn acquire(&self) -> Option<SessionProxy> {
         match self.available_sessions.pop() {
             Some(session) => Some({
                 info!("Acquiring session, {} available", self.available_sessions.len());
+                self.update_metrics();
                 SessionProxy::new(session, &self)
             }),
             None => None,
@@ -76,6 +79,11 @@ impl SessionPool {
             self.sessions_available_signal.notify_all();
         }
         info!("Releasing session, {} available", self.available_sessions.len());
+        self.update_metrics();
+    }
+
+    fn update_metrics(&self) {
+        webdriver_sessions(self.available_sessions.len(), self.available_sessions.capacity());
     }
 }
 
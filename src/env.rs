@@ -1,8 +1,10 @@
+use crate::auth::{parse_api_keys, ApiKey};
 use crate::env::Browser::{Chrome, Firefox};
 use crate::linkedin::web_driver::profiles::Profile;
 use log::Level;
 use serde::de::Unexpected::Str;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
 use thirtyfour::session;
 use tokio::sync::OnceCell;
 
@@ -20,6 +22,21 @@ impl Display for Browser {
         }
     }
 }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QueueBackend {
+    Azure,
+    File,
+}
+
+/// How results are authenticated towards the manager API.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ManagerAuth {
+    None,
+    Bearer(String),
+    /// Shared secret for signing each request body
+    Hmac(String),
+}
+
 pub struct Env {
     pub log_level: log::LevelFilter,
     pub port: u16,
@@ -33,19 +50,40 @@ pub struct Env {
     pub driver_session_count: u16,
     pub azure_search_uri: String,
     pub azure_search_dequeue_api: String,
+    pub azure_search_queue_api: String,
     pub azure_profile_uri: String,
     pub azure_profile_dequeue_api: String,
     pub azure_profile_queue_api: String,
+    pub azure_control_uri: String,
+    pub azure_control_dequeue_api: String,
+    pub azure_control_queue_api: String,
     pub azure_manager_bus_uri: String,
     pub azure_manager_bus_api: String,
     pub azure_sas_key_name: String,
     pub azure_manager_bus_key: String,
     pub azure_sas_profile_key: String,
     pub azure_sas_search_key: String,
+    pub azure_sas_control_key: String,
     pub manager_search_api: String,
     pub manager_profile_api: String,
+    pub manager_auth: ManagerAuth,
     pub linkedin_username: String,
     pub linkedin_password: String,
+    pub queue_backend: QueueBackend,
+    pub queue_dir: String,
+    pub control_subscription: String,
+    pub outbox_dir: String,
+    pub dead_letter_dir: String,
+    pub seen_profiles_path: String,
+    pub max_delivery_attempts: u32,
+    pub outbox_max_attempts: u32,
+    pub receive_wait: Duration,
+    pub shutdown_timeout: Duration,
+    pub job_retention: Duration,
+    pub profiles_per_hour: u32,
+    pub api_keys: Vec<ApiKey>,
+    pub status_port: u16,
+    pub admin_clients: Vec<String>,
 }
 
 static ENV: OnceCell<Env> = OnceCell::const_new();
@@ -72,6 +110,9 @@ pub fn env_azure_search_uri() -> String {
 pub fn env_azure_search_dequeue_api() -> String {
     std::env::var("AZURE_SEARCH_DEQUEUE_API").unwrap_or_else(|_| "".to_string())
 }
+pub fn env_azure_search_queue_api() -> String {
+    std::env::var("AZURE_SEARCH_QUEUE_API").unwrap_or_else(|_| "".to_string())
+}
 pub fn env_azure_profile_uri() -> String {
     std::env::var("AZURE_PROFILE_URI").unwrap_or_else(|_| "".to_string())
 }
@@ -81,6 +122,18 @@ pub fn env_azure_profile_dequeue_api() -> String {
 pub fn env_azure_profile_queue_api() -> String {
     std::env::var("AZURE_PROFILE_QUEUE_API").unwrap_or_else(|_| "".to_string())
 }
+pub fn env_azure_control_uri() -> String {
+    std::env::var("AZURE_CONTROL_URI").unwrap_or_else(|_| "".to_string())
+}
+/// Without it the bus worker does not read control messages. A queue hands each command to one worker
+/// only, with several workers send commands to a topic and point this at the worker's own subscription,
+/// `<topic>/subscriptions/<worker>/messages/head`.
+pub fn env_azure_control_dequeue_api() -> String {
+    std::env::var("AZURE_CONTROL_DEQUEUE_API").unwrap_or_else(|_| "".to_string())
+}
+pub fn env_azure_control_queue_api() -> String {
+    std::env::var("AZURE_CONTROL_QUEUE_API").unwrap_or_else(|_| "".to_string())
+}
 
 pub fn env_azure_manager_bus_uri() -> String {
     std::env::var("AZURE_MANAGER_BUS_URI").unwrap_or_else(|_| "".to_string())
@@ -106,6 +159,10 @@ pub fn env_azure_sas_search_key() -> String {
     std::env::var("AZURE_SAS_SEARCH_KEY").unwrap_or_else(|_| "".to_string())
 }
 
+pub fn env_azure_sas_control_key() -> String {
+    std::env::var("AZURE_SAS_CONTROL_KEY").unwrap_or_else(|_| "".to_string())
+}
+
 pub fn env_linkedin_username() -> String {
     std::env::var("LINKEDIN_USERNAME").unwrap_or_else(|_| "".to_string())
 }
@@ -113,6 +170,99 @@ pub fn env_linkedin_password() -> String {
     std::env::var("LINKEDIN_PASSWORD").unwrap_or_else(|_| "".to_string())
 }
 
+/// Read when the taxonomy is first used rather than through `Env`, profiles are converted outside of
+/// the servers too
+pub fn env_skill_taxonomy_path() -> Option<String> {
+    std::env::var("SKILL_TAXONOMY_PATH").ok()
+}
+
+pub fn env_queue_backend() -> QueueBackend {
+    match std::env::var("QUEUE_BACKEND") {
+        Ok(backend) => match backend.as_str() {
+            "azure" => QueueBackend::Azure,
+            "file" => QueueBackend::File,
+            _ => {
+                warn!("Invalid queue backend {}, defaulting to azure", backend);
+                QueueBackend::Azure
+            }
+        },
+        Err(_) => QueueBackend::Azure,
+    }
+}
+
+pub fn env_queue_dir() -> String {
+    std::env::var("QUEUE_DIR").unwrap_or_else(|_| "./queue/".to_string())
+}
+
+/// Name the bus worker receives control commands under, so each worker sharing a file queue gets every
+/// command. Without it the workers take turns on one control queue.
+pub fn env_control_subscription() -> String {
+    std::env::var("CONTROL_SUBSCRIPTION").unwrap_or_default()
+}
+
+pub fn env_outbox_dir() -> String {
+    std::env::var("OUTBOX_DIR").unwrap_or_else(|_| "./outbox/".to_string())
+}
+
+pub fn env_dead_letter_dir() -> String {
+    std::env::var("DEAD_LETTER_DIR").unwrap_or_else(|_| "./dead_letters/".to_string())
+}
+
+/// Profile ids already converted into profile jobs, one per line
+pub fn env_seen_profiles_path() -> String {
+    std::env::var("SEEN_PROFILES_PATH").unwrap_or_else(|_| "./seen_profiles.txt".to_string())
+}
+
+pub fn env_max_delivery_attempts() -> u32 {
+    let attempts = std::env::var("MAX_DELIVERY_ATTEMPTS").unwrap_or_else(|_| "5".to_string());
+    fatal_unwrap_e!(attempts.parse(), "Failed to parse MAX_DELIVERY_ATTEMPTS {}")
+}
+
+/// Publish attempts before a result is moved to the dead-letter store, high enough to ride out a manager outage
+pub fn env_outbox_max_attempts() -> u32 {
+    let attempts = std::env::var("OUTBOX_MAX_ATTEMPTS").unwrap_or_else(|_| "100".to_string());
+    fatal_unwrap_e!(attempts.parse(), "Failed to parse OUTBOX_MAX_ATTEMPTS {}")
+}
+
+pub fn env_receive_wait() -> Duration {
+    let secs = std::env::var("RECEIVE_WAIT_SECS").unwrap_or_else(|_| "30".to_string());
+    Duration::from_secs(fatal_unwrap_e!(secs.parse(), "Failed to parse RECEIVE_WAIT_SECS {}"))
+}
+
+/// How long finished API jobs and their results stay available
+pub fn env_job_retention() -> Duration {
+    let secs = std::env::var("JOB_RETENTION_SECS").unwrap_or_else(|_| "3600".to_string());
+    Duration::from_secs(fatal_unwrap_e!(secs.parse(), "Failed to parse JOB_RETENTION_SECS {}"))
+}
+
+pub fn env_shutdown_timeout() -> Duration {
+    let secs = std::env::var("SHUTDOWN_TIMEOUT_SECS").unwrap_or_else(|_| "30".to_string());
+    Duration::from_secs(fatal_unwrap_e!(secs.parse(), "Failed to parse SHUTDOWN_TIMEOUT_SECS {}"))
+}
+
+/// Rate budget of the HTTP server's crawler
+pub fn env_profiles_per_hour() -> u32 {
+    let profiles = std::env::var("PROFILES_PER_HOUR").unwrap_or_else(|_| "100".to_string());
+    fatal_unwrap_e!(profiles.parse(), "Failed to parse PROFILES_PER_HOUR {}")
+}
+
+/// Keys without their own quota may use the whole rate budget
+pub fn env_api_keys() -> Vec<ApiKey> {
+    let keys = std::env::var("API_KEYS").unwrap_or_default();
+    fatal_unwrap_e!(parse_api_keys(&keys, env_profiles_per_hour()), "Failed to parse API_KEYS {}")
+}
+
+/// Names of the API_KEYS clients allowed to use the admin endpoints
+pub fn env_admin_clients() -> Vec<String> {
+    let clients = std::env::var("ADMIN_CLIENTS").unwrap_or_default();
+    clients
+        .split(',')
+        .map(str::trim)
+        .filter(|client| !client.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 pub fn env_host() -> String {
     std::env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string())
 }
@@ -125,6 +275,22 @@ pub fn env_manager_profile_api() -> String {
     std::env::var("MANAGER_PROFILE_API").unwrap_or_else(|_| "".to_string())
 }
 
+pub fn env_manager_auth() -> ManagerAuth {
+    if let Ok(token) = std::env::var("MANAGER_AUTH_TOKEN") {
+        return ManagerAuth::Bearer(token);
+    }
+    match std::env::var("MANAGER_HMAC_SECRET") {
+        Ok(secret) => ManagerAuth::Hmac(secret),
+        Err(_) => ManagerAuth::None,
+    }
+}
+
+/// Port of the bus worker's health and status endpoints
+pub fn env_status_port() -> u16 {
+    let port = std::env::var("STATUS_PORT").unwrap_or_else(|_| "8081".to_string());
+    fatal_unwrap_e!(port.parse(), "Failed to parse STATUS_PORT {}")
+}
+
 pub fn env_port() -> u16 {
     std::env::var("PORT").unwrap_or_else(|_| "8080".to_string()).parse().unwrap_or(8080)
 }
@@ -224,19 +390,40 @@ pub async fn get_env() -> &'static Env {
             driver_session_count: env_driver_session_count(),
             azure_search_uri: env_azure_search_uri(),
             azure_search_dequeue_api: env_azure_search_dequeue_api(),
+            azure_search_queue_api: env_azure_search_queue_api(),
             azure_profile_uri: env_azure_profile_uri(),
             azure_profile_dequeue_api: env_azure_profile_dequeue_api(),
             azure_profile_queue_api: env_azure_profile_queue_api(),
+            azure_control_uri: env_azure_control_uri(),
+            azure_control_dequeue_api: env_azure_control_dequeue_api(),
+            azure_control_queue_api: env_azure_control_queue_api(),
             azure_manager_bus_uri: env_azure_manager_bus_uri(),
             azure_manager_bus_api: env_azure_manager_bus_api(),
             azure_sas_key_name: env_azure_sas_key_name(),
             azure_manager_bus_key: env_azure_manager_bus_key(),
             azure_sas_profile_key: env_azure_sas_profile_key(),
             azure_sas_search_key: env_azure_sas_search_key(),
+            azure_sas_control_key: env_azure_sas_control_key(),
             manager_search_api: env_manager_search_api(),
             manager_profile_api: env_manager_profile_api(),
+            manager_auth: env_manager_auth(),
             linkedin_username: env_linkedin_username(),
             linkedin_password: env_linkedin_password(),
+            queue_backend: env_queue_backend(),
+            queue_dir: env_queue_dir(),
+            control_subscription: env_control_subscription(),
+            outbox_dir: env_outbox_dir(),
+            dead_letter_dir: env_dead_letter_dir(),
+            seen_profiles_path: env_seen_profiles_path(),
+            max_delivery_attempts: env_max_delivery_attempts(),
+            outbox_max_attempts: env_outbox_max_attempts(),
+            receive_wait: env_receive_wait(),
+            shutdown_timeout: env_shutdown_timeout(),
+            job_retention: env_job_retention(),
+            profiles_per_hour: env_profiles_per_hour(),
+            api_keys: env_api_keys(),
+            status_port: env_status_port(),
+            admin_clients: env_admin_clients(),
         }
     })
     .await
@@ -1,5 +1,7 @@
+use crate::azure::manager;
 use std::fmt::{Display, Formatter};
 pub type CrawlerResult<T> = Result<T, CrawlerError>;
+#[derive(Debug)]
 pub enum CrawlerError {
     ParseError(String),
     InteractionError(String),
@@ -8,6 +10,7 @@ pub enum CrawlerError {
     LinkedinError(String),
     BusError(String),
     QueueError(String),
+    ManagerError(manager::ManagerError),
 }
 
 impl Display for CrawlerError {
@@ -20,6 +23,17 @@ impl Display for CrawlerError {
             CrawlerError::BusError(e) => write!(f, "BusError {}", e),
             CrawlerError::QueueError(e) => write!(f, "QueueError {}", e),
             CrawlerError::LinkedinError(e) => write!(f, "LinkedinError {}", e),
+            CrawlerError::ManagerError(e) => write!(f, "ManagerError {}", e),
+        }
+    }
+}
+
+impl CrawlerError {
+    /// False when sending the same request again cannot succeed, e.g. the manager rejected it.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            CrawlerError::ManagerError(e) => e.is_retryable(),
+            _ => true,
         }
     }
 }
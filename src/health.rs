@@ -0,0 +1,192 @@
+use crate::control::ControlState;
+use crate::linkedin::api::crawler::Crawler;
+use crate::linkedin::api::rate_limits::RateBudget;
+use actix_web::web::{Data, ServiceConfig};
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct LastError {
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+impl LastError {
+    pub fn now(message: impl Into<String>) -> Self {
+        Self {
+            at: Utc::now(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct SessionStatus {
+    pub authenticated: bool,
+    /// Seconds since the cookies were saved, absent when there are none
+    pub cookie_age_secs: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Debug, Default, ToSchema)]
+pub struct QueueStatus {
+    /// False only when the last receive failed
+    pub reachable: bool,
+    pub last_receive_at: Option<DateTime<Utc>>,
+    pub last_error: Option<LastError>,
+}
+
+/// Outcome of the latest receives, recorded by the worker loops.
+pub struct QueueHealth {
+    status: Mutex<QueueStatus>,
+}
+
+impl Default for QueueHealth {
+    fn default() -> Self {
+        Self {
+            status: Mutex::new(QueueStatus {
+                reachable: true,
+                ..QueueStatus::default()
+            }),
+        }
+    }
+}
+
+impl QueueHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn received(&self) {
+        let mut status = self.status.lock().unwrap();
+        status.reachable = true;
+        status.last_receive_at = Some(Utc::now());
+    }
+
+    pub fn failed(&self, error: impl Into<String>) {
+        let mut status = self.status.lock().unwrap();
+        status.reachable = false;
+        status.last_error = Some(LastError::now(error));
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct Status {
+    pub service: String,
+    pub started_at: DateTime<Utc>,
+    pub uptime_secs: i64,
+    pub session: SessionStatus,
+    pub rate_budget: RateBudget,
+    pub control: ControlState,
+    /// Jobs queued or running in the HTTP server, messages being processed in the bus worker
+    pub in_flight_jobs: usize,
+    /// Only the bus worker reads from a queue
+    pub queue: Option<QueueStatus>,
+    /// The latest of the crawler and queue errors
+    pub last_error: Option<LastError>,
+}
+
+impl Status {
+    pub fn new(service: &str, started_at: DateTime<Utc>, crawler: &Crawler, in_flight_jobs: usize, queue: Option<QueueStatus>) -> Self {
+        let queue_error = queue.as_ref().and_then(|queue| queue.last_error.clone());
+        let last_error = match (crawler.last_error(), queue_error) {
+            (Some(crawler), Some(queue)) if queue.at > crawler.at => Some(queue),
+            (None, queue) => queue,
+            (crawler, _) => crawler,
+        };
+        Self {
+            service: service.to_string(),
+            started_at,
+            uptime_secs: (Utc::now() - started_at).num_seconds(),
+            session: SessionStatus {
+                authenticated: crawler.is_authenticated(),
+                cookie_age_secs: crawler.cookie_age().map(|age| age.as_secs()),
+            },
+            rate_budget: crawler.rate_budget(),
+            control: crawler.control_state(),
+            in_flight_jobs,
+            queue,
+            last_error,
+        }
+    }
+
+    pub fn readiness(&self) -> Readiness {
+        let mut checks = vec![
+            Check {
+                name: "linkedin_session",
+                ok: self.session.authenticated,
+            },
+            Check {
+                name: "rate_budget",
+                ok: self.rate_budget.remaining > 0,
+            },
+        ];
+        if let Some(queue) = &self.queue {
+            checks.push(Check {
+                name: "queue",
+                ok: queue.reachable,
+            });
+        }
+        Readiness {
+            ready: checks.iter().all(|check| check.ok),
+            checks,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+}
+
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: Vec<Check>,
+}
+
+/// Implemented by each binary, the routes below only format what it reports.
+pub trait StatusProvider: Send + Sync {
+    fn status(&self) -> Status;
+}
+
+#[utoipa::path(get, path = "/healthz", tag = "health", responses((status = 200, description = "The process is up")))]
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Every check passed", body = Readiness),
+        (status = 503, description = "A check failed", body = Readiness),
+    )
+)]
+pub async fn readyz(provider: Data<dyn StatusProvider>) -> HttpResponse {
+    let readiness = provider.status().readiness();
+    match readiness.ready {
+        true => HttpResponse::Ok().json(readiness),
+        false => HttpResponse::ServiceUnavailable().json(readiness),
+    }
+}
+
+#[utoipa::path(get, path = "/status", tag = "health", responses((status = 200, body = Status)))]
+pub async fn status(provider: Data<dyn StatusProvider>) -> HttpResponse {
+    HttpResponse::Ok().json(provider.status())
+}
+
+/// Registers `/healthz`, `/readyz` and `/status`, which expect a `Data<dyn StatusProvider>`.
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.route("/healthz", web::get().to(healthz))
+        .route("/readyz", web::get().to(readyz))
+        .route("/status", web::get().to(status));
+}
@@ -0,0 +1,407 @@
+use crate::auth::{ApiKey, ApiKeys};
+use crate::azure::json::ProfileIds;
+use crate::errors::CrawlerResult;
+use crate::linkedin::api::crawler::Crawler;
+use crate::linkedin::api::json::{SearchParams, SearchResult};
+use crate::linkedin::profile::Profile;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+/// What actually crawls, the LinkedIn crawler outside of tests.
+pub trait JobExecutor: Send + Sync {
+    /// Crawls the single search page `params.page`.
+    fn search_page(&self, params: SearchParams) -> impl Future<Output = CrawlerResult<SearchResult>> + Send;
+
+    fn profile(&self, id: &str) -> impl Future<Output = CrawlerResult<Profile>> + Send;
+}
+
+impl JobExecutor for Crawler {
+    async fn search_page(&self, params: SearchParams) -> CrawlerResult<SearchResult> {
+        self.search_people(params, None).await
+    }
+
+    async fn profile(&self, id: &str) -> CrawlerResult<Profile> {
+        Crawler::profile(self, id).await.map(Profile::from)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Search,
+    Profiles,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+pub enum JobRequest {
+    Search(SearchParams),
+    Profiles(ProfileIds),
+}
+
+/// A profile id that could not be crawled.
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ItemError {
+    pub id: String,
+    pub error: String,
+}
+
+/// Published to subscribers of a job as it makes progress.
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JobEvent {
+    /// The whole job at the moment of subscribing, later events follow from there
+    Snapshot {
+        job: Job,
+    },
+    Status {
+        status: JobStatus,
+        done: usize,
+        total: usize,
+        error: Option<String>,
+    },
+    Profile {
+        id: String,
+        profile: serde_json::Value,
+    },
+    SearchPage {
+        page: u16,
+        result: serde_json::Value,
+    },
+    Error(ItemError),
+}
+
+impl JobEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobEvent::Snapshot { .. } => "snapshot",
+            JobEvent::Status { .. } => "status",
+            JobEvent::Profile { .. } => "profile",
+            JobEvent::SearchPage { .. } => "search_page",
+            JobEvent::Error(_) => "error",
+        }
+    }
+
+    /// Formats the event as a Server-Sent Events message.
+    pub fn to_sse(&self) -> String {
+        format!(
+            "event: {}\ndata: {}\n\n",
+            self.name(),
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+
+    /// No more events follow this one.
+    pub fn is_last(&self) -> bool {
+        match self {
+            JobEvent::Snapshot { job } => job.status.is_finished(),
+            JobEvent::Status { status, .. } => status.is_finished(),
+            _ => false,
+        }
+    }
+
+    fn status(job: &Job) -> JobEvent {
+        JobEvent::Status {
+            status: job.status,
+            done: job.done,
+            total: job.total,
+            error: job.error.clone(),
+        }
+    }
+}
+
+/// State of a job as reported to clients.
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// Profiles or search pages handled so far, a search ending early lowers `total` to match
+    pub done: usize,
+    pub total: usize,
+    pub request_metadata: Option<String>,
+    /// Name of the API client that submitted the job, none when the server runs without keys
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Why a failed job failed
+    pub error: Option<String>,
+    /// Results produced so far, profiles or one search result per page depending on the kind
+    pub results: Vec<serde_json::Value>,
+    pub errors: Vec<ItemError>,
+}
+
+impl Job {
+    /// Only the client that submitted a job and admins may see, follow or cancel it.
+    pub fn is_visible_to(&self, client: Option<&ApiKey>, keys: &ApiKeys) -> bool {
+        match client {
+            Some(client) => self.owner.as_deref() == Some(client.name.as_str()) || keys.is_admin(client),
+            None => self.owner.is_none(),
+        }
+    }
+}
+
+struct Entry {
+    job: Job,
+    /// Taken by the worker when the job starts
+    request: Option<JobRequest>,
+    cancel: Arc<AtomicBool>,
+    events: broadcast::Sender<JobEvent>,
+}
+
+/// Events kept for a subscriber that falls behind, it gets a fresh snapshot once they overflow
+const EVENT_BUFFER: usize = 256;
+
+/// Jobs submitted over the HTTP API. They run one at a time in submission order, the crawler shares
+/// a single rate limit anyway. Finished jobs are forgotten once they are older than `retention`.
+pub struct JobManager {
+    jobs: Mutex<HashMap<String, Entry>>,
+    retention: Duration,
+    sender: UnboundedSender<String>,
+    receiver: Mutex<Option<UnboundedReceiver<String>>>,
+}
+
+impl JobManager {
+    pub fn new(retention: Duration) -> Arc<Self> {
+        let (sender, receiver) = unbounded_channel();
+        Arc::new(Self {
+            jobs: Mutex::new(HashMap::new()),
+            retention,
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        })
+    }
+
+    pub fn submit(&self, request: JobRequest, owner: Option<String>) -> Job {
+        let (kind, total, request_metadata) = match &request {
+            JobRequest::Search(params) => (
+                JobKind::Search,
+                params.end.saturating_sub(params.page) as usize,
+                params.request_metadata.clone(),
+            ),
+            JobRequest::Profiles(ids) => (JobKind::Profiles, ids.ids.len(), ids.request_metadata.clone()),
+        };
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            status: JobStatus::Queued,
+            done: 0,
+            total,
+            request_metadata,
+            owner,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            error: None,
+            results: Vec::new(),
+            errors: Vec::new(),
+        };
+        let entry = Entry {
+            job: job.clone(),
+            request: Some(request),
+            cancel: Arc::new(AtomicBool::new(false)),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        };
+        let mut jobs = self.jobs.lock().unwrap();
+        self.evict_expired(&mut jobs);
+        jobs.insert(job.id.clone(), entry);
+        drop(jobs);
+        if self.sender.send(job.id.clone()).is_err() {
+            error!("Job worker is gone, job {} will not run", job.id);
+        }
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.evict_expired(&mut jobs);
+        jobs.get(id).map(|entry| entry.job.clone())
+    }
+
+    /// Drops finished jobs with their results once they are older than the retention.
+    fn evict_expired(&self, jobs: &mut HashMap<String, Entry>) {
+        let cutoff = match chrono::Duration::from_std(self.retention)
+            .ok()
+            .and_then(|retention| Utc::now().checked_sub_signed(retention))
+        {
+            Some(cutoff) => cutoff,
+            None => return,
+        };
+        jobs.retain(|_, entry| entry.job.finished_at.is_none_or(|finished_at| finished_at > cutoff));
+    }
+
+    /// Jobs queued or running.
+    pub fn in_flight(&self) -> usize {
+        self.jobs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|entry| !entry.job.status.is_finished())
+            .count()
+    }
+
+    /// The job as it is now together with a receiver for everything that happens to it afterwards.
+    pub fn subscribe(&self, id: &str) -> Option<(Job, broadcast::Receiver<JobEvent>)> {
+        let jobs = self.jobs.lock().unwrap();
+        let entry = jobs.get(id)?;
+        Some((entry.job.clone(), entry.events.subscribe()))
+    }
+
+    /// A queued job is cancelled right away, a running one stops after its current profile or page.
+    /// Finished jobs are left as they are.
+    pub fn cancel(&self, id: &str) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let entry = jobs.get_mut(id)?;
+        match entry.job.status {
+            JobStatus::Queued => {
+                let event = Self::finish(&mut entry.job, JobStatus::Cancelled);
+                let _ = entry.events.send(event);
+            }
+            JobStatus::Running => entry.cancel.store(true, Relaxed),
+            _ => {}
+        }
+        Some(entry.job.clone())
+    }
+
+    /// Starts the task running queued jobs. Only the first call has any effect.
+    pub fn spawn<E: JobExecutor + 'static>(self: &Arc<Self>, executor: &'static E) -> Option<JoinHandle<()>> {
+        let mut receiver = self.receiver.lock().unwrap().take()?;
+        let manager = self.clone();
+        Some(tokio::spawn(async move {
+            while let Some(id) = receiver.recv().await {
+                manager.run(executor, &id).await;
+            }
+        }))
+    }
+
+    fn finish(job: &mut Job, status: JobStatus) -> JobEvent {
+        job.status = status;
+        job.finished_at = Some(Utc::now());
+        JobEvent::status(job)
+    }
+
+    /// Changes the job and publishes the event describing the change. Both happen under the lock, so
+    /// a subscriber never misses or repeats a change relative to its snapshot.
+    fn update(&self, id: &str, f: impl FnOnce(&mut Job) -> JobEvent) {
+        if let Some(entry) = self.jobs.lock().unwrap().get_mut(id) {
+            let event = f(&mut entry.job);
+            // Nobody listening is fine
+            let _ = entry.events.send(event);
+        }
+    }
+
+    async fn run<E: JobExecutor>(&self, executor: &E, id: &str) {
+        let (request, cancel) = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let entry = match jobs.get_mut(id) {
+                Some(entry) if entry.job.status == JobStatus::Queued => entry,
+                _ => return,
+            };
+            entry.job.status = JobStatus::Running;
+            entry.job.started_at = Some(Utc::now());
+            let _ = entry.events.send(JobEvent::status(&entry.job));
+            (entry.request.take(), entry.cancel.clone())
+        };
+        info!("Running job {}", id);
+        match request {
+            Some(JobRequest::Search(params)) => self.run_search(executor, id, params, &cancel).await,
+            Some(JobRequest::Profiles(ids)) => self.run_profiles(executor, id, ids, &cancel).await,
+            None => {}
+        }
+    }
+
+    async fn run_search<E: JobExecutor>(&self, executor: &E, id: &str, params: SearchParams, cancel: &AtomicBool) {
+        for page in params.page..params.end {
+            if cancel.load(Relaxed) {
+                return self.update(id, |job| Self::finish(job, JobStatus::Cancelled));
+            }
+            let page_params = SearchParams {
+                page,
+                end: page + 1,
+                ..params.clone()
+            };
+            let result = match executor.search_page(page_params).await {
+                Ok(result) => result,
+                Err(e) => {
+                    return self.update(id, |job| {
+                        job.error = Some(e.to_string());
+                        Self::finish(job, JobStatus::Failed)
+                    })
+                }
+            };
+            let exhausted = result.elements.is_empty();
+            let value = serde_json::to_value(&result).unwrap_or_default();
+            self.update(id, |job| {
+                job.done += 1;
+                job.results.push(value.clone());
+                JobEvent::SearchPage { page, result: value }
+            });
+            if exhausted {
+                break;
+            }
+        }
+        self.update(id, |job| {
+            job.total = job.done;
+            Self::finish(job, JobStatus::Completed)
+        });
+    }
+
+    async fn run_profiles<E: JobExecutor>(&self, executor: &E, id: &str, ids: ProfileIds, cancel: &AtomicBool) {
+        for profile_id in ids.ids.iter() {
+            if cancel.load(Relaxed) {
+                break;
+            }
+            let result = executor.profile(profile_id).await;
+            self.update(id, |job| {
+                job.done += 1;
+                match result {
+                    Ok(profile) => {
+                        let profile = serde_json::to_value(&profile).unwrap_or_default();
+                        job.results.push(profile.clone());
+                        JobEvent::Profile {
+                            id: profile_id.clone(),
+                            profile,
+                        }
+                    }
+                    Err(e) => {
+                        let error = ItemError {
+                            id: profile_id.clone(),
+                            error: e.to_string(),
+                        };
+                        job.errors.push(error.clone());
+                        JobEvent::Error(error)
+                    }
+                }
+            });
+        }
+        self.update(id, |job| match cancel.load(Relaxed) {
+            true => Self::finish(job, JobStatus::Cancelled),
+            false => Self::finish(job, JobStatus::Completed),
+        });
+    }
+}
@@ -2,10 +2,18 @@
 extern crate log;
 #[macro_use]
 pub mod macros;
+pub mod auth;
 pub mod azure;
+pub mod control;
+pub mod convert;
 pub mod driver;
 pub mod env;
 pub mod errors;
+pub mod health;
+pub mod jobs;
 pub mod linkedin;
 pub mod logger;
+pub mod metrics;
+pub mod queue;
 pub mod utils;
+pub mod validation;
@@ -1,15 +1,35 @@
 use crate::azure::json::{CrawledProfiles, ProfileIds};
+use crate::control::{ControlCommand, ControlState, PAUSE_POLL};
+use crate::errors::CrawlerError::{ParseError, SessionError};
 use crate::errors::CrawlerResult;
-use crate::linkedin::api::json::{Profile, SearchParams, SearchResult};
-use crate::linkedin::api::rate_limits::RateLimiter;
+use crate::health::LastError;
+use crate::linkedin::api::json::{Profile, SearchParams, SearchResult, SkillView};
+use crate::linkedin::api::rate_limits::{RateBudget, RateLimiter};
 use crate::linkedin::api::LinkedinSession;
+use crate::metrics::{PROFILES_CRAWLED, RATE_LIMIT_SLEEP};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Mutex;
 use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Profiles crawled by `Crawler::profiles`, an id that fails does not stop the others.
+#[derive(Default)]
+pub struct ProfileBatch {
+    pub profiles: Vec<Profile>,
+    /// Ids that failed to crawl, with the reason
+    pub failed: Vec<(String, String)>,
+}
 
 pub struct Crawler {
-    linked_in_session: LinkedinSession,
+    /// Written only when re-authenticating, which waits for requests in flight
+    linked_in_session: RwLock<LinkedinSession>,
     rate_limits: RateLimiter,
+    last_error: Mutex<Option<LastError>>,
+    username: String,
+    password: String,
+    paused: AtomicBool,
+    account_disabled: AtomicBool,
 }
 
 impl Crawler {
@@ -22,42 +42,149 @@ impl Crawler {
             }
         }
         Self {
-            linked_in_session: LinkedinSession::new(),
+            linked_in_session: RwLock::new(LinkedinSession::new()),
             rate_limits,
+            last_error: Mutex::new(None),
+            username: username.to_string(),
+            password: password.to_string(),
+            paused: AtomicBool::new(false),
+            account_disabled: AtomicBool::new(false),
         }
     }
 
-    pub async fn search_people(&self, params: SearchParams) -> CrawlerResult<SearchResult> {
-        self.linked_in_session.search_people(params).await
+    pub async fn search_people(&self, params: SearchParams, interrupt_signal: Option<&AtomicBool>) -> CrawlerResult<SearchResult> {
+        self.wait_until_active(interrupt_signal).await;
+        let result = self.linked_in_session.read().await.search_people(params, interrupt_signal).await;
+        self.record(result)
     }
-    pub async fn profiles(&self, ids: &[String], interrupt_signal: Option<&AtomicBool>) -> CrawlerResult<Vec<Profile>> {
-        let mut crawled_profiles = Vec::with_capacity(ids.len());
+    pub async fn profiles(&self, ids: &[String], interrupt_signal: Option<&AtomicBool>) -> CrawlerResult<ProfileBatch> {
+        let mut batch = ProfileBatch::default();
         for profile in ids.iter() {
+            self.wait_until_active(interrupt_signal).await;
             if let Some(signal) = interrupt_signal {
                 if signal.load(Relaxed) == true {
                     break;
                 }
             }
-            let mut parsed_profile = match self.linked_in_session.profile(profile.as_str()).await {
-                Ok(profile) => profile,
+            match self.crawl_profile(profile).await {
+                Ok(parsed_profile) => batch.profiles.push(parsed_profile),
                 Err(e) => {
                     error!("Failed to crawl profile {} reason: {}", profile, e);
-                    continue;
-                }
-            };
-            let skills = match self.linked_in_session.skills(profile.as_str()).await {
-                Ok(skills) => skills,
-                Err(e) => {
-                    error!("Failed to crawl skills {} reason:{}", profile, e);
-                    continue;
+                    batch.failed.push((profile.clone(), e.to_string()));
                 }
-            };
-            parsed_profile.skill_view = skills;
-            crawled_profiles.push(parsed_profile);
-            let wait_time = self.rate_limits.next().unwrap();
-            info!("Sleeping. Rate limit: {}", wait_time.as_secs());
-            tokio::time::sleep(wait_time).await;
-        }
-        Ok(crawled_profiles)
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Crawls one profile with its skills, then waits out the rate limit.
+    pub async fn profile(&self, id: &str) -> CrawlerResult<Profile> {
+        self.wait_until_active(None).await;
+        self.crawl_profile(id).await
+    }
+
+    /// Crawls just the skills of one profile, then waits out the rate limit.
+    pub async fn skills(&self, id: &str) -> CrawlerResult<SkillView> {
+        self.wait_until_active(None).await;
+        let skill_view = self.record(self.linked_in_session.read().await.skills(id).await)?;
+        self.wait_out_rate_limit().await;
+        Ok(skill_view)
+    }
+
+    async fn crawl_profile(&self, id: &str) -> CrawlerResult<Profile> {
+        let session = self.linked_in_session.read().await;
+        let mut parsed_profile = self.record(session.profile(id).await)?;
+        parsed_profile.skill_view = self.record(session.skills(id).await)?;
+        drop(session);
+        PROFILES_CRAWLED.inc();
+        self.wait_out_rate_limit().await;
+        Ok(parsed_profile)
+    }
+
+    async fn wait_out_rate_limit(&self) {
+        let wait_time = self.rate_limits.next().unwrap();
+        RATE_LIMIT_SLEEP.observe(wait_time.as_secs_f64());
+        info!("Sleeping. Rate limit: {}", wait_time.as_secs());
+        tokio::time::sleep(wait_time).await;
+    }
+
+    /// False while re-authenticating.
+    pub fn is_authenticated(&self) -> bool {
+        self.linked_in_session.try_read().is_ok_and(|session| session.is_auth())
+    }
+
+    pub fn cookie_age(&self) -> Option<Duration> {
+        self.linked_in_session.try_read().ok()?.cookie_age()
+    }
+
+    /// Waits while paused or while the account is disabled, unless interrupted.
+    async fn wait_until_active(&self, interrupt_signal: Option<&AtomicBool>) {
+        while !self.control_state().is_active() {
+            if interrupt_signal.is_some_and(|signal| signal.load(Relaxed)) {
+                return;
+            }
+            tokio::time::sleep(PAUSE_POLL).await;
+        }
+    }
+
+    pub fn control_state(&self) -> ControlState {
+        ControlState {
+            paused: self.paused.load(Relaxed),
+            profiles_per_hour: self.rate_limits.profiles_per_hour(),
+            account: self.username.clone(),
+            account_disabled: self.account_disabled.load(Relaxed),
+        }
+    }
+
+    /// Applies the command to the running crawler and returns the resulting state.
+    pub async fn apply(&self, command: ControlCommand) -> CrawlerResult<ControlState> {
+        info!("Applying control command {}", command.name());
+        match command {
+            ControlCommand::Pause => self.paused.store(true, Relaxed),
+            ControlCommand::Resume => self.paused.store(false, Relaxed),
+            ControlCommand::SetBudget { profiles_per_hour: 0 } => {
+                return Err(ParseError(
+                    "A budget of 0 profiles per hour would not wait at all, pause instead".to_string(),
+                ))
+            }
+            ControlCommand::SetBudget { profiles_per_hour } => self.rate_limits.set_profiles_per_hour(profiles_per_hour),
+            ControlCommand::DisableAccount { account } => self.account(&account)?.store(true, Relaxed),
+            ControlCommand::EnableAccount { account } => self.account(&account)?.store(false, Relaxed),
+            ControlCommand::Reauthenticate => self.reauthenticate().await?,
+        }
+        Ok(self.control_state())
+    }
+
+    fn account(&self, account: &str) -> CrawlerResult<&AtomicBool> {
+        match account == self.username {
+            true => Ok(&self.account_disabled),
+            false => Err(SessionError(format!("Unknown account {}", account))),
+        }
+    }
+
+    /// Logs in again even when the saved cookies look valid, e.g. after LinkedIn revoked them.
+    async fn reauthenticate(&self) -> CrawlerResult<()> {
+        let mut session = self.linked_in_session.write().await;
+        let mut fresh = LinkedinSession::new();
+        self.record(fresh.authenticate(&self.username, &self.password).await)?;
+        // Like on start up, the new session is built from the cookies authenticating saved
+        *session = LinkedinSession::new();
+        Ok(())
+    }
+
+    pub fn rate_budget(&self) -> RateBudget {
+        self.rate_limits.budget()
+    }
+
+    /// The most recent failure talking to LinkedIn.
+    pub fn last_error(&self) -> Option<LastError> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    fn record<T>(&self, result: CrawlerResult<T>) -> CrawlerResult<T> {
+        if let Err(e) = &result {
+            *self.last_error.lock().unwrap() = Some(LastError::now(e.to_string()));
+        }
+        result
     }
 }
@@ -1,14 +1,18 @@
 use regex::Regex;
 use serde::de::Unexpected;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
+#[derive(Clone, Copy, utoipa::ToSchema)]
+#[schema(rename_all = "lowercase")]
 pub enum GeoUrnMap {
     Czechia = 104508036,
     Slovakia = 103119917,
 }
 
+#[derive(Clone, Copy, utoipa::ToSchema)]
+#[schema(rename_all = "lowercase")]
 pub enum NetworkDepth {
     One,
     Two,
@@ -35,7 +39,20 @@ impl<'de> Deserialize<'de> for NetworkDepth {
             "one" => Ok(NetworkDepth::One),
             "two" => Ok(NetworkDepth::Two),
             "three" => Ok(NetworkDepth::Three),
-            _ => Err(serde::de::Error::custom("Invalid NetworkDepth")),
+            _ => Err(serde::de::Error::unknown_variant(&s, &["one", "two", "three"])),
+        }
+    }
+}
+
+impl Serialize for NetworkDepth {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            NetworkDepth::One => serializer.serialize_str("one"),
+            NetworkDepth::Two => serializer.serialize_str("two"),
+            NetworkDepth::Three => serializer.serialize_str("three"),
         }
     }
 }
@@ -57,11 +74,22 @@ impl<'de> Deserialize<'de> for GeoUrnMap {
         match s.as_str() {
             "czechia" => Ok(GeoUrnMap::Czechia),
             "slovakia" => Ok(GeoUrnMap::Slovakia),
-            _ => Err(serde::de::Error::custom("invalid value")),
+            _ => Err(serde::de::Error::unknown_variant(&s, &["czechia", "slovakia"])),
         }
     }
 }
-#[derive(serde::Deserialize)]
+impl Serialize for GeoUrnMap {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            GeoUrnMap::Czechia => serializer.serialize_str("czechia"),
+            GeoUrnMap::Slovakia => serializer.serialize_str("slovakia"),
+        }
+    }
+}
+#[derive(serde::Deserialize, serde::Serialize, Clone, utoipa::ToSchema)]
 pub struct SearchParams {
     pub countries: Option<Vec<GeoUrnMap>>,
     pub keywords: Option<String>,
@@ -376,7 +404,7 @@ pub struct Publication {
     pub publisher: Option<String>,
     pub url: Option<String>,
 }
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, utoipa::ToSchema)]
 pub struct SearchResult {
     pub elements: Vec<SearchItem>,
     /// TODO should be cleaned as request meta data doesn't belong here
@@ -384,6 +412,9 @@ pub struct SearchResult {
     #[serde(skip_serializing)]
     pub total_lookup: u16,
     pub total: u64,
+    /// Page to continue from when the search was interrupted before its end page
+    #[serde(skip_serializing)]
+    pub resume_page: Option<u16>,
 }
 
 impl<'de> Deserialize<'de> for SearchResult {
@@ -443,6 +474,7 @@ impl<'de> Deserialize<'de> for SearchResult {
                 elements: Vec::new(),
                 total: 0,
                 total_lookup: 0,
+                resume_page: None,
             });
         }
 
@@ -461,6 +493,7 @@ impl<'de> Deserialize<'de> for SearchResult {
             total,
             total_lookup,
             request_metadata: None,
+            resume_page: None,
         })
     }
 }
@@ -565,7 +598,7 @@ where
     Ok(out)
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Clone, utoipa::ToSchema)]
 pub struct SearchItem {
     pub first_name: String,
     pub last_name: String,
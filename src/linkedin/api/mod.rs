@@ -6,7 +6,8 @@ mod utils;
 use crate::errors::CrawlerError::{LinkedinError, SessionError};
 use crate::errors::CrawlerResult;
 use crate::linkedin::api::json::{AuthenticateResponse, FetchCookiesResponse, Profile, SearchParams, SearchResult, Skill, SkillView};
-use crate::linkedin::api::utils::{cookies_session_id, load_cookies, save_cookies};
+use crate::linkedin::api::utils::{cookies_age, cookies_session_id, load_cookies, save_cookies};
+use crate::metrics::{linkedin_response, LINKEDIN_PARSE_FAILURES};
 use actix_web::cookie::CookieJar;
 use actix_web::web::Json;
 use cookie::Cookie;
@@ -19,6 +20,8 @@ use serde::de::Unexpected::Str;
 use std::error::Error;
 use std::fmt::format;
 use std::ops::Deref;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::io::AsyncReadExt;
@@ -39,6 +42,10 @@ impl LinkedinSession {
     pub fn is_auth(&self) -> bool {
         self.is_auth
     }
+
+    pub fn cookie_age(&self) -> Option<Duration> {
+        cookies_age()
+    }
     //TODO This should be a static place in the memory. Shouldn't be created every time
 
     fn create_default_headers(csrf_token: Option<&str>) -> HeaderMap {
@@ -61,10 +68,9 @@ impl LinkedinSession {
         let auth_url = format!("{}{}", Self::LINKEDIN_URL, "/uas/authenticate");
         let default_headers = Self::create_default_headers(None);
 
-        let response = fatal_unwrap_e!(
-            self.client.get(auth_url).headers(default_headers).send().await,
-            "Failed to obtain linkedin set-cookies {}"
-        );
+        let response = self.client.get(auth_url).headers(default_headers).send().await;
+        linkedin_response("session", &response);
+        let response = fatal_unwrap_e!(response, "Failed to obtain linkedin set-cookies {}");
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap();
@@ -74,6 +80,7 @@ impl LinkedinSession {
         let auth_response = match response.json::<FetchCookiesResponse>().await {
             Ok(response) => response,
             Err(e) => {
+                LINKEDIN_PARSE_FAILURES.with_label_values(&["session"]).inc();
                 return Err(SessionError(format!("Failed to parse auth response {}", e)));
             }
         };
@@ -100,14 +107,15 @@ impl LinkedinSession {
             ("session_password", password),
             ("JSESSIONID", formatted_session_id.as_ref()),
         ];
-        let response = match self
+        let response = self
             .client
             .post(format!("{}{}", Self::LINKEDIN_URL, "/uas/authenticate"))
             .headers(headers)
             .form(&form)
             .send()
-            .await
-        {
+            .await;
+        linkedin_response("authenticate", &response);
+        let response = match response {
             Ok(response) => response,
             Err(e) => {
                 return Err(SessionError(format!("Failed authenticate request {}", e)));
@@ -125,6 +133,7 @@ impl LinkedinSession {
         let response_data = match response.json::<AuthenticateResponse>().await {
             Ok(response_data) => response_data,
             Err(e) => {
+                LINKEDIN_PARSE_FAILURES.with_label_values(&["authenticate"]).inc();
                 return Err(SessionError(format!("Failed to parse authenticate response {}", e)));
             }
         };
@@ -149,14 +158,19 @@ impl LinkedinSession {
         info!("Getting profile {}", profile_id);
         let endpoint = format!("{}/identity/profiles/{}/profileView", Self::API_URL, profile_id);
         let headers = Self::create_default_headers(Some(&self.session_id));
-        let profile = match self.client.get(endpoint).headers(headers).send().await {
+        let response = self.client.get(endpoint).headers(headers).send().await;
+        linkedin_response("profile", &response);
+        let profile = match response {
             Ok(response) => {
                 if !response.status().is_success() {
                     return Err(LinkedinError(format!("Failed to get profile {}", response.text().await.unwrap())));
                 }
                 match response.json::<Profile>().await {
                     Ok(profile) => profile,
-                    Err(e) => return Err(SessionError(format!("Failed to parse profile {:?}", e))),
+                    Err(e) => {
+                        LINKEDIN_PARSE_FAILURES.with_label_values(&["profile"]).inc();
+                        return Err(SessionError(format!("Failed to parse profile {:?}", e)));
+                    }
                 }
             }
             Err(e) => return Err(SessionError(format!("Failed to get profile {}", e))),
@@ -165,7 +179,7 @@ impl LinkedinSession {
         Ok(profile)
     }
 
-    pub async fn search_people(&self, mut params: SearchParams) -> CrawlerResult<SearchResult> {
+    pub async fn search_people(&self, mut params: SearchParams, interrupt_signal: Option<&AtomicBool>) -> CrawlerResult<SearchResult> {
         if params.page > params.end {
             return Err(SessionError("Start page cannot be greater than end page".to_string()));
         }
@@ -216,10 +230,15 @@ impl LinkedinSession {
             request_metadata: params.request_metadata.take(),
             total: 0,
             total_lookup: 0,
+            resume_page: None,
         };
         info!("searching {} from total {}", current_offset, total_offset);
         let encoded_keywords = encode(&keywords);
         while current_offset < total_offset {
+            if interrupt_signal.is_some_and(|signal| signal.load(Relaxed)) {
+                search_response.resume_page = Some(current_offset / ITEM_PER_PAGE);
+                break;
+            }
             let endpoint = format!(
                 "{}/graphql?variables=(start:{},origin:GLOBAL_SEARCH_HEADER,query:(keywords:{},flagshipSearchIntent:SEARCH_SRP,queryParameters:{},includeFiltersInResponse:false))&queryId=voyagerSearchDashClusters.b0928897b71bd00a5a7291755dcd64f0",
                 Self::API_URL,
@@ -229,10 +248,15 @@ impl LinkedinSession {
             );
             let headers = Self::create_default_headers(Some(&self.session_id));
 
-            let response = match self.client.get(endpoint).headers(headers).send().await {
+            let response = self.client.get(endpoint).headers(headers).send().await;
+            linkedin_response("search", &response);
+            let response = match response {
                 Ok(response) => match response.json::<SearchResult>().await {
                     Ok(result) => result,
-                    Err(e) => return Err(SessionError(format!("search people parse failed: {:?}", e))),
+                    Err(e) => {
+                        LINKEDIN_PARSE_FAILURES.with_label_values(&["search"]).inc();
+                        return Err(SessionError(format!("search people parse failed: {:?}", e)));
+                    }
                 },
                 Err(e) => return Err(SessionError(format!("search people failed {:?}", e))),
             };
@@ -255,10 +279,15 @@ impl LinkedinSession {
     pub async fn skills(&self, profile_id: &str) -> CrawlerResult<SkillView> {
         let endpoint = format!("{}/identity/profiles/{}/skills?count=100&start=0", Self::API_URL, profile_id);
         let headers = Self::create_default_headers(Some(&self.session_id));
-        let skills = match self.client.get(endpoint).headers(headers).send().await {
+        let response = self.client.get(endpoint).headers(headers).send().await;
+        linkedin_response("skills", &response);
+        let skills = match response {
             Ok(response) => match response.json::<SkillView>().await {
                 Ok(skills) => skills,
-                Err(e) => return Err(SessionError(format!("Failed to parse skills {:?}", e))),
+                Err(e) => {
+                    LINKEDIN_PARSE_FAILURES.with_label_values(&["skills"]).inc();
+                    return Err(SessionError(format!("Failed to parse skills {:?}", e)));
+                }
             },
             Err(e) => return Err(SessionError(format!("Failed to get skills {:?}", e))),
         };
@@ -1,38 +1,71 @@
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
-use std::sync::atomic::AtomicUsize;
+use serde::Serialize;
+use std::collections::VecDeque;
 use std::sync::atomic::Ordering::{Acquire, Release};
-use std::time::Duration;
+use std::sync::atomic::{AtomicU32, AtomicUsize};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+const BUDGET_WINDOW: Duration = Duration::from_secs(3600);
+
+/// How long a LinkedIn request takes, the crawlers leave this out of the time they wait
+pub const AVG_RESPONSE_TIME_MS: u32 = 800;
+/// Above this budget the requests alone fill the hour and the crawler would never wait
+pub const MAX_PROFILES_PER_HOUR: u32 = 3_600_000 / AVG_RESPONSE_TIME_MS;
+
+/// Profiles crawled within the last hour against the hourly budget.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+pub struct RateBudget {
+    pub profiles_per_hour: u32,
+    pub used: u32,
+    pub remaining: u32,
+}
 
 pub struct RateLimiter {
-    profiles_per_hour: u32,
+    profiles_per_hour: AtomicU32,
     avg_response_time_ms: u32,
-    waits: Vec<u64>,
+    /// Replaced as a whole when the budget changes
+    waits: RwLock<Vec<u64>>,
     current: AtomicUsize,
-    end: usize,
+    /// When each wait was handed out during the last hour
+    used: Mutex<VecDeque<Instant>>,
 }
 
 impl RateLimiter {
     pub fn new(profiles_per_hour: u32, avg_response_time_ms: u32) -> Self {
-        let mut waits = Self::generate_random_waits(profiles_per_hour, avg_response_time_ms);
-        let current = AtomicUsize::new(0);
-        let end = waits.len();
+        let waits = Self::generate_random_waits(profiles_per_hour, avg_response_time_ms);
         Self {
-            profiles_per_hour,
-            waits,
-            current,
-            end,
+            profiles_per_hour: AtomicU32::new(profiles_per_hour),
+            waits: RwLock::new(waits),
+            current: AtomicUsize::new(0),
             avg_response_time_ms,
+            used: Mutex::new(VecDeque::new()),
         }
     }
 
+    pub fn profiles_per_hour(&self) -> u32 {
+        self.profiles_per_hour.load(Acquire)
+    }
+
+    /// Changes the budget of a running crawler, the next wait is already drawn from the new budget.
+    pub fn set_profiles_per_hour(&self, profiles_per_hour: u32) {
+        let waits = Self::generate_random_waits(profiles_per_hour, self.avg_response_time_ms);
+        // Reset while holding the lock, so no index into the old waits is used on the new ones
+        let mut guard = self.waits.write().unwrap();
+        *guard = waits;
+        self.current.store(0, Release);
+        self.profiles_per_hour.store(profiles_per_hour, Release);
+    }
+
     pub fn generate_random_waits(profiles_per_hour: u32, avg_response_time_ms: u32) -> Vec<u64> {
         if profiles_per_hour == 0 {
             return vec![0];
         }
 
-        let total_request_time_sec = (profiles_per_hour * avg_response_time_ms) as f32 / 1000f32;
-        let total_wait_time = 3600f32 - total_request_time_sec;
+        let total_request_time_sec = profiles_per_hour as f32 * avg_response_time_ms as f32 / 1000f32;
+        let total_wait_time = (3600f32 - total_request_time_sec).max(0f32);
         let set_max = (((1f32 + 8f32 * total_wait_time).sqrt() - 1f32) / 2f32) as usize;
         // Create sequential array from 0 to set_max
         let mut waits: Vec<u64> = (0..=set_max).map(|x| x as u64).collect();
@@ -40,8 +73,36 @@ impl RateLimiter {
         waits
     }
 
+    pub fn budget(&self) -> RateBudget {
+        self.budget_at(Instant::now())
+    }
+
+    pub fn budget_at(&self, now: Instant) -> RateBudget {
+        let count = self.used_at(now, false) as u32;
+        let profiles_per_hour = self.profiles_per_hour();
+        RateBudget {
+            profiles_per_hour,
+            used: count,
+            remaining: profiles_per_hour.saturating_sub(count),
+        }
+    }
+
+    /// Forgets waits older than the budget window, optionally recording a new one at `now`.
+    fn used_at(&self, now: Instant, record: bool) -> usize {
+        let mut used = self.used.lock().unwrap();
+        while used.front().is_some_and(|at| now.saturating_duration_since(*at) >= BUDGET_WINDOW) {
+            used.pop_front();
+        }
+        if record {
+            used.push_back(now);
+        }
+        used.len()
+    }
+
     pub fn next(&self) -> Option<Duration> {
-        let end = self.end;
+        self.used_at(Instant::now(), true);
+        let waits = self.waits.read().unwrap();
+        let end = waits.len();
         let current_local = match self.current.fetch_update(Release, Acquire, |current| {
             let new = current + 1;
             if new >= end {
@@ -53,8 +114,6 @@ impl RateLimiter {
             Ok(current) => current,
             Err(_) => return None,
         };
-        let wait_time = unsafe { self.waits.get_unchecked(current_local) };
-
-        unsafe { Some(Duration::from_secs(*wait_time)) }
+        waits.get(current_local).map(|wait_time| Duration::from_secs(*wait_time))
     }
 }
@@ -1,8 +1,11 @@
 use regex::Regex;
 use std::io::{Read, Write};
+use std::time::Duration;
+
+const COOKIES_FILE: &str = "cookie.dat";
 
 pub fn load_cookies() -> Option<String> {
-    let mut file = match std::fs::File::open("cookie.dat") {
+    let mut file = match std::fs::File::open(COOKIES_FILE) {
         Ok(file) => file,
         Err(_) => {
             info!("Failed to open cookies file");
@@ -18,7 +21,7 @@ pub fn load_cookies() -> Option<String> {
 }
 
 pub fn save_cookies(cookies: &[u8]) {
-    let mut file = match std::fs::File::create("cookie.dat") {
+    let mut file = match std::fs::File::create(COOKIES_FILE) {
         Ok(file) => file,
         Err(e) => {
             error!("Failed to open cookies file {}", e);
@@ -31,6 +34,12 @@ pub fn save_cookies(cookies: &[u8]) {
     }
 }
 
+/// Time since the cookies were last saved, which happens on every authentication.
+pub fn cookies_age() -> Option<Duration> {
+    let modified = std::fs::metadata(COOKIES_FILE).and_then(|metadata| metadata.modified()).ok()?;
+    modified.elapsed().ok()
+}
+
 pub fn cookies_session_id(cookies: &str) -> Option<String> {
     let re = Regex::new(r#"JSESSIONID="(.*?)"(?:;|$)"#).unwrap();
     info!("Checking cookie {}", cookies);
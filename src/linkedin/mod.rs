@@ -1,2 +1,3 @@
 pub mod api;
+pub mod profile;
 pub mod web_driver;
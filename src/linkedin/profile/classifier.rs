@@ -0,0 +1,367 @@
+use crate::linkedin::web_driver::enums::{JobFunction, SeniorityLevel};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, ToSchema)]
+pub struct Classification {
+    pub seniority: Option<SeniorityLevel>,
+    pub function: Option<JobFunction>,
+}
+
+// Keywords are matched against whole words of the title after lowercasing and folding diacritics, so
+// they are written without diacritics. Multi word keywords match consecutive words. A leading `*`
+// matches any word ending with the keyword and a trailing `*` any word starting with it, which covers
+// German compounds such as "Vertriebsleiter".
+#[rustfmt::skip]
+const SENIORITY_RULES: &[(SeniorityLevel, &[&str])] = &[
+    (
+        SeniorityLevel::Owner,
+        &[
+            "owner", "founder", "cofounder", "majitel", "zakladatel", "spoluzakladatel", "inhaber", "grunder", "mitgrunder",
+            "wlasciciel", "zalozyciel", "wspolzalozyciel",
+        ],
+    ),
+    (
+        SeniorityLevel::CXO,
+        &[
+            "ceo", "cto", "cfo", "coo", "cio", "cmo", "cpo", "ciso", "chief", "president", "managing director", "konatel",
+            "generalny riaditel", "vykonny riaditel", "prezident", "jednatel", "generalni reditel", "geschaftsfuhrer", "vorstand",
+            "prasident", "prezes",
+        ],
+    ),
+    (
+        SeniorityLevel::VicePresident,
+        &["vice president", "vp", "svp", "evp", "avp", "viceprezident", "vizeprasident", "wiceprezes"],
+    ),
+    (
+        SeniorityLevel::Director,
+        &[
+            "director", "head of", "head", "riaditel", "reditel", "direktor", "abteilungsleiter", "bereichsleiter", "dyrektor",
+        ],
+    ),
+    (
+        SeniorityLevel::ExperienceManager,
+        &["manager", "manazer", "veduci", "vedouci", "leiter", "*leiter", "kierownik", "menedzer"],
+    ),
+    (
+        SeniorityLevel::EntryLevelManager,
+        &[
+            "team lead", "team leader", "tech lead", "lead", "supervisor", "teamleader", "teamleiter", "gruppenleiter", "koordinator",
+            "coordinator",
+        ],
+    ),
+    (
+        SeniorityLevel::Strategic,
+        &["principal", "staff", "architect", "architekt", "distinguished", "fellow", "expert", "expertka"],
+    ),
+    (SeniorityLevel::Senior, &["senior", "sr", "starszy", "seniorny"]),
+    (
+        SeniorityLevel::EntryLevel,
+        &["junior", "jr", "associate", "assistant", "asistent", "asistentka", "absolvent", "graduate", "mlodszy", "mladsi", "einsteiger"],
+    ),
+    (
+        SeniorityLevel::InTraining,
+        &[
+            "intern", "internship", "trainee", "apprentice", "student", "stazista", "stazistka", "praktikant", "praktikantka",
+            "praktikantin", "werkstudent", "auszubildender", "azubi", "stazysta", "praktykant",
+        ],
+    ),
+];
+
+// Ordered from specific to generic: at the same keyword length, the first rule in the table wins.
+#[rustfmt::skip]
+const FUNCTION_RULES: &[(JobFunction, &[&str])] = &[
+    (
+        JobFunction::ProductManagement,
+        &["product manager", "product management", "product owner", "product lead", "produktovy manazer", "produktmanager"],
+    ),
+    (
+        JobFunction::ProgramAndProjectManagement,
+        &[
+            "project manager", "project management", "program manager", "programme manager", "delivery manager", "scrum master", "projektovy manazer",
+            "projektovy manager", "projektleiter", "kierownik projektu", "pmo", "projektmanager",
+        ],
+    ),
+    (
+        JobFunction::QualityAssurance,
+        &["qa", "quality assurance", "quality", "tester", "testing", "test", "sdet", "kvalita", "qualitat*", "jakosc", "testerka"],
+    ),
+    (
+        JobFunction::CustomerSuccessAndSupport,
+        &[
+            "customer success", "customer support", "customer service", "customer care", "support", "helpdesk", "service desk",
+            "zakaznicka podpora", "zakaznicky servis", "kundenservice", "kundenbetreuer", "obsluga klienta",
+        ],
+    ),
+    (
+        JobFunction::BusinessDevelopment,
+        &["business development", "bizdev", "partnerships", "biznes development", "geschaftsentwicklung", "rozvoj obchodu"],
+    ),
+    (
+        JobFunction::Sales,
+        &[
+            "account executive", "account manager", "key account", "sales", "salesman", "obchodnik", "obchodny", "obchodni",
+            "vertrieb*", "*vertrieb", "verkaufer", "handlowiec", "sprzedaz", "predajca", "prodejce",
+        ],
+    ),
+    (
+        JobFunction::Marketing,
+        &["marketing*", "seo", "sem", "brand", "growth", "cmo", "marketer*", "content"],
+    ),
+    (
+        JobFunction::MediaAndCommunications,
+        &[
+            "pr", "communications", "communication", "journalist", "editor", "copywriter", "redaktor", "redaktorka", "novinar",
+            "journalistin", "dziennikarz", "spokesperson", "hovorca", "mluvci", "komunikace", "komunikacia", "kommunikation",
+        ],
+    ),
+    (
+        JobFunction::HumanResources,
+        &[
+            "hr", "human resources", "recruiter", "recruitment", "recruiting", "talent acquisition", "people partner",
+            "personalista", "personalistka", "naborar", "naborarka", "personalreferent", "personal*", "rekruter", "headhunter",
+        ],
+    ),
+    (
+        JobFunction::Accounting,
+        &["accountant", "accounting", "bookkeeper", "auditor", "audit", "uctovnik", "uctovnicka", "ucetni", "buchhalter", "ksiegowy", "ksiegowa"],
+    ),
+    (
+        JobFunction::Finance,
+        &[
+            "finance", "financial", "cfo", "controller", "controlling", "treasury", "investment", "banker", "financny", "financni",
+            "finanz*", "finansowy",
+        ],
+    ),
+    (
+        JobFunction::Legal,
+        &[
+            "lawyer", "attorney", "counsel", "legal", "paralegal", "advokat", "advokatka", "pravnik", "pravnicka", "jurist",
+            "juristin", "rechtsanwalt", "prawnik", "radca prawny",
+        ],
+    ),
+    (
+        JobFunction::Purchasing,
+        &["purchasing", "procurement", "buyer", "nakupca", "nakupci", "nakup", "einkaufer", "einkauf", "zakupy", "kupiec"],
+    ),
+    (
+        JobFunction::Operations,
+        &[
+            "operations", "operation", "coo", "logistics", "supply chain", "warehouse", "prevadzka", "provoz", "*logistik",
+            "logistyka", "logistyk",
+        ],
+    ),
+    (
+        JobFunction::RealEstate,
+        &["real estate", "realtor", "property", "reality", "makler", "realitny", "realitni", "immobilien*", "nieruchomosci"],
+    ),
+    (
+        JobFunction::HealthServices,
+        &["doctor", "nurse", "physician", "pharmacist", "therapist", "lekar", "lekarka", "sestra", "arzt", "arztin", "lekarz", "pielegniarka"],
+    ),
+    (
+        JobFunction::Education,
+        &[
+            "teacher", "lecturer", "professor", "tutor", "ucitel", "ucitelka", "lektor", "lektorka", "docent", "pedagog", "lehrer",
+            "lehrerin", "nauczyciel", "wykladowca", "profesor",
+        ],
+    ),
+    (
+        JobFunction::Research,
+        &["research", "researcher", "scientist", "vyskumnik", "vyzkumnik", "vedec", "vedecky", "forscher", "forscherin", "naukowiec", "badacz"],
+    ),
+    (
+        JobFunction::CommunityAndSocialServices,
+        &["social worker", "community", "socialny pracovnik", "socialni pracovnik", "sozialarbeiter", "pracownik socjalny"],
+    ),
+    (
+        JobFunction::ArtsAndDesign,
+        &[
+            "designer", "design", "ux", "ui", "graphic", "illustrator", "art director", "dizajner", "grafik", "grafickar",
+            "gestalter", "*designer", "projektant",
+        ],
+    ),
+    (
+        JobFunction::Administrative,
+        &["administrative", "secretary", "receptionist", "office manager", "sekretarka", "sekretar", "recepcna", "recepcni", "sekretarin"],
+    ),
+    (
+        JobFunction::InformationTechnology,
+        &[
+            "it", "system administrator", "sysadmin", "network", "infrastructure", "security", "database", "dba", "cloud", "spravca",
+            "spravce", "administrator", "cio",
+        ],
+    ),
+    (
+        JobFunction::Engineering,
+        &[
+            "engineer", "engineering", "developer", "programmer", "software", "devops", "sre", "cto", "inzinier", "inzenyr", "vyvojar",
+            "programator", "entwickler", "*entwickler", "ingenieur", "*ingenieur", "programista", "inzynier", "architect", "architekt",
+        ],
+    ),
+    (
+        JobFunction::Entrepreneurship,
+        &["founder", "cofounder", "entrepreneur", "podnikatel", "zakladatel", "unternehmer", "grunder", "przedsiebiorca", "zalozyciel"],
+    ),
+    (
+        JobFunction::Consulting,
+        &["consultant", "consulting", "advisor", "adviser", "konzultant", "konzultantka", "poradca", "poradce", "berater", "*berater", "doradca"],
+    ),
+];
+
+enum TokenPattern {
+    Exact(String),
+    Prefix(String),
+    Suffix(String),
+}
+
+impl TokenPattern {
+    fn new(word: &str) -> Self {
+        let word = fold_diacritics(word);
+        if let Some(suffix) = word.strip_prefix('*') {
+            TokenPattern::Suffix(suffix.to_string())
+        } else if let Some(prefix) = word.strip_suffix('*') {
+            TokenPattern::Prefix(prefix.to_string())
+        } else {
+            TokenPattern::Exact(word)
+        }
+    }
+
+    fn is_affix(&self) -> bool {
+        !matches!(self, TokenPattern::Exact(_))
+    }
+
+    fn matches(&self, token: &str) -> bool {
+        match self {
+            TokenPattern::Exact(word) => token == word,
+            TokenPattern::Prefix(prefix) => token.starts_with(prefix.as_str()),
+            TokenPattern::Suffix(suffix) => token.ends_with(suffix.as_str()),
+        }
+    }
+}
+
+struct Keyword<T> {
+    label: T,
+    patterns: Vec<TokenPattern>,
+}
+
+/// Rule table compiled into keywords sorted so that longer and exact keywords are tried first. A matched
+/// keyword consumes its words, so "vice president" is never also matched as "president".
+struct RuleSet<T> {
+    keywords: Vec<Keyword<T>>,
+}
+
+impl<T: Copy> RuleSet<T> {
+    fn new(rules: &[(T, &[&str])]) -> Self {
+        let mut keywords: Vec<(usize, Keyword<T>)> = Vec::new();
+        for (index, (label, words)) in rules.iter().enumerate() {
+            for word in words.iter() {
+                let patterns = word.split_whitespace().map(TokenPattern::new).collect();
+                keywords.push((index, Keyword { label: *label, patterns }));
+            }
+        }
+        keywords.sort_by_key(|(index, keyword)| {
+            let is_affix = keyword.patterns.iter().any(TokenPattern::is_affix);
+            (std::cmp::Reverse(keyword.patterns.len()), is_affix, *index)
+        });
+        Self {
+            keywords: keywords.into_iter().map(|(_, keyword)| keyword).collect(),
+        }
+    }
+
+    fn matches(&self, tokens: &[String]) -> Vec<T> {
+        let mut consumed = vec![false; tokens.len()];
+        let mut labels = Vec::new();
+        for keyword in self.keywords.iter() {
+            let length = keyword.patterns.len();
+            if length > tokens.len() {
+                continue;
+            }
+            for start in 0..=tokens.len() - length {
+                let window = start..start + length;
+                if consumed[window.clone()].iter().any(|consumed| *consumed) {
+                    continue;
+                }
+                let is_match = keyword
+                    .patterns
+                    .iter()
+                    .zip(tokens[window.clone()].iter())
+                    .all(|(pattern, token)| pattern.matches(token));
+                if is_match {
+                    consumed[window].iter_mut().for_each(|consumed| *consumed = true);
+                    labels.push(keyword.label);
+                }
+            }
+        }
+        labels
+    }
+}
+
+pub struct TitleClassifier {
+    seniority: RuleSet<SeniorityLevel>,
+    function: RuleSet<JobFunction>,
+}
+
+impl TitleClassifier {
+    pub fn new() -> Self {
+        Self {
+            seniority: RuleSet::new(SENIORITY_RULES),
+            function: RuleSet::new(FUNCTION_RULES),
+        }
+    }
+
+    /// Picks the most senior level mentioned in the title and the function of the most specific keyword.
+    pub fn classify(&self, title: &str) -> Classification {
+        let tokens = tokenize(title);
+        Classification {
+            seniority: self.seniority.matches(&tokens).into_iter().min(),
+            function: self.function.matches(&tokens).into_iter().next(),
+        }
+    }
+}
+
+impl Default for TitleClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static CLASSIFIER: OnceLock<TitleClassifier> = OnceLock::new();
+
+pub fn classify_title(title: &str) -> Classification {
+    CLASSIFIER.get_or_init(TitleClassifier::new).classify(title)
+}
+
+fn fold_diacritics(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match c {
+            'á' | 'ä' | 'ą' => folded.push('a'),
+            'č' | 'ć' => folded.push('c'),
+            'ď' => folded.push('d'),
+            'é' | 'ě' | 'ę' => folded.push('e'),
+            'í' => folded.push('i'),
+            'ĺ' | 'ľ' | 'ł' => folded.push('l'),
+            'ň' | 'ń' => folded.push('n'),
+            'ó' | 'ô' | 'ö' => folded.push('o'),
+            'ŕ' | 'ř' => folded.push('r'),
+            'š' | 'ś' => folded.push('s'),
+            'ť' => folded.push('t'),
+            'ú' | 'ů' | 'ü' => folded.push('u'),
+            'ý' => folded.push('y'),
+            'ž' | 'ź' | 'ż' => folded.push('z'),
+            'ß' => folded.push_str("ss"),
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
+fn tokenize(title: &str) -> Vec<String> {
+    fold_diacritics(title)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,152 @@
+use crate::linkedin::profile::{Date, Position, Profile, TimePeriod};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum DatePrecision {
+    Year,
+    Month,
+    Day,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct NormalizedDate {
+    pub date: NaiveDate,
+    pub precision: DatePrecision,
+}
+
+/// Months since year 0, used to compare and merge periods at month granularity.
+fn month_index(year: i32, month: u32) -> i32 {
+    year * 12 + month as i32 - 1
+}
+
+impl Date {
+    pub fn precision(&self) -> DatePrecision {
+        match (self.month, self.day) {
+            (Some(_), Some(_)) => DatePrecision::Day,
+            (Some(_), None) => DatePrecision::Month,
+            _ => DatePrecision::Year,
+        }
+    }
+
+    /// Earliest calendar day the date can refer to, e.g. 2020-01-01 for a bare `2020`.
+    pub fn first_day(&self) -> Option<NaiveDate> {
+        match self.precision() {
+            DatePrecision::Year => NaiveDate::from_ymd_opt(self.year, 1, 1),
+            DatePrecision::Month => NaiveDate::from_ymd_opt(self.year, self.month?, 1),
+            DatePrecision::Day => NaiveDate::from_ymd_opt(self.year, self.month?, self.day?),
+        }
+    }
+
+    /// Latest calendar day the date can refer to, e.g. 2020-12-31 for a bare `2020`.
+    pub fn last_day(&self) -> Option<NaiveDate> {
+        match self.precision() {
+            DatePrecision::Year => NaiveDate::from_ymd_opt(self.year, 12, 31),
+            DatePrecision::Month => {
+                let month = self.month?;
+                let next_month = if month == 12 {
+                    NaiveDate::from_ymd_opt(self.year + 1, 1, 1)?
+                } else {
+                    NaiveDate::from_ymd_opt(self.year, month + 1, 1)?
+                };
+                next_month.pred_opt()
+            }
+            DatePrecision::Day => self.first_day(),
+        }
+    }
+
+    pub fn normalize(&self) -> Option<NormalizedDate> {
+        Some(NormalizedDate {
+            date: self.first_day()?,
+            precision: self.precision(),
+        })
+    }
+}
+
+impl TimePeriod {
+    pub fn start_date(&self) -> Option<NormalizedDate> {
+        self.start.as_ref().and_then(Date::normalize)
+    }
+
+    pub fn end_date(&self) -> Option<NormalizedDate> {
+        self.end.as_ref().and_then(Date::normalize)
+    }
+
+    /// A period with a start but no end is still ongoing ("Present").
+    pub fn is_current(&self) -> bool {
+        self.start.is_some() && self.end.is_none()
+    }
+
+    /// Inclusive range of month indices covered by the period. Year precision starts in January and
+    /// ends in December, open and future ends are capped at `today`. `None` if the start is unknown
+    /// or the end precedes the start.
+    fn month_range(&self, today: NaiveDate) -> Option<(i32, i32)> {
+        let start = self.start.as_ref()?;
+        let start_index = month_index(start.year, start.month.unwrap_or(1));
+        let today_index = month_index(today.year(), today.month());
+        let end_index = match self.end.as_ref() {
+            Some(end) => month_index(end.year, end.month.unwrap_or(12)).min(today_index),
+            None => today_index,
+        };
+        if end_index < start_index {
+            return None;
+        }
+        Some((start_index, end_index))
+    }
+
+    /// Duration in whole months, counting both the first and the last month like LinkedIn does,
+    /// so `Jan 2020 – Mar 2020` is 3 months.
+    pub fn duration_months(&self, today: NaiveDate) -> Option<u32> {
+        self.month_range(today).map(|(start, end)| (end - start + 1) as u32)
+    }
+}
+
+impl Position {
+    pub fn is_current(&self) -> bool {
+        self.time_period.as_ref().is_some_and(TimePeriod::is_current)
+    }
+
+    pub fn duration_months(&self, today: NaiveDate) -> Option<u32> {
+        self.time_period.as_ref().and_then(|period| period.duration_months(today))
+    }
+}
+
+impl Profile {
+    pub fn current_positions(&self) -> Vec<&Position> {
+        self.positions.iter().filter(|position| position.is_current()).collect()
+    }
+
+    /// Months of experience across all positions. Overlapping positions, e.g. a side job next to a
+    /// full time role, are merged so the same month is never counted twice.
+    pub fn total_experience_months(&self, today: NaiveDate) -> u32 {
+        let mut ranges: Vec<(i32, i32)> = self
+            .positions
+            .iter()
+            .filter_map(|position| position.time_period.as_ref())
+            .filter_map(|period| period.month_range(today))
+            .collect();
+        ranges.sort_unstable();
+
+        let mut total = 0;
+        let mut current: Option<(i32, i32)> = None;
+        for (start, end) in ranges {
+            current = match current {
+                Some((current_start, current_end)) if start <= current_end + 1 => Some((current_start, current_end.max(end))),
+                Some((current_start, current_end)) => {
+                    total += current_end - current_start + 1;
+                    Some((start, end))
+                }
+                None => Some((start, end)),
+            };
+        }
+        if let Some((start, end)) = current {
+            total += end - start + 1;
+        }
+        total as u32
+    }
+
+    pub fn years_of_experience(&self, today: NaiveDate) -> f32 {
+        self.total_experience_months(today) as f32 / 12f32
+    }
+}
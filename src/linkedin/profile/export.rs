@@ -0,0 +1,136 @@
+use crate::azure::json::CrawledProfiles;
+use crate::errors::CrawlerError::ParseError;
+use crate::errors::CrawlerResult;
+use crate::linkedin::profile::Profile;
+use chrono::NaiveDate;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const CSV_HEADER: [&str; 16] = [
+    "profile_id",
+    "first_name",
+    "last_name",
+    "headline",
+    "seniority",
+    "function",
+    "current_title",
+    "current_company",
+    "years_of_experience",
+    "location",
+    "country",
+    "industry",
+    "skills",
+    "languages",
+    "url",
+    "sales_url",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Jsonl,
+    /// One row per profile, list columns are joined with `; `
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ExportFormat::Json),
+            "jsonl" => Ok(ExportFormat::Jsonl),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("Unknown export format {}, expected json, jsonl or csv", s)),
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportFormat::Json => write!(f, "json"),
+            ExportFormat::Jsonl => write!(f, "jsonl"),
+            ExportFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// Reads crawled profiles as published to the manager (`{"profiles": [...]}`), as a plain array or
+/// as one profile per line.
+pub fn read_profiles(input: &str) -> CrawlerResult<Vec<Profile>> {
+    let trimmed = input.trim_start();
+    if trimmed.starts_with('[') {
+        return serde_json::from_str(trimmed).map_err(|e| ParseError(format!("Failed to parse profiles {}", e)));
+    }
+    if let Ok(crawled) = serde_json::from_str::<CrawledProfiles>(trimmed) {
+        return Ok(crawled.profiles);
+    }
+    trimmed
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| ParseError(format!("Failed to parse profile on line {} {}", i + 1, e))))
+        .collect()
+}
+
+pub fn export_profiles(profiles: &[Profile], format: ExportFormat, today: NaiveDate) -> String {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(profiles).unwrap_or_default(),
+        ExportFormat::Jsonl => profiles
+            .iter()
+            .map(|profile| serde_json::to_string(profile).unwrap_or_default() + "\n")
+            .collect(),
+        ExportFormat::Csv => {
+            let mut out = csv_row(CSV_HEADER.iter().map(|column| column.to_string()));
+            for profile in profiles {
+                out.push_str(&csv_row(csv_fields(profile, today)));
+            }
+            out
+        }
+    }
+}
+
+fn csv_fields(profile: &Profile, today: NaiveDate) -> Vec<String> {
+    let current = profile.current_positions().into_iter().next();
+    let classification = &profile.headline_classification;
+    let skills: Vec<&str> = profile
+        .skills
+        .iter()
+        .map(|skill| skill.canonical_name.as_deref().unwrap_or(&skill.name))
+        .collect();
+    let languages: Vec<&str> = profile.languages.iter().map(|language| language.name.as_str()).collect();
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    vec![
+        text(&profile.profile_id),
+        profile.first_name.clone(),
+        profile.last_name.clone(),
+        text(&profile.headline),
+        classification.seniority.map(|seniority| seniority.to_string()).unwrap_or_default(),
+        classification.function.map(|function| function.to_string()).unwrap_or_default(),
+        current.map(|position| position.title.clone()).unwrap_or_default(),
+        current.and_then(|position| position.company.clone()).unwrap_or_default(),
+        format!("{:.1}", profile.years_of_experience(today)),
+        text(&profile.location),
+        text(&profile.country),
+        text(&profile.industry),
+        skills.join("; "),
+        languages.join("; "),
+        text(&profile.url),
+        text(&profile.sales_url),
+    ]
+}
+
+fn csv_row(fields: impl IntoIterator<Item = String>) -> String {
+    let fields: Vec<String> = fields.into_iter().map(|field| csv_escape(&field)).collect();
+    fields.join(",") + "\n"
+}
+
+/// Quotes fields containing separators, quotes or line breaks as RFC 4180 expects.
+pub fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
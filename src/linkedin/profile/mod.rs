@@ -0,0 +1,344 @@
+pub mod classifier;
+pub mod dates;
+pub mod export;
+pub mod skills;
+
+use crate::linkedin::api::json;
+use crate::linkedin::profile::classifier::{classify_title, Classification};
+use crate::linkedin::profile::skills::{skill_taxonomy, SkillCategory};
+use crate::linkedin::web_driver::profiles;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Bumped whenever a field is removed or changes meaning. Adding optional fields keeps the version.
+pub const PROFILE_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileSource {
+    Api,
+    WebDriver,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Date {
+    pub year: i32,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, ToSchema)]
+pub struct TimePeriod {
+    pub start: Option<Date>,
+    pub end: Option<Date>,
+    /// Unparsed text as rendered on the page, only set for web driver profiles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Profile {
+    pub schema_version: u32,
+    pub source: ProfileSource,
+    pub profile_id: Option<String>,
+    pub first_name: String,
+    pub last_name: String,
+    pub headline: Option<String>,
+    #[serde(default)]
+    pub headline_classification: Classification,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub country: Option<String>,
+    pub industry: Option<String>,
+    pub picture_url: Option<String>,
+    pub url: Option<String>,
+    pub sales_url: Option<String>,
+    pub positions: Vec<Position>,
+    pub education: Vec<Education>,
+    pub skills: Vec<Skill>,
+    pub languages: Vec<Language>,
+    pub certifications: Vec<Certification>,
+    pub projects: Vec<Project>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Position {
+    pub title: String,
+    pub company: Option<String>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub time_period: Option<TimePeriod>,
+    #[serde(default)]
+    pub classification: Classification,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Education {
+    pub school: Option<String>,
+    pub degree: Option<String>,
+    pub field_of_study: Option<String>,
+    pub time_period: Option<TimePeriod>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Skill {
+    pub name: String,
+    pub endorsements: Option<u16>,
+    #[serde(default)]
+    pub canonical_id: Option<String>,
+    #[serde(default)]
+    pub canonical_name: Option<String>,
+    #[serde(default)]
+    pub category: Option<SkillCategory>,
+}
+
+impl Skill {
+    /// Keeps the raw name and attaches the taxonomy entry it maps to, if any.
+    pub fn new(name: String, endorsements: Option<u16>) -> Self {
+        let canonical = skill_taxonomy().lookup(&name);
+        Self {
+            canonical_id: canonical.map(|skill| skill.id.clone()),
+            canonical_name: canonical.map(|skill| skill.name.clone()),
+            category: canonical.map(|skill| skill.category),
+            name,
+            endorsements,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Language {
+    pub name: String,
+    pub proficiency: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Certification {
+    pub name: String,
+    pub authority: Option<String>,
+    pub url: Option<String>,
+    pub time_period: Option<TimePeriod>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Project {
+    pub title: String,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub time_period: Option<TimePeriod>,
+}
+
+fn non_empty(value: String) -> Option<String> {
+    if value.trim().is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+impl From<json::Date> for Date {
+    fn from(date: json::Date) -> Self {
+        Self {
+            year: date.year,
+            month: date.month.and_then(|month| u32::try_from(month).ok()),
+            day: date.day.and_then(|day| u32::try_from(day).ok()),
+        }
+    }
+}
+
+impl From<json::TimePeriod> for TimePeriod {
+    fn from(period: json::TimePeriod) -> Self {
+        Self {
+            start: period.start_date.map(Date::from),
+            end: period.end_date.map(Date::from),
+            raw: None,
+        }
+    }
+}
+
+impl From<profiles::Interval> for TimePeriod {
+    fn from(interval: profiles::Interval) -> Self {
+        let start = interval.start.trim();
+        let end = interval.end.trim();
+        let raw = match (start.is_empty(), end.is_empty()) {
+            (true, true) => None,
+            (false, true) => Some(start.to_string()),
+            _ => Some(format!("{} – {}", start, end)),
+        };
+        match interval.to_date_range(None) {
+            Some(range) => Self { raw, ..Self::from(range) },
+            None => {
+                if let Some(raw) = raw.as_ref() {
+                    warn!("Failed to parse date range {}", raw);
+                }
+                Self {
+                    start: None,
+                    end: None,
+                    raw,
+                }
+            }
+        }
+    }
+}
+
+impl From<json::Profile> for Profile {
+    fn from(profile: json::Profile) -> Self {
+        let view = profile.profile;
+        let positions = profile
+            .position_view
+            .elements
+            .into_iter()
+            .map(|position| Position {
+                classification: classify_title(&position.title),
+                title: position.title,
+                company: position.company_name,
+                location: position.location_name,
+                description: position.description,
+                time_period: position.time_period.map(TimePeriod::from),
+            })
+            .collect();
+        let education = profile
+            .education_view
+            .elements
+            .into_iter()
+            .map(|education| Education {
+                school: education.school_name,
+                degree: education.degree_name,
+                field_of_study: education.field_of_study,
+                time_period: education.time_period.map(TimePeriod::from),
+            })
+            .collect();
+        let skills = profile
+            .skill_view
+            .elements
+            .into_iter()
+            .map(|skill| Skill::new(skill.name, None))
+            .collect();
+        let languages = profile
+            .language_view
+            .elements
+            .into_iter()
+            .map(|language| Language {
+                name: language.name,
+                proficiency: language.proficiency,
+            })
+            .collect();
+        let certifications = profile
+            .certification_view
+            .elements
+            .into_iter()
+            .map(|certificate| Certification {
+                name: certificate.name,
+                authority: certificate.authority,
+                url: certificate.url,
+                time_period: certificate.time_period.map(TimePeriod::from),
+            })
+            .collect();
+        let projects = profile
+            .project_view
+            .elements
+            .into_iter()
+            .map(|project| Project {
+                title: project.title,
+                description: project.description,
+                url: project.url,
+                time_period: project.time_period.map(TimePeriod::from),
+            })
+            .collect();
+
+        Self {
+            schema_version: PROFILE_SCHEMA_VERSION,
+            source: ProfileSource::Api,
+            url: Some(format!("https://www.linkedin.com/in/{}", profile.profile_urn)),
+            profile_id: Some(profile.profile_urn),
+            first_name: view.first_name,
+            last_name: view.last_name,
+            headline_classification: classify_title(&view.headline),
+            headline: non_empty(view.headline),
+            summary: view.summary,
+            location: view.location_name,
+            country: non_empty(view.geo_country_name),
+            industry: view.industry_name,
+            picture_url: view.picture_url,
+            sales_url: None,
+            positions,
+            education,
+            skills,
+            languages,
+            certifications,
+            projects,
+        }
+    }
+}
+
+impl From<profiles::Profile> for Profile {
+    fn from(profile: profiles::Profile) -> Self {
+        let (first_name, last_name) = match profile.name.trim().split_once(' ') {
+            Some((first_name, last_name)) => (first_name.to_string(), last_name.trim().to_string()),
+            None => (profile.name.trim().to_string(), String::new()),
+        };
+        let profile_id = profile.url.trim_end_matches('/').rsplit_once("/in/").map(|(_, id)| id.to_string());
+        let positions = profile
+            .experience
+            .unwrap_or_default()
+            .into_iter()
+            .map(|experience| Position {
+                classification: classify_title(&experience.position),
+                title: experience.position,
+                company: None,
+                location: None,
+                description: None,
+                time_period: Some(TimePeriod::from(experience.interval)),
+            })
+            .collect();
+        let education = profile
+            .education
+            .unwrap_or_default()
+            .into_iter()
+            .map(|education| Education {
+                school: non_empty(education.title),
+                degree: non_empty(education.degree),
+                field_of_study: non_empty(education.field),
+                time_period: Some(TimePeriod::from(education.interval)),
+            })
+            .collect();
+        let skills = profile
+            .skills
+            .unwrap_or_default()
+            .into_iter()
+            .map(|skill| Skill::new(skill.name, Some(skill.endorsements)))
+            .collect();
+        let languages = profile
+            .languages
+            .unwrap_or_default()
+            .into_iter()
+            .map(|language| Language {
+                name: language.language,
+                proficiency: non_empty(language.fluency),
+            })
+            .collect();
+
+        Self {
+            schema_version: PROFILE_SCHEMA_VERSION,
+            source: ProfileSource::WebDriver,
+            profile_id,
+            first_name,
+            last_name,
+            headline_classification: classify_title(&profile.description),
+            headline: non_empty(profile.description),
+            summary: profile.about,
+            location: non_empty(profile.location),
+            country: None,
+            industry: None,
+            picture_url: non_empty(profile.profile_picture_url),
+            url: non_empty(profile.url),
+            sales_url: profile.sales_url,
+            positions,
+            education,
+            skills,
+            languages,
+            certifications: Vec::new(),
+            projects: Vec::new(),
+        }
+    }
+}
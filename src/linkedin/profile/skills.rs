@@ -0,0 +1,149 @@
+use crate::env::env_skill_taxonomy_path;
+use crate::errors::CrawlerError::ParseError;
+use crate::errors::CrawlerResult;
+use crate::linkedin::api::json;
+use crate::linkedin::web_driver::profiles;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use utoipa::ToSchema;
+
+const BUNDLED_TAXONOMY: &str = include_str!("skills.json");
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SkillCategory {
+    ProgrammingLanguage,
+    Framework,
+    Database,
+    Cloud,
+    Devops,
+    Tool,
+    Methodology,
+    Data,
+    Design,
+    Business,
+    SoftSkill,
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CanonicalSkill {
+    pub id: String,
+    pub name: String,
+    pub category: SkillCategory,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+pub struct SkillTaxonomy {
+    skills: Vec<CanonicalSkill>,
+    aliases: HashMap<String, usize>,
+}
+
+/// Lowercases, drops parenthesised qualifiers like "(Programming Language)" and collapses whitespace.
+pub fn normalize_skill_name(name: &str) -> String {
+    let mut stripped = String::with_capacity(name.len());
+    let mut depth = 0;
+    for c in name.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            c if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    let stripped = if stripped.trim().is_empty() { name } else { stripped.as_str() };
+    stripped.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+}
+
+impl SkillTaxonomy {
+    pub fn empty() -> Self {
+        Self {
+            skills: Vec::new(),
+            aliases: HashMap::new(),
+        }
+    }
+
+    pub fn bundled() -> Self {
+        let mut taxonomy = Self::empty();
+        fatal_unwrap_e!(taxonomy.extend_from_json(BUNDLED_TAXONOMY), "Bundled skill taxonomy is invalid {}");
+        taxonomy
+    }
+
+    /// Merges entries into the taxonomy. An entry with an existing id replaces its name and category
+    /// and adds its aliases, so user files can both add new skills and extend bundled ones.
+    pub fn extend_from_json(&mut self, raw: &str) -> CrawlerResult<()> {
+        let entries: Vec<CanonicalSkill> = match serde_json::from_str(raw) {
+            Ok(entries) => entries,
+            Err(e) => return Err(ParseError(format!("Failed to parse skill taxonomy {}", e))),
+        };
+        for entry in entries {
+            let index = match self.skills.iter().position(|skill| skill.id == entry.id) {
+                Some(index) => {
+                    let skill = &mut self.skills[index];
+                    skill.name = entry.name.clone();
+                    skill.category = entry.category;
+                    skill.aliases.extend(entry.aliases.iter().cloned());
+                    index
+                }
+                None => {
+                    self.skills.push(entry.clone());
+                    self.skills.len() - 1
+                }
+            };
+            let keys = [entry.id.as_str(), entry.name.as_str()]
+                .into_iter()
+                .chain(entry.aliases.iter().map(String::as_str))
+                .map(normalize_skill_name);
+            for key in keys {
+                self.aliases.insert(key, index);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn extend_from_file(&mut self, path: &str) -> CrawlerResult<()> {
+        let raw = match std::fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(e) => return Err(ParseError(format!("Failed to read skill taxonomy {} {}", path, e))),
+        };
+        self.extend_from_json(&raw)
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<&CanonicalSkill> {
+        self.aliases.get(&normalize_skill_name(name)).map(|index| &self.skills[*index])
+    }
+
+    pub fn skills(&self) -> &[CanonicalSkill] {
+        &self.skills
+    }
+}
+
+static TAXONOMY: OnceLock<SkillTaxonomy> = OnceLock::new();
+
+/// Bundled taxonomy extended with the file from `SKILL_TAXONOMY_PATH`, if set.
+pub fn skill_taxonomy() -> &'static SkillTaxonomy {
+    TAXONOMY.get_or_init(|| {
+        let mut taxonomy = SkillTaxonomy::bundled();
+        if let Some(path) = env_skill_taxonomy_path() {
+            match taxonomy.extend_from_file(&path) {
+                Ok(_) => info!("Loaded skill taxonomy extension {}", path),
+                Err(e) => error!("{}", e),
+            }
+        }
+        taxonomy
+    })
+}
+
+impl json::Skill {
+    pub fn canonical(&self) -> Option<&'static CanonicalSkill> {
+        skill_taxonomy().lookup(&self.name)
+    }
+}
+
+impl profiles::Skill {
+    pub fn canonical(&self) -> Option<&'static CanonicalSkill> {
+        skill_taxonomy().lookup(&self.name)
+    }
+}
@@ -0,0 +1,308 @@
+use crate::linkedin::profile::{Date, TimePeriod};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Locale {
+    En,
+    Sk,
+    Cz,
+    De,
+    Pl,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateRange {
+    pub start: Option<Date>,
+    pub end: Option<Date>,
+    pub is_current: bool,
+    /// Duration as rendered next to the range, e.g. "2 yrs 3 mos"
+    pub duration_months: Option<u32>,
+}
+
+impl From<DateRange> for TimePeriod {
+    fn from(range: DateRange) -> Self {
+        Self {
+            start: range.start,
+            end: range.end,
+            raw: None,
+        }
+    }
+}
+
+const RANGE_DELIMITERS: [char; 3] = ['–', '—', '-'];
+const DURATION_DELIMITERS: [char; 3] = ['·', '•', '\n'];
+
+impl Locale {
+    pub const ALL: [Locale; 5] = [Locale::En, Locale::Sk, Locale::Cz, Locale::De, Locale::Pl];
+
+    /// Month names as LinkedIn renders them, abbreviated and full, without trailing dots.
+    fn months(&self) -> [&'static [&'static str]; 12] {
+        match self {
+            Locale::En => [
+                &["jan", "january"],
+                &["feb", "february"],
+                &["mar", "march"],
+                &["apr", "april"],
+                &["may"],
+                &["jun", "june"],
+                &["jul", "july"],
+                &["aug", "august"],
+                &["sep", "sept", "september"],
+                &["oct", "october"],
+                &["nov", "november"],
+                &["dec", "december"],
+            ],
+            Locale::Sk => [
+                &["jan", "január", "januára"],
+                &["feb", "február", "februára"],
+                &["mar", "marec", "marca"],
+                &["apr", "apríl", "apríla"],
+                &["máj", "maj", "mája"],
+                &["jún", "jun", "júna"],
+                &["júl", "jul", "júla"],
+                &["aug", "august", "augusta"],
+                &["sep", "september", "septembra"],
+                &["okt", "október", "októbra"],
+                &["nov", "november", "novembra"],
+                &["dec", "december", "decembra"],
+            ],
+            Locale::Cz => [
+                &["led", "leden", "ledna"],
+                &["úno", "únor", "února"],
+                &["bře", "březen", "března"],
+                &["dub", "duben", "dubna"],
+                &["kvě", "květen", "května"],
+                &["čvn", "čer", "červen", "června"],
+                &["čvc", "črc", "červenec", "července"],
+                &["srp", "srpen", "srpna"],
+                &["zář", "září"],
+                &["říj", "říjen", "října"],
+                &["lis", "listopad", "listopadu"],
+                &["pro", "prosinec", "prosince"],
+            ],
+            Locale::De => [
+                &["jan", "januar"],
+                &["feb", "februar"],
+                &["mär", "märz", "mrz"],
+                &["apr", "april"],
+                &["mai"],
+                &["jun", "juni"],
+                &["jul", "juli"],
+                &["aug", "august"],
+                &["sep", "sept", "september"],
+                &["okt", "oktober"],
+                &["nov", "november"],
+                &["dez", "dezember"],
+            ],
+            Locale::Pl => [
+                &["sty", "styczeń", "stycznia"],
+                &["lut", "luty", "lutego"],
+                &["mar", "marzec", "marca"],
+                &["kwi", "kwiecień", "kwietnia"],
+                &["maj", "maja"],
+                &["cze", "czerwiec", "czerwca"],
+                &["lip", "lipiec", "lipca"],
+                &["sie", "sierpień", "sierpnia"],
+                &["wrz", "wrzesień", "września"],
+                &["paź", "październik", "października"],
+                &["lis", "listopad", "listopada"],
+                &["gru", "grudzień", "grudnia"],
+            ],
+        }
+    }
+
+    fn present(&self) -> &'static [&'static str] {
+        match self {
+            Locale::En => &["present", "now", "current"],
+            Locale::Sk => &["súčasnosť", "súčasnost", "dnes"],
+            Locale::Cz => &["současnost", "dosud", "dnes"],
+            Locale::De => &["heute", "gegenwart"],
+            Locale::Pl => &["obecnie", "teraz"],
+        }
+    }
+
+    fn year_units(&self) -> &'static [&'static str] {
+        match self {
+            Locale::En => &["yr", "yrs", "year", "years"],
+            Locale::Sk => &["r", "rok", "roky", "rokov"],
+            Locale::Cz => &["r", "rok", "roky", "let", "roků"],
+            Locale::De => &["j", "jahr", "jahre", "jahren"],
+            Locale::Pl => &["r", "rok", "lata", "lat"],
+        }
+    }
+
+    fn month_units(&self) -> &'static [&'static str] {
+        match self {
+            Locale::En => &["mo", "mos", "month", "months"],
+            Locale::Sk => &["mes", "mesiac", "mesiace", "mesiacov"],
+            Locale::Cz => &["měs", "měsíc", "měsíce", "měsíců"],
+            Locale::De => &["mon", "monat", "monate", "monaten"],
+            Locale::Pl => &["mies", "miesiąc", "miesiące", "miesięcy"],
+        }
+    }
+
+    fn month(&self, token: &str) -> Option<u32> {
+        self.months()
+            .iter()
+            .position(|aliases| aliases.contains(&token))
+            .map(|index| index as u32 + 1)
+    }
+}
+
+fn normalize_token(token: &str) -> String {
+    token
+        .trim_matches(|c: char| c == '.' || c == ',' || c == '(' || c == ')')
+        .to_lowercase()
+}
+
+fn tokens(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(normalize_token)
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+fn parse_year(token: &str) -> Option<i32> {
+    if token.len() != 4 {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Either a date or the "present" marker, followed by how many tokens were consumed.
+enum Endpoint {
+    Date(Date),
+    Present,
+}
+
+fn parse_endpoint(tokens: &[String], locale: Locale) -> Option<(Endpoint, usize)> {
+    let first = tokens.first()?;
+    if locale.present().contains(&first.as_str()) {
+        return Some((Endpoint::Present, 1));
+    }
+    if let Some(year) = parse_year(first) {
+        return Some((
+            Endpoint::Date(Date {
+                year,
+                month: None,
+                day: None,
+            }),
+            1,
+        ));
+    }
+    // 04/2016 and 04.2016
+    if let Some((month, year)) = first.split_once(['/', '.']) {
+        let month = month.parse::<u32>().ok().filter(|month| (1..=12).contains(month))?;
+        let year = parse_year(year)?;
+        return Some((
+            Endpoint::Date(Date {
+                year,
+                month: Some(month),
+                day: None,
+            }),
+            1,
+        ));
+    }
+    let month = locale.month(first)?;
+    let year = parse_year(tokens.get(1)?)?;
+    Some((
+        Endpoint::Date(Date {
+            year,
+            month: Some(month),
+            day: None,
+        }),
+        2,
+    ))
+}
+
+/// Parses "2 yrs 3 mos" style durations into months. Unknown units fail the whole duration.
+pub fn parse_duration(text: &str, locale: Locale) -> Option<u32> {
+    let tokens = tokens(text);
+    if tokens.is_empty() {
+        return None;
+    }
+    let mut months = 0;
+    let mut iter = tokens.iter();
+    while let Some(amount) = iter.next() {
+        let amount: u32 = amount.parse().ok()?;
+        let unit = iter.next()?;
+        if locale.year_units().contains(&unit.as_str()) {
+            months += amount * 12;
+        } else if locale.month_units().contains(&unit.as_str()) {
+            months += amount;
+        } else {
+            return None;
+        }
+    }
+    Some(months)
+}
+
+/// The range and whether its duration was understood too, an unreadable duration such as "less than a
+/// year" does not throw away the dates.
+fn parse_with_locale(text: &str, locale: Locale) -> Option<(DateRange, bool)> {
+    let (range, duration) = match text.split_once(|c| DURATION_DELIMITERS.contains(&c)) {
+        Some((range, duration)) => (range, Some(duration)),
+        None => (text, None),
+    };
+    let (start_text, end_text) = match range.split_once(|c| RANGE_DELIMITERS.contains(&c)) {
+        Some((start, end)) => (start, Some(end)),
+        None => (range, None),
+    };
+
+    let start_tokens = tokens(start_text);
+    let start = match parse_endpoint(&start_tokens, locale)? {
+        (Endpoint::Date(date), consumed) if consumed == start_tokens.len() => date,
+        _ => return None,
+    };
+
+    // Sales navigator renders the duration without a delimiter, e.g. "Jan 2020–Present 2 yrs 3 mos"
+    let mut duration_text = duration.map(str::to_string);
+    let (end, is_current) = match end_text {
+        Some(end_text) => {
+            let end_tokens = tokens(end_text);
+            let (endpoint, consumed) = parse_endpoint(&end_tokens, locale)?;
+            if consumed < end_tokens.len() {
+                if duration_text.is_some() {
+                    return None;
+                }
+                duration_text = Some(end_tokens[consumed..].join(" "));
+            }
+            match endpoint {
+                Endpoint::Date(date) => (Some(date), false),
+                Endpoint::Present => (None, true),
+            }
+        }
+        // A single date without a range, e.g. an education with only a graduation year
+        None => (Some(start.clone()), false),
+    };
+
+    let duration_months = duration_text
+        .as_deref()
+        .and_then(|duration_text| parse_duration(duration_text, locale));
+    let understood = duration_text.is_none() || duration_months.is_some();
+
+    let range = DateRange {
+        start: Some(start),
+        end,
+        is_current,
+        duration_months,
+    };
+    Some((range, understood))
+}
+
+/// Parses a rendered date range such as "Jan 2020 – Present · 2 yrs 3 mos". Without a locale, every
+/// supported locale is tried in order and the first one that understands the whole text wins, or else
+/// the first one that understands the dates.
+pub fn parse_date_range(text: &str, locale: Option<Locale>) -> Option<DateRange> {
+    if let Some(locale) = locale {
+        return parse_with_locale(text, locale).map(|(range, _)| range);
+    }
+    let mut dates_only = None;
+    for locale in Locale::ALL {
+        match parse_with_locale(text, locale) {
+            Some((range, true)) => return Some(range),
+            Some((range, false)) => dates_only = dates_only.or(Some(range)),
+            None => {}
+        }
+    }
+    dates_only
+}
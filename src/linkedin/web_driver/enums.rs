@@ -1,7 +1,7 @@
 use std::fmt;
 use std::fmt::Display;
-#[derive(serde::Deserialize, Debug)]
-pub enum String {
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+pub enum JobFunction {
     Accounting,
     Administrative,
     ArtsAndDesign,
@@ -29,39 +29,40 @@ pub enum String {
     CustomerSuccessAndSupport,
 }
 
-impl Display for String {
+impl Display for JobFunction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
-            String::Accounting => "Accounting",
-            String::Administrative => "Administrative",
-            String::ArtsAndDesign => "Arts and Design",
-            String::BusinessDevelopment => "Business Development",
-            String::CommunityAndSocialServices => "Community and Social Services",
-            String::Consulting => "Consulting",
-            String::Education => "Education",
-            String::Engineering => "Engineering",
-            String::Entrepreneurship => "Entrepreneurship",
-            String::Finance => "Finance",
-            String::HealthServices => "Health Services",
-            String::HumanResources => "Human Resources",
-            String::InformationTechnology => "Information Technology",
-            String::Legal => "Legal",
-            String::Marketing => "Marketing",
-            String::MediaAndCommunications => "Media and Communications",
-            String::Operations => "Operations",
-            String::ProductManagement => "Product Management",
-            String::ProgramAndProjectManagement => "Program and Project Management",
-            String::Purchasing => "Purchasing",
-            String::QualityAssurance => "Quality Assurance",
-            String::RealEstate => "Real Estate",
-            String::Research => "Research",
-            String::Sales => "Sales",
-            String::CustomerSuccessAndSupport => "Customer Success and Support",
+            JobFunction::Accounting => "Accounting",
+            JobFunction::Administrative => "Administrative",
+            JobFunction::ArtsAndDesign => "Arts and Design",
+            JobFunction::BusinessDevelopment => "Business Development",
+            JobFunction::CommunityAndSocialServices => "Community and Social Services",
+            JobFunction::Consulting => "Consulting",
+            JobFunction::Education => "Education",
+            JobFunction::Engineering => "Engineering",
+            JobFunction::Entrepreneurship => "Entrepreneurship",
+            JobFunction::Finance => "Finance",
+            JobFunction::HealthServices => "Health Services",
+            JobFunction::HumanResources => "Human Resources",
+            JobFunction::InformationTechnology => "Information Technology",
+            JobFunction::Legal => "Legal",
+            JobFunction::Marketing => "Marketing",
+            JobFunction::MediaAndCommunications => "Media and Communications",
+            JobFunction::Operations => "Operations",
+            JobFunction::ProductManagement => "Product Management",
+            JobFunction::ProgramAndProjectManagement => "Program and Project Management",
+            JobFunction::Purchasing => "Purchasing",
+            JobFunction::QualityAssurance => "Quality Assurance",
+            JobFunction::RealEstate => "Real Estate",
+            JobFunction::Research => "Research",
+            JobFunction::Sales => "Sales",
+            JobFunction::CustomerSuccessAndSupport => "Customer Success and Support",
         };
         write!(f, "{}", s)
     }
 }
-#[derive(serde::Deserialize)]
+/// Ordered from the most to the least senior, so `min` picks the highest level.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, utoipa::ToSchema)]
 pub enum SeniorityLevel {
     Owner,
     CXO,
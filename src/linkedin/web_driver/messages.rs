@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// An InMail to send from Sales Navigator.
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct MessageRequest {
+    /// Sales Navigator lead url, `https://www.linkedin.com/sales/lead/...`
+    pub sales_url: String,
+    /// Ignored when the lead's message form has no subject
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Sent,
+    Failed,
+}
+
+/// What happened to one message of a request.
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct MessageDelivery {
+    pub id: String,
+    pub sales_url: String,
+    pub status: DeliveryStatus,
+    pub error: Option<String>,
+    pub attempted_at: DateTime<Utc>,
+}
+
+impl MessageDelivery {
+    pub fn new(message: &MessageRequest, error: Option<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            sales_url: message.sales_url.clone(),
+            status: match error {
+                Some(_) => DeliveryStatus::Failed,
+                None => DeliveryStatus::Sent,
+            },
+            error,
+            attempted_at: Utc::now(),
+        }
+    }
+}
@@ -1,6 +1,8 @@
 pub mod crawler;
+pub mod date_range;
 pub mod enums;
 pub mod linkedin;
+pub mod messages;
 pub mod profiles;
 pub mod sales;
 pub mod sales_crawler;
@@ -1,3 +1,4 @@
+use crate::linkedin::web_driver::date_range::{parse_date_range, DateRange, Locale};
 use serde::Serialize;
 use std::fmt::{Display, Formatter};
 
@@ -105,6 +106,22 @@ impl Interval {
             })
             .ok_or("failed to find delimiter")
     }
+
+    /// Like `from_str`, but text without the delimiter, e.g. a single date, "Present" or a range with a
+    /// hyphen, is kept whole as the start for `to_date_range` to parse.
+    pub fn from_text(s: &str, delimiter: &str) -> Self {
+        Self::from_str(s, delimiter).unwrap_or_else(|_| Interval {
+            start: s.to_string(),
+            end: "".to_string(),
+        })
+    }
+
+    pub fn to_date_range(&self, locale: Option<Locale>) -> Option<DateRange> {
+        if self.end.trim().is_empty() {
+            return parse_date_range(&self.start, locale);
+        }
+        parse_date_range(&format!("{} – {}", self.start, self.end), locale)
+    }
 }
 impl Display for Interval {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -435,8 +435,14 @@ pub async fn parse_timeline(timeline: WebElement, results: &mut Vec<Experience>)
             }
         };
 
-        let time_text = time.text().await.unwrap();
-        let interval = Interval::from_str(time_text.as_str(), "–").unwrap();
+        let time_text = match time.text().await {
+            Ok(time_text) => time_text,
+            Err(_) => {
+                warn!("Failed to get experience duration text");
+                continue;
+            }
+        };
+        let interval = Interval::from_text(time_text.as_str(), "–");
         results.push(Experience {
             position: title.text().await.unwrap(),
             interval,
@@ -479,13 +485,7 @@ pub async fn parse_experience_entry(experience_entry: WebElement, result: &mut V
             return;
         }
     };
-    let interval = match Interval::from_str(time_text.as_str(), "–") {
-        Ok(interval) => interval,
-        Err(_) => {
-            warn!("Failed to parse experience duration");
-            return;
-        }
-    };
+    let interval = Interval::from_text(time_text.as_str(), "–");
     let title = match job_title.text().await {
         Ok(title) => title,
         Err(_) => {
@@ -638,7 +638,7 @@ pub async fn parse_education_entry(education_entry: WebElement, education_array:
     let result_timeline = education_main_div.find(By::XPath("./p[2]/span[2]")).await;
 
     let interval = match result_timeline {
-        Ok(timeline) => Interval::from_str(timeline.text().await.unwrap().as_str(), "–").unwrap(),
+        Ok(timeline) => Interval::from_text(timeline.text().await.unwrap_or_default().as_str(), "–"),
         Err(_) => {
             info!("No education timeline found.");
             Interval {
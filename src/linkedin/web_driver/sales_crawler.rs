@@ -1,10 +1,12 @@
 use crate::driver::session_manager::SessionProxy;
 use crate::errors::CrawlerError::DriverError;
 use crate::errors::CrawlerResult;
+use crate::linkedin::web_driver::messages::{MessageDelivery, MessageRequest};
 use crate::linkedin::web_driver::profiles::{Profile, SearchResult};
 use crate::linkedin::web_driver::sales::{
     parse_sales_profile, parse_search, send_message, set_function_search, set_geography_search, set_job_title_search, set_keyword_search,
 };
+use crate::metrics;
 use std::time::Duration;
 
 pub struct SalesCrawler<'a> {
@@ -48,6 +50,20 @@ impl<'a> SalesCrawler<'a> {
         let driver_ext = self.proxy.session.as_ref().unwrap();
         send_message(driver_ext, sales_url, subject, body).await
     }
+    /// Sends the message, recording a failure instead of returning it so one bad lead does not stop
+    /// the rest of a batch.
+    pub async fn deliver(&self, message: &MessageRequest) -> MessageDelivery {
+        let error = match self.send_message(&message.sales_url, &message.subject, &message.body).await {
+            Ok(()) => None,
+            Err(e) => {
+                error!("Failed to send message to {} {}", message.sales_url, e);
+                Some(e.to_string())
+            }
+        };
+        let delivery = MessageDelivery::new(message, error);
+        metrics::message_delivery(delivery.status);
+        delivery
+    }
     pub async fn test_detection(&self) {
         let driver_ext = self.proxy.session.as_ref().unwrap();
         driver_ext.driver.goto("https://demo.fingerprint.com/playground").await.unwrap();
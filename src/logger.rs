@@ -2,7 +2,9 @@ use chrono::Utc;
 use log::{LevelFilter, Metadata, Record};
 use std::sync::Once;
 
-pub struct Logger;
+pub struct Logger {
+    stderr: bool,
+}
 impl log::Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
         if !(metadata.target().starts_with("omi") || metadata.target().starts_with("actix")) {
@@ -13,19 +15,32 @@ impl log::Log for Logger {
 
     fn log(&self, record: &Record) {
         let now = Utc::now();
-        println!("[{}][{}] {}", now.to_rfc3339(), record.level(), record.args());
+        match self.stderr {
+            true => eprintln!("[{}][{}] {}", now.to_rfc3339(), record.level(), record.args()),
+            false => println!("[{}][{}] {}", now.to_rfc3339(), record.level(), record.args()),
+        }
     }
 
     fn flush(&self) {}
 }
 
-static LOGGER: Logger = Logger;
+static LOGGER: Logger = Logger { stderr: false };
+static STDERR_LOGGER: Logger = Logger { stderr: true };
 static INIT: Once = Once::new();
 
 impl Logger {
     pub fn init(logging_level: LevelFilter) {
+        Self::init_with(&LOGGER, logging_level);
+    }
+
+    /// Logs to stderr, for commands that write their results to stdout.
+    pub fn init_stderr(logging_level: LevelFilter) {
+        Self::init_with(&STDERR_LOGGER, logging_level);
+    }
+
+    fn init_with(logger: &'static Logger, logging_level: LevelFilter) {
         INIT.call_once(|| {
-            log::set_logger(&LOGGER).expect("Failed to set logger");
+            log::set_logger(logger).expect("Failed to set logger");
             log::set_max_level(logging_level);
         });
     }
@@ -1,61 +1,534 @@
-#[macro_use]
-extern crate log;
-
-use log::LevelFilter;
+use chrono::Utc;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use log::{error, info, warn};
+use omicron_crawler::azure::json::{CrawledProfiles, ProfileIds, ProfileJob, SearchJob};
 use omicron_crawler::azure::AzureClient;
-use omicron_crawler::env::load_env;
+use omicron_crawler::control::ControlCommand;
+use omicron_crawler::convert::{batches, dedupe, read_search_dump, SeenProfiles};
+use omicron_crawler::env::{get_env, load_env, Env, QueueBackend};
+use omicron_crawler::errors::CrawlerError::{ParseError, QueueError};
+use omicron_crawler::errors::CrawlerResult;
+use omicron_crawler::health::SessionStatus;
+use omicron_crawler::linkedin::api::crawler::Crawler;
+use omicron_crawler::linkedin::api::json::{GeoUrnMap, NetworkDepth, SearchParams};
+use omicron_crawler::linkedin::api::rate_limits::{RateLimiter, AVG_RESPONSE_TIME_MS};
+use omicron_crawler::linkedin::api::LinkedinSession;
+use omicron_crawler::linkedin::profile::export::{export_profiles, read_profiles, ExportFormat};
+use omicron_crawler::linkedin::profile::{Profile, Skill};
 use omicron_crawler::logger::Logger;
+use omicron_crawler::queue::dead_letter::DeadLetterStore;
+use omicron_crawler::queue::file::FileQueue;
+use omicron_crawler::queue::{Delivery, WorkQueue};
+use omicron_crawler::validation::{FieldError, Validate, ValidationErrors};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::process::exit;
+use std::time::Duration;
+
+/// One-off crawls and queue maintenance, configured by the same environment as the servers.
+#[derive(Parser)]
+#[command(name = "omicron_crawler", version)]
+struct Cli {
+    /// Write the result to this file instead of stdout
+    #[arg(short, long, global = true)]
+    output: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Log in to LinkedIn and save the session cookies
+    Auth {
+        /// Log in again even when the saved cookies look valid
+        #[arg(long)]
+        force: bool,
+    },
+    /// Search people, printing the search result
+    Search(Box<SearchArgs>),
+    /// Crawl profiles with their skills, waiting out the rate limit after each one
+    Profile {
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Print the skills of a profile
+    Skills { id: String },
+    /// Crawl the profiles listed in a POST /profiles body, e.g. {"ids": [...], "request_metadata": "..."}
+    CrawlFile {
+        /// JSON file, `-` reads stdin
+        file: String,
+    },
+    /// Convert crawled profiles to JSON, JSON lines or CSV
+    Export {
+        /// Crawled profiles as JSON, a JSON array or JSON lines, `-` reads stdin
+        file: String,
+        #[arg(short, long, default_value = "csv")]
+        format: ExportFormat,
+    },
+    /// Turn a search result dump into profile jobs ready for `queue push profiles`
+    Convert(Box<ConvertArgs>),
+    /// Inspect or feed the work queues configured by QUEUE_BACKEND
+    #[command(subcommand)]
+    Queue(QueueCommand),
+    /// Inspect, replay or drop the messages kept in DEAD_LETTER_DIR
+    #[command(subcommand)]
+    DeadLetters(DeadLetterCommand),
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    /// Search parameters as sent to POST /search, flags given next to it override its fields
+    #[arg(long)]
+    params: Option<String>,
+    #[arg(long)]
+    keywords: Option<String>,
+    #[arg(long)]
+    first_name: Option<String>,
+    #[arg(long)]
+    last_name: Option<String>,
+    #[arg(long)]
+    title: Option<String>,
+    #[arg(long)]
+    company: Option<String>,
+    #[arg(long)]
+    school: Option<String>,
+    /// czechia or slovakia, repeatable
+    #[arg(long = "country", value_parser = parse_value::<GeoUrnMap>)]
+    countries: Vec<GeoUrnMap>,
+    /// Profile language code, e.g. en, repeatable
+    #[arg(long = "language")]
+    languages: Vec<String>,
+    /// one, two or three, repeatable
+    #[arg(long, value_parser = parse_value::<NetworkDepth>)]
+    network_depth: Vec<NetworkDepth>,
+    #[arg(long)]
+    request_metadata: Option<String>,
+    /// First page to search, 0 based
+    #[arg(long)]
+    page: Option<u16>,
+    /// Page to stop before, defaults to searching just the first page
+    #[arg(long)]
+    end: Option<u16>,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// API or web driver search results, `-` reads stdin
+    file: String,
+    /// Profile ids per job
+    #[arg(long, default_value_t = 25, value_parser = clap::value_parser!(u32).range(1..))]
+    chunk_size: u32,
+    /// Request metadata for every job, defaults to the one of the API search
+    #[arg(long)]
+    request_metadata: Option<String>,
+    /// Keep ids pushed before, as listed in SEEN_PROFILES_PATH
+    #[arg(long)]
+    keep_seen: bool,
+    /// Write Sales Navigator lead urls to this file as `[{"sales_url": ...}]` for the web driver, the API
+    /// crawler cannot open them
+    #[arg(long)]
+    sales_urls: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum QueueCommand {
+    /// Print the next message without removing it, this counts as a delivery
+    Peek {
+        queue: QueueName,
+        /// Seconds to wait for a message
+        #[arg(long, default_value_t = 0)]
+        wait: u64,
+    },
+    /// Send a message, or each message of a JSON array, bare payloads get a fresh envelope. Pushed
+    /// profile ids are recorded in SEEN_PROFILES_PATH
+    Push {
+        queue: QueueName,
+        /// JSON file, `-` reads stdin
+        file: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DeadLetterCommand {
+    /// List dead letters, oldest first
+    List,
+    /// Print a dead letter with its payload
+    Show { id: String },
+    /// Put a dead letter back on its queue
+    Replay {
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        id: Option<String>,
+        /// Put every dead letter back on its queue
+        #[arg(long)]
+        all: bool,
+    },
+    /// Delete a dead letter
+    Drop { id: String },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum QueueName {
+    Searches,
+    Profiles,
+    Control,
+}
+
+/// Parses a flag the way the same value is deserialized from a request body.
+fn parse_value<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_value(Value::String(value.to_lowercase())).map_err(|e| e.to_string())
+}
+
+fn read_input(file: &str) -> CrawlerResult<String> {
+    let mut input = String::new();
+    let result = match file {
+        "-" => std::io::stdin().read_to_string(&mut input).map(|_| ()),
+        path => std::fs::read_to_string(path).map(|content| input = content),
+    };
+    result.map_err(|e| ParseError(format!("Failed to read {} {}", file, e)))?;
+    Ok(input)
+}
+
+fn read_json<T: DeserializeOwned>(file: &str) -> CrawlerResult<T> {
+    serde_json::from_str(&read_input(file)?).map_err(|e| ParseError(format!("Failed to parse {} {}", file, e)))
+}
+
+fn check(errors: Vec<FieldError>) -> CrawlerResult<()> {
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(ParseError(ValidationErrors { errors }.to_string())),
+    }
+}
+
+fn validated<T: Validate>(value: T) -> CrawlerResult<T> {
+    check(value.validate())?;
+    Ok(value)
+}
+
+fn write_output(output: Option<&str>, content: &str) -> CrawlerResult<()> {
+    match output {
+        Some(path) => std::fs::write(path, content).map_err(|e| ParseError(format!("Failed to write {} {}", path, e))),
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default() + "\n"
+}
+
+/// Repeated flags replace the list from the parameters file rather than extend it.
+fn or_list<T>(flag: Vec<T>, current: Option<Vec<T>>) -> Option<Vec<T>> {
+    match flag.is_empty() {
+        true => current,
+        false => Some(flag),
+    }
+}
+
+impl SearchArgs {
+    fn into_params(self) -> CrawlerResult<SearchParams> {
+        let mut params = match &self.params {
+            Some(file) => read_json::<SearchParams>(file)?,
+            None => SearchParams {
+                countries: None,
+                keywords: None,
+                keyword_first_name: None,
+                keyword_last_name: None,
+                keyword_title: None,
+                keyword_company: None,
+                keyword_school: None,
+                profile_language: None,
+                request_metadata: None,
+                network_depth: None,
+                page: 0,
+                end: 1,
+            },
+        };
+        params.keywords = self.keywords.or(params.keywords);
+        params.keyword_first_name = self.first_name.or(params.keyword_first_name);
+        params.keyword_last_name = self.last_name.or(params.keyword_last_name);
+        params.keyword_title = self.title.or(params.keyword_title);
+        params.keyword_company = self.company.or(params.keyword_company);
+        params.keyword_school = self.school.or(params.keyword_school);
+        params.request_metadata = self.request_metadata.or(params.request_metadata);
+        params.countries = or_list(self.countries, params.countries);
+        params.profile_language = or_list(self.languages, params.profile_language);
+        params.network_depth = or_list(self.network_depth, params.network_depth);
+        params.end = match (self.page, self.end) {
+            (_, Some(end)) => end,
+            // A page alone searches at least that page
+            (Some(page), None) => params.end.max(page + 1),
+            (None, None) => params.end,
+        };
+        params.page = self.page.unwrap_or(params.page);
+        validated(params)
+    }
+}
+
+/// Session from the saved cookies, logging in first when there are none.
+async fn session(env: &Env, force: bool) -> CrawlerResult<LinkedinSession> {
+    let mut session = LinkedinSession::new();
+    if force || !session.is_auth() {
+        session.authenticate(&env.linkedin_username, &env.linkedin_password).await?;
+        // Like on start up of the servers, the session is rebuilt from the cookies authenticating saved
+        session = LinkedinSession::new();
+    }
+    Ok(session)
+}
+
+async fn crawler(env: &Env) -> CrawlerResult<Crawler> {
+    session(env, false).await?;
+    Ok(Crawler::new(
+        RateLimiter::new(env.profiles_per_hour, AVG_RESPONSE_TIME_MS),
+        &env.linkedin_username,
+        &env.linkedin_password,
+    )
+    .await)
+}
+
+fn delivery_json<T: Serialize>(delivery: &Delivery<T>) -> Value {
+    let message = match &delivery.message {
+        Ok(message) => json!({ "message": message }),
+        Err(malformed) => json!({ "malformed": malformed.raw, "error": malformed.error }),
+    };
+    json!({ "queue": delivery.receipt.queue, "delivery_count": delivery.receipt.delivery_count, "delivery": message })
+}
+
+async fn peek<Q: WorkQueue>(queue: &Q, name: QueueName, wait: Duration) -> CrawlerResult<Option<Value>> {
+    let (shown, receipt) = match name {
+        QueueName::Searches => match queue.dequeue_search(wait).await? {
+            Some(delivery) => (delivery_json(&delivery), delivery.receipt),
+            None => return Ok(None),
+        },
+        QueueName::Profiles => match queue.dequeue_profiles(wait).await? {
+            Some(delivery) => (delivery_json(&delivery), delivery.receipt),
+            None => return Ok(None),
+        },
+        QueueName::Control => match queue.dequeue_control(wait).await? {
+            Some(delivery) => (delivery_json(&delivery), delivery.receipt),
+            None => return Ok(None),
+        },
+    };
+    queue.abandon(&receipt).await?;
+    Ok(Some(shown))
+}
+
+/// Every message is checked before the first one is sent, so a bad file sends nothing.
+fn parse_messages<T: DeserializeOwned>(input: Value, validate: impl Fn(&T) -> Vec<FieldError>) -> CrawlerResult<Vec<T>> {
+    let values = match input {
+        Value::Array(values) => values,
+        value => vec![value],
+    };
+    let mut messages = Vec::with_capacity(values.len());
+    for (i, value) in values.into_iter().enumerate() {
+        let message = serde_json::from_value::<T>(value).map_err(|e| ParseError(format!("Invalid message {} {}", i, e)))?;
+        check(validate(&message))?;
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+async fn push<Q: WorkQueue>(queue: &Q, name: QueueName, input: Value) -> CrawlerResult<usize> {
+    match name {
+        QueueName::Searches => {
+            let jobs = parse_messages::<SearchJob>(input, |job| job.payload.validate())?;
+            for job in &jobs {
+                queue.requeue_search(job).await?;
+            }
+            Ok(jobs.len())
+        }
+        QueueName::Profiles => {
+            let jobs = parse_messages::<ProfileJob>(input, |job| job.payload.validate())?;
+            let mut seen = SeenProfiles::open(&get_env().await.seen_profiles_path).await?;
+            for job in &jobs {
+                queue.requeue_profiles(job).await?;
+                seen.record(&job.payload.ids).await?;
+            }
+            Ok(jobs.len())
+        }
+        QueueName::Control => {
+            let commands = parse_messages::<ControlCommand>(input, |_| Vec::new())?;
+            for command in &commands {
+                queue.requeue_control(command).await?;
+            }
+            Ok(commands.len())
+        }
+    }
+}
+
+async fn run_queue<Q: WorkQueue>(queue: &Q, command: QueueCommand) -> CrawlerResult<String> {
+    match command {
+        QueueCommand::Peek { queue: name, wait } => match peek(queue, name, Duration::from_secs(wait)).await? {
+            Some(shown) => Ok(to_json(&shown)),
+            None => Err(QueueError("Queue is empty".to_string())),
+        },
+        QueueCommand::Push { queue: name, file } => {
+            let pushed = push(queue, name, read_json(&file)?).await?;
+            info!("Pushed {} messages", pushed);
+            Ok(to_json(&json!({ "pushed": pushed })))
+        }
+    }
+}
+
+async fn replay<Q: WorkQueue>(store: &DeadLetterStore, queue: &Q, id: Option<String>) -> CrawlerResult<usize> {
+    let ids = match id {
+        Some(id) => vec![id],
+        None => store.list().await?.into_iter().map(|letter| letter.id).collect(),
+    };
+    for id in &ids {
+        store.replay(id, queue).await?;
+        info!("Replayed {}", id);
+    }
+    Ok(ids.len())
+}
+
+async fn run_dead_letters(env: &Env, command: DeadLetterCommand) -> CrawlerResult<String> {
+    let store = DeadLetterStore::open(&env.dead_letter_dir).await?;
+    match command {
+        DeadLetterCommand::List => {
+            let lines: Vec<String> = store
+                .list()
+                .await?
+                .iter()
+                .map(|letter| {
+                    let queue = letter.queue.to_string();
+                    format!(
+                        "{}  {:<8}  deliveries {:<3}  {}  {}\n",
+                        letter.id, queue, letter.delivery_count, letter.dead_lettered_at, letter.reason
+                    )
+                })
+                .collect();
+            Ok(lines.concat())
+        }
+        DeadLetterCommand::Show { id } => match store.get(&id).await? {
+            Some(letter) => Ok(to_json(&letter)),
+            None => Err(QueueError(format!("No dead letter {}", id))),
+        },
+        DeadLetterCommand::Replay { id, all: _ } => {
+            let replayed = match env.queue_backend {
+                QueueBackend::Azure => replay(&store, &AzureClient::new().await, id).await?,
+                QueueBackend::File => replay(&store, &FileQueue::new(&env.queue_dir).await?, id).await?,
+            };
+            Ok(to_json(&json!({ "replayed": replayed })))
+        }
+        DeadLetterCommand::Drop { id } => {
+            store.remove(&id).await?;
+            Ok(to_json(&json!({ "dropped": id })))
+        }
+    }
+}
+
+/// Seen ids are only recorded once `queue push profiles` sent their jobs, so converting the same dump again
+/// gives the same jobs.
+async fn convert(env: &Env, args: ConvertArgs) -> CrawlerResult<String> {
+    let found = read_search_dump(&read_input(&args.file)?)?;
+    for url in &found.unrecognized {
+        warn!("No profile id in {}", url);
+    }
+    let sales_urls = dedupe(found.sales_urls);
+    match &args.sales_urls {
+        Some(path) => {
+            let urls: Vec<Value> = sales_urls.iter().map(|url| json!({ "sales_url": url })).collect();
+            write_output(Some(path), &to_json(&urls))?;
+            info!("Wrote {} Sales Navigator leads to {}", urls.len(), path);
+        }
+        None if !sales_urls.is_empty() => warn!(
+            "Skipping {} Sales Navigator leads, pass --sales-urls to keep them",
+            sales_urls.len()
+        ),
+        None => {}
+    }
+    let found_count = found.ids.len();
+    let seen = SeenProfiles::open(&env.seen_profiles_path).await?;
+    let unique = dedupe(found.ids);
+    let unique_count = unique.len();
+    let ids = match args.keep_seen {
+        true => unique,
+        false => seen.unseen(unique),
+    };
+    info!(
+        "Converting {} of {} found profiles, {} were pushed before",
+        ids.len(),
+        found_count,
+        unique_count - ids.len()
+    );
+    let request_metadata = args.request_metadata.or(found.request_metadata);
+    let jobs = batches(&ids, args.chunk_size as usize, request_metadata.as_deref());
+    Ok(to_json(&jobs))
+}
+
+async fn run(command: Command, output: Option<&str>) -> CrawlerResult<()> {
+    let env = get_env().await;
+    let content = match command {
+        Command::Auth { force } => {
+            let session = session(env, force).await?;
+            let status = SessionStatus {
+                authenticated: session.is_auth(),
+                cookie_age_secs: session.cookie_age().map(|age| age.as_secs()),
+            };
+            to_json(&status)
+        }
+        Command::Search(args) => {
+            let params = args.into_params()?;
+            info!("Searching {}", params);
+            to_json(&crawler(env).await?.search_people(params, None).await?)
+        }
+        Command::Profile { ids } => {
+            let crawler = crawler(env).await?;
+            let mut profiles = Vec::with_capacity(ids.len());
+            for id in &ids {
+                // Like crawl-file, one failed profile does not throw away the others
+                match crawler.profile(id).await {
+                    Ok(profile) => profiles.push(Profile::from(profile)),
+                    Err(e) => error!("Failed to crawl profile {} reason: {}", id, e),
+                }
+            }
+            info!("Crawled {} of {} profiles", profiles.len(), ids.len());
+            to_json(&profiles)
+        }
+        Command::Skills { id } => {
+            let skill_view = crawler(env).await?.skills(&id).await?;
+            let skills: Vec<Skill> = skill_view.elements.into_iter().map(|skill| Skill::new(skill.name, None)).collect();
+            to_json(&skills)
+        }
+        Command::CrawlFile { file } => {
+            let ids = validated(read_json::<ProfileIds>(&file)?)?;
+            let batch = crawler(env).await?.profiles(&ids.ids, None).await?;
+            info!("Crawled {} of {} profiles", batch.profiles.len(), ids.ids.len());
+            let crawled = CrawledProfiles {
+                profiles: batch.profiles.into_iter().map(Profile::from).collect(),
+                request_metadata: ids.request_metadata,
+            };
+            to_json(&crawled)
+        }
+        Command::Export { file, format } => {
+            let profiles = read_profiles(&read_input(&file)?)?;
+            export_profiles(&profiles, format, Utc::now().date_naive())
+        }
+        Command::Queue(command) => match env.queue_backend {
+            QueueBackend::Azure => run_queue(&AzureClient::new().await, command).await?,
+            QueueBackend::File => run_queue(&FileQueue::new(&env.queue_dir).await?, command).await?,
+        },
+        Command::DeadLetters(command) => run_dead_letters(env, command).await?,
+        Command::Convert(args) => convert(env, *args).await?,
+    };
+    write_output(output, &content)
+}
 
-//TODO
-// 1. Create a generic error handler macro that will generically handle cases like not found / stale element / etc
-//    - In case of stale, we should refetch the element and retry until timeout
-//    - In case of not found, we retry with a timeout
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
-    Logger::init(LevelFilter::Trace);
+    let cli = Cli::parse();
     load_env();
-    let azure_client = AzureClient::new().await;
-    let response = match azure_client.dequeue_search().await {
-        Ok(response) => response,
-        Err(e) => panic!("Failed to dequeue profile {}", e),
-    };
-    // load_env();
-    //
-    // let env = get_env().await;
-    // let manager: SessionManager<GeckoDriverService> = SessionManager::new(
-    //     env.driver_host.as_str(),
-    //     env.driver_port,
-    //     env.driver_session_count,
-    //     env.driver_path.as_str(),
-    //     env.profile_path.as_str(),
-    //     env.browser_binary_path.as_deref(),
-    // )
-    // .await;
-    // let pool = &manager.pool;
-    // let session = pool.acquire().unwrap();
-    // let crawler = LinkedinCrawler::new(session).await;
-    //
-    // match crawler.parse_profile("https://www.linkedin.com/in/matus-chochlik-154a7827/").await {
-    //     Ok(profile) => println!("{}", profile),
-    //     Err(e) => println!("{}", e),
-    // }
-    //
-    // tokio::time::sleep(Duration::from_secs(5)).await;
-
-    // let result = crawler
-    //     .parse_profile("https://www.linkedin.com/sales/lead/ACwAABtsCsMBHH4i-dKLOpQqrcqE4H3YDX8CbxE")
-    //     .await;
-    // println!("{}", result.ok().unwrap());
-
-    //
-    // fatal_unwrap_e!(
-    //     crawler
-    //         .set_search_filters(Some("Java".to_string()), None, None, Some("Slovakia".to_string()))
-    //         .await,
-    //     "{}"
-    // );
-    // let results = fatal_unwrap_e!(crawler.parse_search().await, "{}");
-    // let first = results.first().unwrap();
-    // let profile = crawler.parse_profile(&first.sales_url).await;
-    // println!("{}", fatal_unwrap_e!(profile, "{}"));
+    // Results go to stdout, so logs must not
+    Logger::init_stderr(get_env().await.log_level);
+    match run(cli.command, cli.output.as_deref()).await {
+        Ok(()) => {}
+        Err(e) => {
+            error!("{}", e);
+            exit(1);
+        }
+    }
 }
@@ -0,0 +1,197 @@
+use crate::errors::CrawlerResult;
+use crate::linkedin::web_driver::messages::DeliveryStatus;
+use crate::queue::QueueKind;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::ServiceConfig;
+use actix_web::{web, Error, HttpResponse};
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge_vec, Encoder, Histogram,
+    HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use reqwest::Response;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+pub static LINKEDIN_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "linkedin_requests_total",
+        "Requests to LinkedIn by endpoint and response status, error when no response came",
+        &["endpoint", "status"]
+    )
+    .unwrap()
+});
+
+pub static LINKEDIN_PARSE_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "linkedin_parse_failures_total",
+        "LinkedIn responses that could not be parsed",
+        &["endpoint"]
+    )
+    .unwrap()
+});
+
+pub static PROFILES_CRAWLED: LazyLock<IntCounter> =
+    LazyLock::new(|| register_int_counter!("profiles_crawled_total", "Profiles crawled with their skills").unwrap());
+
+pub static RATE_LIMIT_SLEEP: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "rate_limit_sleep_seconds",
+        "Time slept between profiles to stay within the rate budget",
+        vec![1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0]
+    )
+    .unwrap()
+});
+
+pub static QUEUE_RECEIVES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "queue_receives_total",
+        "Queue receives by queue and outcome: message, empty or error",
+        &["queue", "outcome"]
+    )
+    .unwrap()
+});
+
+pub static MANAGER_PUSH_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "manager_push_duration_seconds",
+        "Time to submit a result to the manager by label and outcome",
+        &["label", "outcome"]
+    )
+    .unwrap()
+});
+
+pub static MANAGER_PUSH_RETRIES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "manager_push_retries_total",
+        "Failed result publishes left in the outbox to be retried"
+    )
+    .unwrap()
+});
+
+pub static WEBDRIVER_SESSIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "webdriver_sessions",
+        "WebDriver sessions in the pool by state: idle or busy",
+        &["state"]
+    )
+    .unwrap()
+});
+
+pub static MESSAGES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "messages_total",
+        "Sales Navigator messages by delivery status: sent or failed",
+        &["status"]
+    )
+    .unwrap()
+});
+
+pub static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "http_requests_total",
+        "Requests served by route, method and status",
+        &["route", "method", "status"]
+    )
+    .unwrap()
+});
+
+pub static HTTP_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "http_request_duration_seconds",
+        "Time to serve a request by route",
+        &["route", "method"]
+    )
+    .unwrap()
+});
+
+/// Counts a LinkedIn request by the status of its response.
+pub fn linkedin_response(endpoint: &str, response: &Result<Response, reqwest::Error>) {
+    let status = match response {
+        Ok(response) => response.status().as_u16().to_string(),
+        Err(_) => "error".to_string(),
+    };
+    LINKEDIN_REQUESTS.with_label_values(&[endpoint, status.as_str()]).inc();
+}
+
+pub fn queue_receive<T>(kind: QueueKind, received: &CrawlerResult<Option<T>>) {
+    let outcome = match received {
+        Ok(Some(_)) => "message",
+        Ok(None) => "empty",
+        Err(_) => "error",
+    };
+    QUEUE_RECEIVES.with_label_values(&[kind.to_string().as_str(), outcome]).inc();
+}
+
+pub fn webdriver_sessions(idle: usize, total: usize) {
+    WEBDRIVER_SESSIONS.with_label_values(&["idle"]).set(idle as i64);
+    WEBDRIVER_SESSIONS
+        .with_label_values(&["busy"])
+        .set(total.saturating_sub(idle) as i64);
+}
+
+pub fn message_delivery(status: DeliveryStatus) {
+    let status = match status {
+        DeliveryStatus::Sent => "sent",
+        DeliveryStatus::Failed => "failed",
+    };
+    MESSAGES.with_label_values(&[status]).inc();
+}
+
+/// Everything registered so far in the Prometheus text format.
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        error!("Failed to encode metrics {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses((status = 200, description = "Metrics in the Prometheus text format", content_type = "text/plain"))
+)]
+pub async fn metrics() -> HttpResponse {
+    HttpResponse::Ok().content_type(TextEncoder::new().format_type()).body(render())
+}
+
+/// Creates every metric, so each is exported from the first scrape rather than from its first use.
+pub fn register() {
+    LazyLock::force(&LINKEDIN_REQUESTS);
+    LazyLock::force(&LINKEDIN_PARSE_FAILURES);
+    LazyLock::force(&PROFILES_CRAWLED);
+    LazyLock::force(&RATE_LIMIT_SLEEP);
+    LazyLock::force(&QUEUE_RECEIVES);
+    LazyLock::force(&MANAGER_PUSH_DURATION);
+    LazyLock::force(&MANAGER_PUSH_RETRIES);
+    LazyLock::force(&WEBDRIVER_SESSIONS);
+    LazyLock::force(&MESSAGES);
+    LazyLock::force(&HTTP_REQUESTS);
+    LazyLock::force(&HTTP_REQUEST_DURATION);
+}
+
+/// Registers `/metrics`.
+pub fn configure(cfg: &mut ServiceConfig) {
+    register();
+    cfg.route("/metrics", web::get().to(metrics));
+}
+
+/// Middleware counting and timing every request. Routes are labelled by their pattern so ids in paths
+/// do not create a series each.
+pub async fn track_http(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let response = next.call(req).await?;
+    let route = response.request().match_pattern().unwrap_or_else(|| "unmatched".to_string());
+    let status = response.status().as_u16().to_string();
+    HTTP_REQUESTS
+        .with_label_values(&[route.as_str(), method.as_str(), status.as_str()])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[route.as_str(), method.as_str()])
+        .observe(start.elapsed().as_secs_f64());
+    Ok(response)
+}
@@ -0,0 +1,57 @@
+use crate::azure::chunk::{split_profile_job, MAX_MESSAGE_BYTES};
+use crate::azure::json::{ProfileJob, SearchJob};
+use crate::azure::{AzureClient, Label};
+use crate::control::ControlCommand;
+use crate::errors::CrawlerResult;
+use crate::queue::{Delivery, Receipt, WorkQueue};
+use serde::Serialize;
+use std::time::Duration;
+
+impl WorkQueue for AzureClient {
+    async fn dequeue_search(&self, wait: Duration) -> CrawlerResult<Option<Delivery<SearchJob>>> {
+        AzureClient::dequeue_search(self, wait).await
+    }
+
+    async fn dequeue_profiles(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ProfileJob>>> {
+        self.dequeue_profile(wait).await
+    }
+
+    async fn dequeue_control(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ControlCommand>>> {
+        AzureClient::dequeue_control(self, wait).await
+    }
+
+    async fn complete(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.complete_message(receipt).await
+    }
+
+    async fn abandon(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.abandon_message(receipt).await
+    }
+
+    async fn renew_lock(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.renew_message_lock(receipt).await
+    }
+
+    async fn requeue_search(&self, job: &SearchJob) -> CrawlerResult<()> {
+        self.push_to_search_queue(job).await
+    }
+
+    /// A job too large for one message goes out as several smaller jobs.
+    async fn requeue_profiles(&self, job: &ProfileJob) -> CrawlerResult<()> {
+        for part in split_profile_job(job.clone(), MAX_MESSAGE_BYTES) {
+            self.push_to_queue(&part).await?;
+        }
+        Ok(())
+    }
+
+    async fn requeue_control(&self, command: &ControlCommand) -> CrawlerResult<()> {
+        self.push_to_control_queue(command).await
+    }
+
+    async fn publish_result<T>(&self, data: &T, label: Label) -> CrawlerResult<()>
+    where
+        T: Serialize + Send + Sync,
+    {
+        self.push_to_manager(data, label).await
+    }
+}
@@ -0,0 +1,151 @@
+use crate::azure::json::{ProfileJob, SearchJob};
+use crate::control::ControlCommand;
+use crate::errors::CrawlerError::{ParseError, QueueError};
+use crate::errors::CrawlerResult;
+use crate::queue::file::{create_dir, list, rename};
+use crate::queue::outbox::OutboxEntry;
+use crate::queue::{next_lock_id, QueueKind, Receipt, WorkQueue};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A message taken out of circulation, with enough context to inspect and replay it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeadLetter {
+    pub id: String,
+    pub queue: QueueKind,
+    pub reason: String,
+    pub delivery_count: u32,
+    /// Message body as received, not necessarily valid json
+    pub payload: String,
+    pub dead_lettered_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DeadLetter {
+    pub fn new(receipt: &Receipt, payload: String, reason: impl Into<String>) -> Self {
+        Self {
+            id: next_lock_id(),
+            queue: receipt.queue,
+            reason: reason.into(),
+            delivery_count: receipt.delivery_count,
+            payload,
+            dead_lettered_at: chrono::Utc::now(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DeadLetterPolicy {
+    /// Deliveries allowed before a message is considered poison
+    pub max_attempts: u32,
+}
+
+impl DeadLetterPolicy {
+    pub fn exceeded(&self, receipt: &Receipt) -> bool {
+        receipt.delivery_count > self.max_attempts
+    }
+}
+
+/// Dead letters kept as one json file each in a local directory, oldest first.
+pub struct DeadLetterStore {
+    dir: PathBuf,
+}
+
+impl DeadLetterStore {
+    pub async fn open(dir: impl AsRef<Path>) -> CrawlerResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        create_dir(&dir).await?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    pub async fn store(&self, letter: &DeadLetter) -> CrawlerResult<()> {
+        let json = match serde_json::to_string_pretty(letter) {
+            Ok(json) => json,
+            Err(e) => return Err(ParseError(format!("Failed to serialize dead letter {}", e))),
+        };
+        let tmp = self.dir.join(format!("{}.tmp", letter.id));
+        if let Err(e) = tokio::fs::write(&tmp, json).await {
+            return Err(QueueError(format!("Failed to write dead letter {} {}", tmp.display(), e)));
+        }
+        rename(&tmp, &self.path(&letter.id)).await
+    }
+
+    pub async fn get(&self, id: &str) -> CrawlerResult<Option<DeadLetter>> {
+        let path = self.path(id);
+        let raw = match tokio::fs::read_to_string(&path).await {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(QueueError(format!("Failed to read dead letter {} {}", path.display(), e))),
+        };
+        match serde_json::from_str(&raw) {
+            Ok(letter) => Ok(Some(letter)),
+            Err(e) => Err(ParseError(format!("Invalid dead letter {} {}", path.display(), e))),
+        }
+    }
+
+    pub async fn list(&self) -> CrawlerResult<Vec<DeadLetter>> {
+        let mut letters = Vec::new();
+        for name in list(&self.dir).await? {
+            if let Some(id) = name.strip_suffix(".json") {
+                if let Some(letter) = self.get(id).await? {
+                    letters.push(letter);
+                }
+            }
+        }
+        Ok(letters)
+    }
+
+    pub async fn remove(&self, id: &str) -> CrawlerResult<()> {
+        let path = self.path(id);
+        match tokio::fs::remove_file(&path).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(QueueError(format!("Failed to remove dead letter {} {}", path.display(), e))),
+        }
+    }
+
+    /// Puts the message back on its original queue and forgets it. The payload may have been fixed by
+    /// hand in the meantime, it has to parse now.
+    pub async fn replay<Q: WorkQueue>(&self, id: &str, queue: &Q) -> CrawlerResult<()> {
+        let letter = match self.get(id).await? {
+            Some(letter) => letter,
+            None => return Err(QueueError(format!("No dead letter {}", id))),
+        };
+        let result = match letter.queue {
+            QueueKind::Search => match serde_json::from_str::<SearchJob>(&letter.payload) {
+                Ok(job) => queue.requeue_search(&job).await,
+                Err(e) => Err(ParseError(format!("Dead letter {} is still malformed {}", id, e))),
+            },
+            QueueKind::Profiles => match serde_json::from_str::<ProfileJob>(&letter.payload) {
+                Ok(job) => queue.requeue_profiles(&job).await,
+                Err(e) => Err(ParseError(format!("Dead letter {} is still malformed {}", id, e))),
+            },
+            QueueKind::Control => match serde_json::from_str::<ControlCommand>(&letter.payload) {
+                Ok(command) => queue.requeue_control(&command).await,
+                Err(e) => Err(ParseError(format!("Dead letter {} is still malformed {}", id, e))),
+            },
+            QueueKind::Results => match serde_json::from_str::<OutboxEntry>(&letter.payload) {
+                Ok(entry) => queue.publish_result(&entry.data, entry.label).await,
+                Err(e) => Err(ParseError(format!("Dead letter {} is still malformed {}", id, e))),
+            },
+        };
+        result?;
+        self.remove(id).await
+    }
+}
+
+/// Moves a delivery to the dead-letter store and completes it, so the queue stops redelivering it.
+pub async fn dead_letter<Q: WorkQueue>(
+    queue: &Q,
+    store: &DeadLetterStore,
+    receipt: &Receipt,
+    payload: String,
+    reason: &str,
+) -> CrawlerResult<()> {
+    let letter = DeadLetter::new(receipt, payload, reason);
+    store.store(&letter).await?;
+    warn!("Dead-lettered {} message as {}: {}", receipt.queue, letter.id, reason);
+    queue.complete(receipt).await
+}
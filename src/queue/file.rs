@@ -0,0 +1,500 @@
+use crate::azure::json::{ProfileJob, SearchJob};
+use crate::azure::Label;
+use crate::control::ControlCommand;
+use crate::errors::CrawlerError::{ParseError, QueueError};
+use crate::errors::CrawlerResult;
+use crate::queue::{next_lock_id, parse_message, Delivery, PublishedResult, QueueKind, Receipt, WorkQueue};
+use fs2::FileExt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, MutexGuard};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+const RESULT_FILE: &str = "results.jsonl";
+const LOCK_FILE: &str = "queue.lock";
+const OWNERS_DIR: &str = "owners";
+const SUBSCRIPTIONS_DIR: &str = "subscriptions";
+/// Jobs may be appended by other processes, so an empty queue is polled while waiting
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Serialize, Deserialize)]
+struct LockedMessage {
+    delivery_count: u32,
+    /// Raw line as it was queued, it may not be valid json
+    payload: String,
+}
+
+/// Queue backed by JSONL files in a local directory, one message per line. New jobs are read from
+/// `searches.jsonl`, `profiles.jsonl` and `control.jsonl` front to back and results are appended to
+/// `results.jsonl`.
+///
+/// A received message is moved into `<queue>.locked/`, completing deletes it and abandoning moves it
+/// to `<queue>.abandoned/`, which is delivered before any new message.
+///
+/// Several processes may share the directory, e.g. a worker and `queue push`, so every change to the
+/// files is made under an advisory lock on `queue.lock`. Each open queue also holds the lock on its own
+/// file in `owners/` and names its locked messages after it. Messages whose owner file is no longer
+/// locked were left behind by a worker that exited or crashed, they are treated as abandoned when the
+/// next queue is opened.
+///
+/// Workers sharing the directory take turns on `control.jsonl`, so a command reaches only one of them.
+/// A worker that subscribed under a name in `subscriptions/` reads `control.<name>.jsonl` instead, and
+/// every pushed command is appended to each subscriber's file while there are any.
+pub struct FileQueue {
+    dir: PathBuf,
+    lock: Mutex<()>,
+    owner: String,
+    /// Name the control files of this queue start with, `control` or `control.<subscription>`
+    control_lane: String,
+    /// Locked until the queue is dropped, closing it tells other processes this owner is gone
+    _owner_file: std::fs::File,
+}
+
+/// Where a received message came from, it is only removed there once its locked copy exists.
+enum Source {
+    Abandoned(PathBuf),
+    /// The queue file and what remains of it without the first line
+    QueueFile {
+        path: PathBuf,
+        rest: String,
+    },
+}
+
+/// Exclusive access to the queue files, for the tasks of this process and for other processes.
+struct DirLock<'a> {
+    _guard: MutexGuard<'a, ()>,
+    _file: std::fs::File,
+}
+
+pub(crate) async fn create_dir(dir: &Path) -> CrawlerResult<()> {
+    match tokio::fs::create_dir_all(dir).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(QueueError(format!("Failed to create queue directory {} {}", dir.display(), e))),
+    }
+}
+
+pub(crate) async fn rename(from: &Path, to: &Path) -> CrawlerResult<()> {
+    match tokio::fs::rename(from, to).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(QueueError(format!("Failed to move {} to {} {}", from.display(), to.display(), e))),
+    }
+}
+
+/// File names in the directory, sorted so that the oldest lock id comes first.
+pub(crate) async fn list(dir: &Path) -> CrawlerResult<Vec<String>> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) => return Err(QueueError(format!("Failed to list {} {}", dir.display(), e))),
+    };
+    let mut names = Vec::new();
+    loop {
+        match entries.next_entry().await {
+            Ok(Some(entry)) => names.push(entry.file_name().to_string_lossy().to_string()),
+            Ok(None) => break,
+            Err(e) => return Err(QueueError(format!("Failed to list {} {}", dir.display(), e))),
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn open_lock_file(path: &Path) -> std::io::Result<std::fs::File> {
+    std::fs::OpenOptions::new().create(true).truncate(false).write(true).open(path)
+}
+
+/// Owner of a locked message, from its `<lock id>@<owner>.json` file name.
+fn owner_of(name: &str) -> Option<&str> {
+    name.strip_suffix(".json")?.split_once('@').map(|(_, owner)| owner)
+}
+
+fn subscription_lane(name: &str) -> String {
+    format!("{}.{}", QueueKind::Control, name)
+}
+
+impl FileQueue {
+    pub async fn new(dir: impl AsRef<Path>) -> CrawlerResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let owners_dir = dir.join(OWNERS_DIR);
+        create_dir(&owners_dir).await?;
+        create_dir(&dir.join(SUBSCRIPTIONS_DIR)).await?;
+        let owner = Uuid::new_v4().simple().to_string();
+        let owner_path = owners_dir.join(format!("{}.lock", owner));
+        let owner_file = match open_lock_file(&owner_path).and_then(|file| file.try_lock_exclusive().map(|_| file)) {
+            Ok(file) => file,
+            Err(e) => return Err(QueueError(format!("Failed to lock {} {}", owner_path.display(), e))),
+        };
+        let queue = Self {
+            dir,
+            lock: Mutex::new(()),
+            owner,
+            control_lane: QueueKind::Control.to_string(),
+            _owner_file: owner_file,
+        };
+        for kind in [QueueKind::Search, QueueKind::Profiles, QueueKind::Control] {
+            create_dir(&queue.locked_dir(kind)).await?;
+            create_dir(&queue.abandoned_dir(kind)).await?;
+        }
+        queue.recover().await?;
+        Ok(queue)
+    }
+
+    /// Receives control commands under `name` from now on, commands pushed while the worker is stopped
+    /// wait for it. Names are letters, digits, `-` and `_`.
+    pub async fn subscribe(mut self, name: &str) -> CrawlerResult<Self> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(QueueError(format!("Invalid control subscription {:?}", name)));
+        }
+        let path = self.dir.join(SUBSCRIPTIONS_DIR).join(name);
+        {
+            let _lock = self.lock_dir().await?;
+            if let Err(e) = tokio::fs::OpenOptions::new().create(true).append(true).open(&path).await {
+                return Err(QueueError(format!("Failed to subscribe {} {}", path.display(), e)));
+            }
+        }
+        self.control_lane = subscription_lane(name);
+        create_dir(&self.locked_dir(QueueKind::Control)).await?;
+        create_dir(&self.abandoned_dir(QueueKind::Control)).await?;
+        Ok(self)
+    }
+
+    async fn subscriptions(&self) -> CrawlerResult<Vec<String>> {
+        list(&self.dir.join(SUBSCRIPTIONS_DIR)).await
+    }
+
+    /// Moves messages locked by owners that are gone to the abandoned messages and forgets those owners.
+    async fn recover(&self) -> CrawlerResult<()> {
+        let _lock = self.lock_dir().await?;
+        let owners_dir = self.dir.join(OWNERS_DIR);
+        let mut live = HashSet::new();
+        for name in list(&owners_dir).await? {
+            let owner = match name.strip_suffix(".lock") {
+                Some(owner) => owner.to_string(),
+                None => continue,
+            };
+            let path = owners_dir.join(&name);
+            // The lock is only free once the process holding it closed the file
+            let gone = owner != self.owner && open_lock_file(&path).and_then(|file| file.try_lock_exclusive()).is_ok();
+            match gone {
+                true => {
+                    if let Err(e) = tokio::fs::remove_file(&path).await {
+                        warn!("Failed to remove queue owner {} {}", path.display(), e);
+                    }
+                }
+                false => {
+                    live.insert(owner);
+                }
+            }
+        }
+        let mut lanes: Vec<String> = [QueueKind::Search, QueueKind::Profiles, QueueKind::Control]
+            .map(|kind| kind.to_string())
+            .into();
+        lanes.extend(self.subscriptions().await?.iter().map(|name| subscription_lane(name)));
+        for lane in lanes {
+            let locked_dir = self.lane_path(&lane, ".locked");
+            // A subscription's files only exist once its worker opened them
+            if !locked_dir.exists() {
+                continue;
+            }
+            for name in list(&locked_dir).await? {
+                if owner_of(&name).is_some_and(|owner| live.contains(owner)) {
+                    continue;
+                }
+                warn!("Recovering {} message {} locked by a previous run", lane, name);
+                rename(&locked_dir.join(&name), &self.lane_path(&lane, ".abandoned").join(&name)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for other tasks and other processes to finish with the queue files.
+    async fn lock_dir(&self) -> CrawlerResult<DirLock<'_>> {
+        let guard = self.lock.lock().await;
+        let path = self.dir.join(LOCK_FILE);
+        let locking = path.clone();
+        let result =
+            tokio::task::spawn_blocking(move || open_lock_file(&locking).and_then(|file| file.lock_exclusive().map(|_| file))).await;
+        match result {
+            Ok(Ok(file)) => Ok(DirLock {
+                _guard: guard,
+                _file: file,
+            }),
+            Ok(Err(e)) => Err(QueueError(format!("Failed to lock {} {}", path.display(), e))),
+            Err(e) => Err(QueueError(format!("Failed to lock {} {}", path.display(), e))),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn lane_path(&self, lane: &str, suffix: &str) -> PathBuf {
+        self.dir.join(format!("{}{}", lane, suffix))
+    }
+
+    fn lane(&self, kind: QueueKind) -> String {
+        match kind {
+            QueueKind::Control => self.control_lane.clone(),
+            _ => kind.to_string(),
+        }
+    }
+
+    fn queue_file(&self, kind: QueueKind) -> PathBuf {
+        self.lane_path(&self.lane(kind), ".jsonl")
+    }
+
+    fn locked_dir(&self, kind: QueueKind) -> PathBuf {
+        self.lane_path(&self.lane(kind), ".locked")
+    }
+
+    fn abandoned_dir(&self, kind: QueueKind) -> PathBuf {
+        self.lane_path(&self.lane(kind), ".abandoned")
+    }
+
+    fn locked_file(&self, receipt: &Receipt) -> PathBuf {
+        self.locked_dir(receipt.queue).join(format!("{}.json", receipt.lock_id))
+    }
+
+    pub async fn push_search(&self, job: &SearchJob) -> CrawlerResult<()> {
+        let _lock = self.lock_dir().await?;
+        self.append(&self.queue_file(QueueKind::Search), job).await
+    }
+
+    pub async fn push_profiles(&self, job: &ProfileJob) -> CrawlerResult<()> {
+        let _lock = self.lock_dir().await?;
+        self.append(&self.queue_file(QueueKind::Profiles), job).await
+    }
+
+    /// Appends the command for every subscribed worker, or to `control.jsonl` while nobody subscribed.
+    pub async fn push_control(&self, command: &ControlCommand) -> CrawlerResult<()> {
+        let _lock = self.lock_dir().await?;
+        let subscriptions = self.subscriptions().await?;
+        if subscriptions.is_empty() {
+            return self
+                .append(&self.lane_path(&QueueKind::Control.to_string(), ".jsonl"), command)
+                .await;
+        }
+        for name in subscriptions {
+            self.append(&self.lane_path(&subscription_lane(&name), ".jsonl"), command).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn results(&self) -> CrawlerResult<Vec<PublishedResult>> {
+        let _lock = self.lock_dir().await?;
+        let lines = self.read_lines(&self.dir.join(RESULT_FILE)).await?;
+        lines
+            .iter()
+            .map(|line| serde_json::from_str(line).map_err(|e| ParseError(format!("Invalid result line {}", e))))
+            .collect()
+    }
+
+    async fn read_lines(&self, path: &Path) -> CrawlerResult<Vec<String>> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(content) => Ok(content.lines().filter(|line| !line.trim().is_empty()).map(str::to_string).collect()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(QueueError(format!("Failed to read {} {}", path.display(), e))),
+        }
+    }
+
+    async fn write<T: Serialize>(&self, path: &Path, data: &T) -> CrawlerResult<()> {
+        let json = match serde_json::to_string(data) {
+            Ok(json) => json,
+            Err(e) => return Err(ParseError(format!("Failed to serialize queue message {}", e))),
+        };
+        match tokio::fs::write(path, json).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(QueueError(format!("Failed to write {} {}", path.display(), e))),
+        }
+    }
+
+    async fn append<T: Serialize>(&self, path: &Path, data: &T) -> CrawlerResult<()> {
+        let mut line = match serde_json::to_string(data) {
+            Ok(line) => line,
+            Err(e) => return Err(ParseError(format!("Failed to serialize queue message {}", e))),
+        };
+        line.push('\n');
+        let mut handle = match tokio::fs::OpenOptions::new().create(true).append(true).open(path).await {
+            Ok(handle) => handle,
+            Err(e) => return Err(QueueError(format!("Failed to open {} {}", path.display(), e))),
+        };
+        // Tokio finishes a write in the background, it has to reach the file while the directory is locked
+        if let Err(e) = handle.write_all(line.as_bytes()).await {
+            return Err(QueueError(format!("Failed to write {} {}", path.display(), e)));
+        }
+        match handle.flush().await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(QueueError(format!("Failed to write {} {}", path.display(), e))),
+        }
+    }
+
+    /// Finds the oldest abandoned message, or else the first line of the queue file, without taking it off
+    /// the queue yet.
+    async fn next_message(&self, kind: QueueKind) -> CrawlerResult<Option<(LockedMessage, Source)>> {
+        let abandoned_dir = self.abandoned_dir(kind);
+        if let Some(name) = list(&abandoned_dir).await?.into_iter().next() {
+            let path = abandoned_dir.join(name);
+            let raw = match tokio::fs::read_to_string(&path).await {
+                Ok(raw) => raw,
+                Err(e) => return Err(QueueError(format!("Failed to read {} {}", path.display(), e))),
+            };
+            let message = match serde_json::from_str(&raw) {
+                Ok(message) => message,
+                Err(e) => {
+                    // Delivered as a malformed message, which the worker moves to the dead-letter store
+                    warn!("Invalid abandoned {} message {} {}", kind, path.display(), e);
+                    LockedMessage {
+                        delivery_count: 0,
+                        payload: raw,
+                    }
+                }
+            };
+            return Ok(Some((message, Source::Abandoned(path))));
+        }
+
+        let path = self.queue_file(kind);
+        let mut lines = self.read_lines(&path).await?;
+        if lines.is_empty() {
+            return Ok(None);
+        }
+        let first = lines.remove(0);
+        let mut rest = lines.join("\n");
+        if !rest.is_empty() {
+            rest.push('\n');
+        }
+        let message = LockedMessage {
+            delivery_count: 0,
+            payload: first,
+        };
+        Ok(Some((message, Source::QueueFile { path, rest })))
+    }
+
+    async fn take(&self, source: Source) -> CrawlerResult<()> {
+        match source {
+            Source::Abandoned(path) => match tokio::fs::remove_file(&path).await {
+                Ok(_) => Ok(()),
+                Err(e) => Err(QueueError(format!("Failed to take message from {} {}", path.display(), e))),
+            },
+            Source::QueueFile { path, rest } => {
+                // Written aside and renamed, truncating the queue file in place would lose every job on a
+                // crash or a full disk
+                let mut tmp = path.clone().into_os_string();
+                tmp.push(".tmp");
+                let tmp = PathBuf::from(tmp);
+                if let Err(e) = tokio::fs::write(&tmp, rest).await {
+                    return Err(QueueError(format!("Failed to take message from {} {}", path.display(), e)));
+                }
+                rename(&tmp, &path).await
+            }
+        }
+    }
+
+    /// The locked copy is written before the message is taken off the queue, a crash in between
+    /// delivers the message again rather than losing it.
+    async fn receive<T: DeserializeOwned>(&self, kind: QueueKind) -> CrawlerResult<Option<Delivery<T>>> {
+        let _lock = self.lock_dir().await?;
+        let (mut locked, source) = match self.next_message(kind).await? {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+        let message = parse_message(&locked.payload);
+        locked.delivery_count += 1;
+        let receipt = Receipt {
+            queue: kind,
+            lock_id: format!("{}@{}", next_lock_id(), self.owner),
+            delivery_count: locked.delivery_count,
+        };
+        let locked_file = self.locked_file(&receipt);
+        self.write(&locked_file, &locked).await?;
+        if let Err(e) = self.take(source).await {
+            // Left on the queue, so the locked copy has to go or the message would be delivered twice
+            let _ = tokio::fs::remove_file(&locked_file).await;
+            return Err(e);
+        }
+        Ok(Some(Delivery { message, receipt }))
+    }
+
+    async fn wait_for<T: DeserializeOwned>(&self, kind: QueueKind, wait: Duration) -> CrawlerResult<Option<Delivery<T>>> {
+        let deadline = Instant::now() + wait;
+        loop {
+            if let Some(delivery) = self.receive(kind).await? {
+                return Ok(Some(delivery));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
+    fn ensure_locked(&self, receipt: &Receipt) -> CrawlerResult<PathBuf> {
+        let path = self.locked_file(receipt);
+        match path.exists() {
+            true => Ok(path),
+            false => Err(QueueError(format!("No {} message locked by {}", receipt.queue, receipt.lock_id))),
+        }
+    }
+}
+
+impl WorkQueue for FileQueue {
+    async fn dequeue_search(&self, wait: Duration) -> CrawlerResult<Option<Delivery<SearchJob>>> {
+        self.wait_for(QueueKind::Search, wait).await
+    }
+
+    async fn dequeue_profiles(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ProfileJob>>> {
+        self.wait_for(QueueKind::Profiles, wait).await
+    }
+
+    async fn dequeue_control(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ControlCommand>>> {
+        self.wait_for(QueueKind::Control, wait).await
+    }
+
+    async fn complete(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        let _lock = self.lock_dir().await?;
+        let path = self.ensure_locked(receipt)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(QueueError(format!("Failed to remove {} {}", path.display(), e))),
+        }
+    }
+
+    async fn abandon(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        let _lock = self.lock_dir().await?;
+        let path = self.ensure_locked(receipt)?;
+        rename(&path, &self.abandoned_dir(receipt.queue).join(format!("{}.json", receipt.lock_id))).await
+    }
+
+    /// Locks on files never expire, so this only checks that the lock is still held.
+    async fn renew_lock(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.ensure_locked(receipt).map(|_| ())
+    }
+
+    async fn requeue_search(&self, job: &SearchJob) -> CrawlerResult<()> {
+        self.push_search(job).await
+    }
+
+    async fn requeue_profiles(&self, job: &ProfileJob) -> CrawlerResult<()> {
+        self.push_profiles(job).await
+    }
+
+    async fn requeue_control(&self, command: &ControlCommand) -> CrawlerResult<()> {
+        self.push_control(command).await
+    }
+
+    async fn publish_result<T>(&self, data: &T, label: Label) -> CrawlerResult<()>
+    where
+        T: Serialize + Send + Sync,
+    {
+        let data = match serde_json::to_value(data) {
+            Ok(data) => data,
+            Err(e) => return Err(ParseError(format!("Failed to serialize result {}", e))),
+        };
+        let _lock = self.lock_dir().await?;
+        self.append(&self.dir.join(RESULT_FILE), &PublishedResult { label, data }).await
+    }
+}
@@ -0,0 +1,232 @@
+use crate::azure::json::{ProfileJob, SearchJob};
+use crate::azure::Label;
+use crate::control::ControlCommand;
+use crate::errors::CrawlerError;
+use crate::errors::CrawlerError::{ParseError, QueueError};
+use crate::errors::CrawlerResult;
+use crate::queue::{next_lock_id, Delivery, PublishedResult, QueueKind, Receipt, WorkQueue};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+struct Entry<T> {
+    delivery_count: u32,
+    message: T,
+}
+
+struct Lane<T> {
+    kind: QueueKind,
+    ready: VecDeque<Entry<T>>,
+    locked: HashMap<String, Entry<T>>,
+}
+
+impl<T: Clone> Lane<T> {
+    fn new(kind: QueueKind) -> Self {
+        Self {
+            kind,
+            ready: VecDeque::new(),
+            locked: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, message: T) {
+        self.ready.push_back(Entry {
+            delivery_count: 0,
+            message,
+        });
+    }
+
+    fn receive(&mut self) -> Option<Delivery<T>> {
+        let mut entry = self.ready.pop_front()?;
+        entry.delivery_count += 1;
+        let receipt = Receipt {
+            queue: self.kind,
+            lock_id: next_lock_id(),
+            delivery_count: entry.delivery_count,
+        };
+        let message = entry.message.clone();
+        self.locked.insert(receipt.lock_id.clone(), entry);
+        Some(Delivery {
+            message: Ok(message),
+            receipt,
+        })
+    }
+
+    fn unlock(&mut self, receipt: &Receipt) -> CrawlerResult<Entry<T>> {
+        match self.locked.remove(&receipt.lock_id) {
+            Some(entry) => Ok(entry),
+            None => Err(QueueError(format!("No {} message locked by {}", self.kind, receipt.lock_id))),
+        }
+    }
+
+    fn complete(&mut self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.unlock(receipt).map(|_| ())
+    }
+
+    /// Abandoned messages go to the front, like a redelivery from Service Bus.
+    fn abandon(&mut self, receipt: &Receipt) -> CrawlerResult<()> {
+        let entry = self.unlock(receipt)?;
+        self.ready.push_front(entry);
+        Ok(())
+    }
+
+    fn renew(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        match self.locked.contains_key(&receipt.lock_id) {
+            true => Ok(()),
+            false => Err(QueueError(format!("No {} message locked by {}", self.kind, receipt.lock_id))),
+        }
+    }
+}
+
+/// Queue held entirely in memory, meant for tests and local runs. Locks never expire.
+pub struct MemoryQueue {
+    searches: Mutex<Lane<SearchJob>>,
+    profiles: Mutex<Lane<ProfileJob>>,
+    control: Mutex<Lane<ControlCommand>>,
+    results: Mutex<Vec<PublishedResult>>,
+    /// Wakes receivers waiting on an empty queue
+    arrived: Notify,
+}
+
+impl Default for MemoryQueue {
+    fn default() -> Self {
+        Self {
+            searches: Mutex::new(Lane::new(QueueKind::Search)),
+            profiles: Mutex::new(Lane::new(QueueKind::Profiles)),
+            control: Mutex::new(Lane::new(QueueKind::Control)),
+            results: Mutex::new(Vec::new()),
+            arrived: Notify::new(),
+        }
+    }
+}
+
+impl MemoryQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_search(&self, job: SearchJob) {
+        self.searches.lock().unwrap().push(job);
+        self.arrived.notify_waiters();
+    }
+
+    pub fn push_profiles(&self, job: ProfileJob) {
+        self.profiles.lock().unwrap().push(job);
+        self.arrived.notify_waiters();
+    }
+
+    pub fn push_control(&self, command: ControlCommand) {
+        self.control.lock().unwrap().push(command);
+        self.arrived.notify_waiters();
+    }
+
+    /// Messages waiting for delivery, not counting locked ones.
+    pub fn pending_searches(&self) -> usize {
+        self.searches.lock().unwrap().ready.len()
+    }
+
+    pub fn pending_profiles(&self) -> usize {
+        self.profiles.lock().unwrap().ready.len()
+    }
+
+    pub fn locked_messages(&self) -> usize {
+        self.searches.lock().unwrap().locked.len() + self.profiles.lock().unwrap().locked.len() + self.control.lock().unwrap().locked.len()
+    }
+
+    pub fn results(&self) -> Vec<PublishedResult> {
+        self.results.lock().unwrap().clone()
+    }
+
+    async fn wait_for<T>(&self, wait: Duration, receive: impl Fn() -> Option<Delivery<T>>) -> Option<Delivery<T>> {
+        let deadline = Instant::now() + wait;
+        loop {
+            // Registered before checking, so a push in between is not missed
+            let arrived = self.arrived.notified();
+            tokio::pin!(arrived);
+            arrived.as_mut().enable();
+            if let Some(delivery) = receive() {
+                return Some(delivery);
+            }
+            if tokio::time::timeout_at(deadline, arrived).await.is_err() {
+                return None;
+            }
+        }
+    }
+}
+
+fn never_received() -> CrawlerError {
+    QueueError("Results are only published, they are never locked".to_string())
+}
+
+impl WorkQueue for MemoryQueue {
+    async fn dequeue_search(&self, wait: Duration) -> CrawlerResult<Option<Delivery<SearchJob>>> {
+        Ok(self.wait_for(wait, || self.searches.lock().unwrap().receive()).await)
+    }
+
+    async fn dequeue_profiles(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ProfileJob>>> {
+        Ok(self.wait_for(wait, || self.profiles.lock().unwrap().receive()).await)
+    }
+
+    async fn dequeue_control(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ControlCommand>>> {
+        Ok(self.wait_for(wait, || self.control.lock().unwrap().receive()).await)
+    }
+
+    async fn complete(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        match receipt.queue {
+            QueueKind::Search => self.searches.lock().unwrap().complete(receipt),
+            QueueKind::Profiles => self.profiles.lock().unwrap().complete(receipt),
+            QueueKind::Control => self.control.lock().unwrap().complete(receipt),
+            QueueKind::Results => Err(never_received()),
+        }
+    }
+
+    async fn abandon(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        match receipt.queue {
+            QueueKind::Search => self.searches.lock().unwrap().abandon(receipt)?,
+            QueueKind::Profiles => self.profiles.lock().unwrap().abandon(receipt)?,
+            QueueKind::Control => self.control.lock().unwrap().abandon(receipt)?,
+            QueueKind::Results => return Err(never_received()),
+        }
+        self.arrived.notify_waiters();
+        Ok(())
+    }
+
+    async fn renew_lock(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        match receipt.queue {
+            QueueKind::Search => self.searches.lock().unwrap().renew(receipt),
+            QueueKind::Profiles => self.profiles.lock().unwrap().renew(receipt),
+            QueueKind::Control => self.control.lock().unwrap().renew(receipt),
+            QueueKind::Results => Err(never_received()),
+        }
+    }
+
+    async fn requeue_search(&self, job: &SearchJob) -> CrawlerResult<()> {
+        self.push_search(job.clone());
+        Ok(())
+    }
+
+    async fn requeue_profiles(&self, job: &ProfileJob) -> CrawlerResult<()> {
+        self.push_profiles(job.clone());
+        Ok(())
+    }
+
+    async fn requeue_control(&self, command: &ControlCommand) -> CrawlerResult<()> {
+        self.push_control(command.clone());
+        Ok(())
+    }
+
+    async fn publish_result<T>(&self, data: &T, label: Label) -> CrawlerResult<()>
+    where
+        T: Serialize + Send + Sync,
+    {
+        let data = match serde_json::to_value(data) {
+            Ok(data) => data,
+            Err(e) => return Err(ParseError(format!("Failed to serialize result {}", e))),
+        };
+        self.results.lock().unwrap().push(PublishedResult { label, data });
+        Ok(())
+    }
+}
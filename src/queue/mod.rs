@@ -0,0 +1,142 @@
+pub mod azure;
+pub mod dead_letter;
+pub mod file;
+pub mod memory;
+pub mod outbox;
+
+use crate::azure::json::{ProfileJob, SearchJob};
+use crate::azure::Label;
+use crate::control::ControlCommand;
+use crate::errors::CrawlerResult;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum QueueKind {
+    #[serde(rename = "searches")]
+    Search,
+    #[serde(rename = "profiles")]
+    Profiles,
+    #[serde(rename = "control")]
+    Control,
+    /// Results for the manager, they are only published so this only shows up on dead letters
+    #[serde(rename = "results")]
+    Results,
+}
+
+impl Display for QueueKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueKind::Search => write!(f, "searches"),
+            QueueKind::Profiles => write!(f, "profiles"),
+            QueueKind::Control => write!(f, "control"),
+            QueueKind::Results => write!(f, "results"),
+        }
+    }
+}
+
+/// Proof of a locked message. Until it is completed the message stays in the queue and is delivered
+/// again once abandoned or once its lock expires.
+#[derive(Clone, Debug)]
+pub struct Receipt {
+    pub queue: QueueKind,
+    /// Backend specific lock handle, the lock URI for Azure
+    pub lock_id: String,
+    /// 1 on the first delivery
+    pub delivery_count: u32,
+}
+
+/// Payload that could not be parsed into the expected message.
+#[derive(Clone, Debug)]
+pub struct Malformed {
+    pub raw: String,
+    pub error: String,
+}
+
+pub struct Delivery<T> {
+    /// Malformed payloads are still delivered, so the worker can dead-letter them instead of losing them
+    pub message: Result<T, Malformed>,
+    pub receipt: Receipt,
+}
+
+pub(crate) fn parse_message<T: DeserializeOwned>(raw: &str) -> Result<T, Malformed> {
+    serde_json::from_str(raw).map_err(|e| Malformed {
+        raw: raw.to_string(),
+        error: e.to_string(),
+    })
+}
+
+/// Source of crawl jobs and sink for their results. Implemented by the Azure Service Bus client and
+/// by the in-memory and file backed queues used for running the worker offline.
+///
+/// Jobs are received with peek-lock semantics: every delivery has to be either completed once the job
+/// is done or abandoned so that it is delivered again.
+pub trait WorkQueue: Send + Sync {
+    /// Waits up to `wait` for a search, returns `None` when none arrived in time.
+    fn dequeue_search(&self, wait: Duration) -> impl Future<Output = CrawlerResult<Option<Delivery<SearchJob>>>> + Send;
+
+    /// Waits up to `wait` for profile ids, returns `None` when none arrived in time.
+    fn dequeue_profiles(&self, wait: Duration) -> impl Future<Output = CrawlerResult<Option<Delivery<ProfileJob>>>> + Send;
+
+    /// Waits up to `wait` for a command changing the running crawler, returns `None` when none arrived in time.
+    fn dequeue_control(&self, wait: Duration) -> impl Future<Output = CrawlerResult<Option<Delivery<ControlCommand>>>> + Send;
+
+    /// Removes the message from the queue for good.
+    fn complete(&self, receipt: &Receipt) -> impl Future<Output = CrawlerResult<()>> + Send;
+
+    /// Releases the lock so the message is delivered again.
+    fn abandon(&self, receipt: &Receipt) -> impl Future<Output = CrawlerResult<()>> + Send;
+
+    /// Extends the lock of a message whose job is still running.
+    fn renew_lock(&self, receipt: &Receipt) -> impl Future<Output = CrawlerResult<()>> + Send;
+
+    /// Hands an unfinished search back to the queue.
+    fn requeue_search(&self, job: &SearchJob) -> impl Future<Output = CrawlerResult<()>> + Send;
+
+    /// Hands unfinished profile ids back to the queue.
+    fn requeue_profiles(&self, job: &ProfileJob) -> impl Future<Output = CrawlerResult<()>> + Send;
+
+    /// Sends a command to the worker reading this queue.
+    fn requeue_control(&self, command: &ControlCommand) -> impl Future<Output = CrawlerResult<()>> + Send;
+
+    fn publish_result<T>(&self, data: &T, label: Label) -> impl Future<Output = CrawlerResult<()>> + Send
+    where
+        T: Serialize + Send + Sync;
+}
+
+/// Azure locks messages for 30 seconds by default, renewing at a third of that leaves room for retries.
+pub const LOCK_RENEW_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Keeps renewing the lock until the returned handle is aborted.
+pub fn spawn_lock_renewal<Q: WorkQueue + 'static>(queue: Arc<Q>, receipt: Receipt, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(e) = queue.renew_lock(&receipt).await {
+                warn!("Failed to renew lock on {} message {}", receipt.queue, e);
+            }
+        }
+    })
+}
+
+static NEXT_LOCK_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Unique, roughly time ordered id for the offline queues.
+pub(crate) fn next_lock_id() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    format!("{:024}-{:08}", nanos, NEXT_LOCK_ID.fetch_add(1, Relaxed))
+}
+
+/// A result as recorded by the offline queues.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct PublishedResult {
+    pub label: Label,
+    pub data: serde_json::Value,
+}
@@ -0,0 +1,257 @@
+use crate::azure::Label;
+use crate::errors::CrawlerError::{ParseError, QueueError};
+use crate::errors::CrawlerResult;
+use crate::metrics::MANAGER_PUSH_RETRIES;
+use crate::queue::dead_letter::{DeadLetter, DeadLetterPolicy, DeadLetterStore};
+use crate::queue::file::{create_dir, list, rename};
+use crate::queue::{next_lock_id, QueueKind, WorkQueue};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinHandle;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OutboxEntry {
+    pub id: String,
+    pub label: Label,
+    pub data: serde_json::Value,
+    /// Failed publish attempts so far
+    pub attempts: u32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Exponential backoff between publish attempts while the destination keeps failing.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay after the given number of consecutive failures, starting at 1.
+    pub fn delay(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Durable journal of results waiting to be published. A result is written to disk before any send
+/// is attempted and is only removed once the queue acknowledged it, so results survive both a manager
+/// outage and a worker restart. Entries are published oldest first.
+///
+/// Results the manager rejects, or that failed more than `policy.max_attempts` times, go to the
+/// dead-letter store so they stop holding up the ones behind them. Entries that cannot be read are
+/// renamed to `<id>.json.invalid` and skipped.
+pub struct Outbox<Q: WorkQueue> {
+    dir: PathBuf,
+    queue: Arc<Q>,
+    dead_letters: DeadLetterStore,
+    policy: DeadLetterPolicy,
+    backoff: Backoff,
+    notify: Notify,
+    /// Serializes drains so an entry is never sent twice concurrently
+    drain_lock: Mutex<()>,
+}
+
+impl<Q: WorkQueue + 'static> Outbox<Q> {
+    pub async fn open(
+        dir: impl AsRef<Path>,
+        queue: Arc<Q>,
+        dead_letters: DeadLetterStore,
+        policy: DeadLetterPolicy,
+        backoff: Backoff,
+    ) -> CrawlerResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        create_dir(&dir).await?;
+        Ok(Self {
+            dir,
+            queue,
+            dead_letters,
+            policy,
+            backoff,
+            notify: Notify::new(),
+            drain_lock: Mutex::new(()),
+        })
+    }
+
+    fn entry_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    async fn write_entry(&self, entry: &OutboxEntry) -> CrawlerResult<()> {
+        let json = match serde_json::to_string(entry) {
+            Ok(json) => json,
+            Err(e) => return Err(ParseError(format!("Failed to serialize outbox entry {}", e))),
+        };
+        // Written aside and renamed, so a crash never leaves a half written entry behind
+        let tmp = self.dir.join(format!("{}.tmp", entry.id));
+        if let Err(e) = tokio::fs::write(&tmp, json).await {
+            return Err(QueueError(format!("Failed to write outbox entry {} {}", tmp.display(), e)));
+        }
+        rename(&tmp, &self.entry_path(&entry.id)).await
+    }
+
+    async fn read_entry(&self, name: &str) -> CrawlerResult<OutboxEntry> {
+        let path = self.dir.join(name);
+        let raw = match tokio::fs::read_to_string(&path).await {
+            Ok(raw) => raw,
+            Err(e) => return Err(QueueError(format!("Failed to read outbox entry {} {}", path.display(), e))),
+        };
+        match serde_json::from_str(&raw) {
+            Ok(entry) => Ok(entry),
+            Err(e) => Err(ParseError(format!("Invalid outbox entry {} {}", path.display(), e))),
+        }
+    }
+
+    async fn entry_names(&self) -> CrawlerResult<Vec<String>> {
+        Ok(list(&self.dir).await?.into_iter().filter(|name| name.ends_with(".json")).collect())
+    }
+
+    /// Journals the result and wakes the sender. Once this returns the result is safe on disk.
+    pub async fn enqueue<T: Serialize>(&self, data: &T, label: Label) -> CrawlerResult<String> {
+        let data = match serde_json::to_value(data) {
+            Ok(data) => data,
+            Err(e) => return Err(ParseError(format!("Failed to serialize result {}", e))),
+        };
+        let entry = OutboxEntry {
+            id: next_lock_id(),
+            label,
+            data,
+            attempts: 0,
+            created_at: chrono::Utc::now(),
+        };
+        self.write_entry(&entry).await?;
+        self.notify.notify_one();
+        Ok(entry.id)
+    }
+
+    pub async fn pending(&self) -> CrawlerResult<Vec<OutboxEntry>> {
+        let mut entries = Vec::new();
+        for name in self.entry_names().await? {
+            entries.push(self.read_entry(&name).await?);
+        }
+        Ok(entries)
+    }
+
+    async fn remove_entry(&self, name: &str) -> CrawlerResult<()> {
+        match tokio::fs::remove_file(self.dir.join(name)).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(QueueError(format!("Failed to remove outbox entry {} {}", name, e))),
+        }
+    }
+
+    async fn quarantine(&self, name: &str) -> CrawlerResult<()> {
+        let path = self.dir.join(name);
+        rename(&path, &self.dir.join(format!("{}.invalid", name))).await
+    }
+
+    async fn dead_letter(&self, name: &str, entry: &OutboxEntry, reason: String) -> CrawlerResult<()> {
+        let letter = DeadLetter {
+            id: next_lock_id(),
+            queue: QueueKind::Results,
+            reason,
+            delivery_count: entry.attempts,
+            payload: serde_json::to_string(entry).unwrap_or_default(),
+            dead_lettered_at: chrono::Utc::now(),
+        };
+        self.dead_letters.store(&letter).await?;
+        warn!("Dead-lettered outbox entry {} as {}: {}", entry.id, letter.id, letter.reason);
+        self.remove_entry(name).await
+    }
+
+    /// Publishes entries oldest first until the outbox is empty or a publish fails in a way that may
+    /// pass on retry. Returns how many entries are still waiting.
+    pub async fn drain(&self) -> CrawlerResult<usize> {
+        let _guard = self.drain_lock.lock().await;
+        let names = self.entry_names().await?;
+        for (index, name) in names.iter().enumerate() {
+            let mut entry = match self.read_entry(name).await {
+                Ok(entry) => entry,
+                Err(e) => {
+                    error!("Skipping unreadable outbox entry {}", e);
+                    self.quarantine(name).await?;
+                    continue;
+                }
+            };
+            match self.queue.publish_result(&entry.data, entry.label).await {
+                Ok(_) => {
+                    self.remove_entry(name).await?;
+                    debug!("Published outbox entry {} after {} failed attempts", entry.id, entry.attempts);
+                }
+                Err(e) if !e.is_retryable() => {
+                    entry.attempts += 1;
+                    self.dead_letter(name, &entry, format!("Rejected {}", e)).await?;
+                }
+                Err(e) => {
+                    entry.attempts += 1;
+                    MANAGER_PUSH_RETRIES.inc();
+                    warn!("Failed to publish outbox entry {}, attempt {} {}", entry.id, entry.attempts, e);
+                    if entry.attempts >= self.policy.max_attempts {
+                        self.dead_letter(name, &entry, format!("Failed {} publish attempts, last {}", entry.attempts, e))
+                            .await?;
+                        continue;
+                    }
+                    self.write_entry(&entry).await?;
+                    return Ok(names.len() - index);
+                }
+            }
+        }
+        Ok(0)
+    }
+
+    /// Drains until empty or until the deadline passes. Returns how many entries are still waiting.
+    pub async fn flush(&self, deadline: Duration) -> CrawlerResult<usize> {
+        let start = tokio::time::Instant::now();
+        let mut failures = 0;
+        loop {
+            let remaining = self.drain().await?;
+            if remaining == 0 {
+                return Ok(0);
+            }
+            failures += 1;
+            let delay = self.backoff.delay(failures);
+            if start.elapsed() + delay > deadline {
+                return Ok(remaining);
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Sends whatever was left over by a previous run, then keeps sending new entries as they arrive,
+    /// backing off while the destination is failing.
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut failures = 0;
+            loop {
+                match self.drain().await {
+                    Ok(0) => {
+                        failures = 0;
+                        self.notify.notified().await;
+                    }
+                    Ok(remaining) => {
+                        failures += 1;
+                        let delay = self.backoff.delay(failures);
+                        info!("{} results waiting in outbox, retrying in {:?}", remaining, delay);
+                        tokio::time::sleep(delay).await;
+                    }
+                    Err(e) => {
+                        failures += 1;
+                        error!("Failed to drain outbox {}", e);
+                        tokio::time::sleep(self.backoff.delay(failures)).await;
+                    }
+                }
+            }
+        })
+    }
+}
@@ -1,35 +1,116 @@
 pub mod services;
 
+use crate::services::admin::{control_state, disable_account, enable_account, pause, reauthenticate, resume, set_budget};
+use crate::services::auth::require_api_key;
 use crate::services::hello;
+use crate::services::jobs::{cancel_job, job_events, job_status, submit_profiles, submit_search};
+use crate::services::messages::send_messages;
+use crate::services::openapi::openapi_json;
 use crate::services::request::{profiles, search};
-use actix_web::{App, HttpServer};
+use crate::services::status::ServerStatus;
+use actix_web::middleware::from_fn;
+use actix_web::{web, App, HttpServer};
+use chrono::Utc;
+use log::warn;
+use omicron_crawler::auth::ApiKeys;
+use omicron_crawler::driver::service::ChromeDriverService;
+use omicron_crawler::driver::session_manager::SessionManager;
 use omicron_crawler::env::{get_env, load_env};
+use omicron_crawler::health::{self, StatusProvider};
+use omicron_crawler::jobs::JobManager;
 use omicron_crawler::linkedin::api::crawler::Crawler;
-use omicron_crawler::linkedin::api::rate_limits::RateLimiter;
+use omicron_crawler::linkedin::api::rate_limits::{RateLimiter, AVG_RESPONSE_TIME_MS};
 use omicron_crawler::logger::Logger;
+use omicron_crawler::metrics::{self, track_http};
+use std::sync::Arc;
 use tokio::sync::OnceCell;
 static CRAWLER: OnceCell<Crawler> = OnceCell::const_new();
+static BROWSERS: OnceCell<SessionManager<ChromeDriverService>> = OnceCell::const_new();
 
 pub async fn get_crawler() -> &'static Crawler {
     CRAWLER
         .get_or_init(|| async {
             let username = get_env().await.linkedin_username.as_str();
             let password = get_env().await.linkedin_password.as_str();
-            let mut crawler = Crawler::new(RateLimiter::new(100, 800), username, password).await;
+            let rate_limits = RateLimiter::new(get_env().await.profiles_per_hour, AVG_RESPONSE_TIME_MS);
+            let mut crawler = Crawler::new(rate_limits, username, password).await;
             crawler
         })
         .await
 }
+/// Starts the browser pool used for Sales Navigator, nothing is started when `DRIVER_SESSION_COUNT` is 0.
+async fn start_browsers() {
+    let env = get_env().await;
+    if env.driver_session_count == 0 {
+        warn!("DRIVER_SESSION_COUNT is 0, messaging is disabled");
+        return;
+    }
+    BROWSERS
+        .get_or_init(|| async {
+            SessionManager::new(
+                env.driver_host.as_str(),
+                env.driver_port,
+                env.driver_session_count,
+                env.driver_path.as_str(),
+                env.profile_path.as_str(),
+                env.browser_binary_path.as_deref(),
+            )
+            .await
+        })
+        .await;
+}
+
+pub fn get_browsers() -> Option<&'static SessionManager<ChromeDriverService>> {
+    BROWSERS.get()
+}
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> std::io::Result<()> {
     load_env();
     let env = get_env().await;
     Logger::init(env.log_level);
-    get_crawler().await;
-    let result = HttpServer::new(|| App::new().service(hello).service(search).service(profiles))
-        .bind((env.host.as_str(), env.port))?
-        .system_exit()
-        .run()
-        .await;
+    let started_at = Utc::now();
+    let jobs = JobManager::new(env.job_retention);
+    jobs.spawn(get_crawler().await);
+    start_browsers().await;
+    let status: Arc<dyn StatusProvider> = Arc::new(ServerStatus {
+        started_at,
+        crawler: get_crawler().await,
+        jobs: jobs.clone(),
+    });
+    let keys = web::Data::new(ApiKeys::new(env.api_keys.clone()).with_admins(env.admin_clients.clone()));
+    if !keys.is_enabled() {
+        warn!("No API_KEYS configured, the server accepts requests from anyone");
+    }
+    let result = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::from(jobs.clone()))
+            .app_data(keys.clone())
+            .app_data(web::Data::from(status.clone()))
+            .wrap(from_fn(require_api_key))
+            .wrap(from_fn(track_http))
+            .service(hello)
+            .service(openapi_json)
+            .configure(health::configure)
+            .configure(metrics::configure)
+            .service(search)
+            .service(profiles)
+            .service(submit_search)
+            .service(submit_profiles)
+            .service(job_status)
+            .service(job_events)
+            .service(cancel_job)
+            .service(send_messages)
+            .service(control_state)
+            .service(pause)
+            .service(resume)
+            .service(set_budget)
+            .service(disable_account)
+            .service(enable_account)
+            .service(reauthenticate)
+    })
+    .bind((env.host.as_str(), env.port))?
+    .system_exit()
+    .run()
+    .await;
     result
 }
@@ -0,0 +1,122 @@
+use crate::get_crawler;
+use crate::services::auth::require_admin;
+use actix_web::web::{Data, Path, ReqData};
+use actix_web::{get, post, put, HttpResponse};
+use log::error;
+use omicron_crawler::auth::{ApiKey, ApiKeys};
+use omicron_crawler::control::{Budget, ControlCommand, ControlState};
+use omicron_crawler::validation::{Valid, ValidationErrors};
+
+async fn apply(keys: &ApiKeys, client: Option<ReqData<ApiKey>>, command: ControlCommand) -> HttpResponse {
+    if let Some(response) = require_admin(keys, client) {
+        return response;
+    }
+    let crawler = get_crawler().await;
+    if let Some(account) = command.account().filter(|account| *account != crawler.control_state().account) {
+        return HttpResponse::NotFound().body(format!("Unknown account {}", account));
+    }
+    match crawler.apply(command).await {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(e) => {
+            error!("Failed to apply control command {}", e);
+            HttpResponse::InternalServerError().body(format!("Failed to apply control command {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    responses((status = 200, body = ControlState), (status = 403, description = "Not an admin client")),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[get("/admin/control")]
+pub async fn control_state(keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    if let Some(response) = require_admin(&keys, client) {
+        return response;
+    }
+    HttpResponse::Ok().json(get_crawler().await.control_state())
+}
+
+/// Stops crawling before the next profile or search page, jobs in progress wait until resumed.
+#[utoipa::path(
+    tag = "admin",
+    responses((status = 200, body = ControlState), (status = 403, description = "Not an admin client")),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[post("/admin/pause")]
+pub async fn pause(keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    apply(&keys, client, ControlCommand::Pause).await
+}
+
+#[utoipa::path(
+    tag = "admin",
+    responses((status = 200, body = ControlState), (status = 403, description = "Not an admin client")),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[post("/admin/resume")]
+pub async fn resume(keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    apply(&keys, client, ControlCommand::Resume).await
+}
+
+#[utoipa::path(
+    tag = "admin",
+    request_body = Budget,
+    responses(
+        (status = 200, body = ControlState),
+        (status = 400, description = "Invalid budget", body = ValidationErrors),
+        (status = 403, description = "Not an admin client"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[put("/admin/budget")]
+pub async fn set_budget(budget: Valid<Budget>, keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    let profiles_per_hour = budget.profiles_per_hour;
+    apply(&keys, client, ControlCommand::SetBudget { profiles_per_hour }).await
+}
+
+#[utoipa::path(
+    tag = "admin",
+    params(("account" = String, Path, description = "LinkedIn username the crawler logs in with")),
+    responses(
+        (status = 200, body = ControlState),
+        (status = 403, description = "Not an admin client"),
+        (status = 404, description = "Unknown account"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[post("/admin/accounts/{account}/disable")]
+pub async fn disable_account(account: Path<String>, keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    let account = account.into_inner();
+    apply(&keys, client, ControlCommand::DisableAccount { account }).await
+}
+
+#[utoipa::path(
+    tag = "admin",
+    params(("account" = String, Path, description = "LinkedIn username the crawler logs in with")),
+    responses(
+        (status = 200, body = ControlState),
+        (status = 403, description = "Not an admin client"),
+        (status = 404, description = "Unknown account"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[post("/admin/accounts/{account}/enable")]
+pub async fn enable_account(account: Path<String>, keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    let account = account.into_inner();
+    apply(&keys, client, ControlCommand::EnableAccount { account }).await
+}
+
+/// Logs in again, waiting for requests already sent with the old session.
+#[utoipa::path(
+    tag = "admin",
+    responses(
+        (status = 200, body = ControlState),
+        (status = 403, description = "Not an admin client"),
+        (status = 500, description = "LinkedIn refused the login"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[post("/admin/reauthenticate")]
+pub async fn reauthenticate(keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    apply(&keys, client, ControlCommand::Reauthenticate).await
+}
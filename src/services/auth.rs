@@ -0,0 +1,70 @@
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::{Data, ReqData};
+use actix_web::{Error, HttpMessage, HttpResponse};
+use log::warn;
+use omicron_crawler::auth::{ApiKey, ApiKeys};
+use omicron_crawler::linkedin::api::json::SearchParams;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Reachable without a key, probes, scrapers and API tooling must work without credentials
+const PUBLIC_PATHS: [&str; 5] = ["/", "/healthz", "/readyz", "/metrics", "/openapi.json"];
+
+fn request_key(req: &ServiceRequest) -> Option<&str> {
+    let headers = req.headers();
+    if let Some(key) = headers.get(API_KEY_HEADER) {
+        return key.to_str().ok();
+    }
+    headers.get("Authorization")?.to_str().ok()?.strip_prefix("Bearer ")
+}
+
+/// Rejects requests without a known key with 401 and makes the client available to handlers as
+/// `ReqData<ApiKey>`.
+pub async fn require_api_key(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, Error> {
+    let keys = match req.app_data::<Data<ApiKeys>>() {
+        Some(keys) if keys.is_enabled() && !PUBLIC_PATHS.contains(&req.path()) => keys.clone(),
+        _ => return Ok(next.call(req).await?.map_into_boxed_body()),
+    };
+    let client = match request_key(&req).and_then(|key| keys.authenticate(key)) {
+        Some(client) => client.clone(),
+        None => {
+            let response = HttpResponse::Unauthorized()
+                .insert_header(("WWW-Authenticate", "Bearer"))
+                .body("Missing or unknown api key");
+            return Ok(req.into_response(response));
+        }
+    };
+    req.extensions_mut().insert(client);
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+/// Takes `cost` from the client's quota, returning the 429 response to send when it is used up.
+pub fn charge(keys: &ApiKeys, client: Option<ReqData<ApiKey>>, cost: u32) -> Option<HttpResponse> {
+    let client = client?;
+    match keys.charge(&client, cost) {
+        Ok(()) => None,
+        Err(retry_after) => {
+            warn!("Client {} is over its quota of {}", client.name, client.quota);
+            Some(
+                HttpResponse::TooManyRequests()
+                    .insert_header(("Retry-After", retry_after.as_secs().to_string()))
+                    .body(format!("Quota of {} per hour exceeded", client.quota)),
+            )
+        }
+    }
+}
+
+/// Returns the 403 response to send unless the client may use the admin endpoints.
+pub fn require_admin(keys: &ApiKeys, client: Option<ReqData<ApiKey>>) -> Option<HttpResponse> {
+    match keys.may_administer(client.as_deref()) {
+        true => None,
+        false => Some(HttpResponse::Forbidden().body("Admin endpoints need API_KEYS and a client listed in ADMIN_CLIENTS")),
+    }
+}
+
+/// Searches cost one unit per page, profile requests one per profile.
+pub fn search_cost(params: &SearchParams) -> u32 {
+    params.end.saturating_sub(params.page).max(1) as u32
+}
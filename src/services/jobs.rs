@@ -0,0 +1,176 @@
+use crate::services::auth::{charge, search_cost};
+use actix_web::web::{Bytes, Data, Path, ReqData};
+use actix_web::{delete, get, post, HttpResponse};
+use futures_util::stream;
+use omicron_crawler::auth::{ApiKey, ApiKeys};
+use omicron_crawler::azure::json::ProfileIds;
+use omicron_crawler::jobs::{Job, JobEvent, JobManager, JobRequest, JobStatus};
+use omicron_crawler::linkedin::api::json::SearchParams;
+use omicron_crawler::validation::{Valid, ValidationErrors};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+/// Comment sent on an idle event stream so proxies do not close it
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// The job, unless it belongs to another client. Other clients get the same 404 as for an unknown job.
+fn visible(job: Option<Job>, keys: &ApiKeys, client: &Option<ReqData<ApiKey>>) -> Option<Job> {
+    job.filter(|job| job.is_visible_to(client.as_deref(), keys))
+}
+
+fn not_found(id: &str) -> HttpResponse {
+    HttpResponse::NotFound().body(format!("Job {} not found", id))
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    request_body = SearchParams,
+    responses(
+        (status = 202, description = "Search job queued", body = Job),
+        (status = 400, description = "Invalid search parameters", body = ValidationErrors),
+        (status = 429, description = "Quota exceeded"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[post("/jobs/search")]
+pub async fn submit_search(
+    jobs: Data<JobManager>,
+    search_params: Valid<SearchParams>,
+    keys: Data<ApiKeys>,
+    client: Option<ReqData<ApiKey>>,
+) -> HttpResponse {
+    let owner = client.as_ref().map(|client| client.name.clone());
+    if let Some(response) = charge(&keys, client, search_cost(&search_params)) {
+        return response;
+    }
+    let job = jobs.submit(JobRequest::Search(search_params.into_inner()), owner);
+    HttpResponse::Accepted().json(job)
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    request_body = ProfileIds,
+    responses(
+        (status = 202, description = "Profiles job queued", body = Job),
+        (status = 400, description = "Invalid profile ids", body = ValidationErrors),
+        (status = 429, description = "Quota exceeded"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[post("/jobs/profiles")]
+pub async fn submit_profiles(
+    jobs: Data<JobManager>,
+    profile_ids: Valid<ProfileIds>,
+    keys: Data<ApiKeys>,
+    client: Option<ReqData<ApiKey>>,
+) -> HttpResponse {
+    let owner = client.as_ref().map(|client| client.name.clone());
+    if let Some(response) = charge(&keys, client, profile_ids.ids.len() as u32) {
+        return response;
+    }
+    let job = jobs.submit(JobRequest::Profiles(profile_ids.into_inner()), owner);
+    HttpResponse::Accepted().json(job)
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    params(("id" = String, Path, description = "Id returned when the job was submitted")),
+    responses(
+        (status = 200, description = "Current state of the job with its partial results", body = Job),
+        (status = 404, description = "Unknown job or a job of another client"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[get("/jobs/{id}")]
+pub async fn job_status(jobs: Data<JobManager>, id: Path<String>, keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    match visible(jobs.get(&id), &keys, &client) {
+        Some(job) => HttpResponse::Ok().json(job),
+        None => not_found(&id),
+    }
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    params(("id" = String, Path, description = "Id returned when the job was submitted")),
+    responses(
+        (status = 202, description = "Job cancelled, a running job stops after its current profile or page", body = Job),
+        (status = 409, description = "Job already finished", body = Job),
+        (status = 404, description = "Unknown job or a job of another client"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[delete("/jobs/{id}")]
+pub async fn cancel_job(jobs: Data<JobManager>, id: Path<String>, keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    if visible(jobs.get(&id), &keys, &client).is_none() {
+        return not_found(&id);
+    }
+    match jobs.cancel(&id) {
+        Some(job) if job.status == JobStatus::Completed || job.status == JobStatus::Failed => HttpResponse::Conflict().json(job),
+        Some(job) => HttpResponse::Accepted().json(job),
+        None => not_found(&id),
+    }
+}
+
+struct EventStream {
+    jobs: Arc<JobManager>,
+    id: String,
+    receiver: Receiver<JobEvent>,
+    next: Option<JobEvent>,
+    finished: bool,
+}
+
+impl EventStream {
+    async fn next_message(mut self) -> Option<(Result<Bytes, actix_web::Error>, Self)> {
+        if self.finished {
+            return None;
+        }
+        let event = match self.next.take() {
+            Some(event) => event,
+            None => match tokio::time::timeout(KEEP_ALIVE, self.receiver.recv()).await {
+                Err(_) => return Some((Ok(Bytes::from_static(b": keep-alive\n\n")), self)),
+                Ok(Ok(event)) => event,
+                // Fell too far behind, start over from the current state
+                Ok(Err(RecvError::Lagged(_))) => {
+                    let (job, receiver) = self.jobs.subscribe(&self.id)?;
+                    self.receiver = receiver;
+                    JobEvent::Snapshot { job }
+                }
+                Ok(Err(RecvError::Closed)) => return None,
+            },
+        };
+        self.finished = event.is_last();
+        Some((Ok(Bytes::from(event.to_sse())), self))
+    }
+}
+
+/// Streams the job as Server-Sent Events, a snapshot first and then every profile, search page and
+/// error as it is produced. The stream ends once the job finishes.
+#[utoipa::path(
+    tag = "jobs",
+    params(("id" = String, Path, description = "Id returned when the job was submitted")),
+    responses(
+        (status = 200, description = "Server-Sent Events, each carrying a job event as JSON", body = JobEvent, content_type = "text/event-stream"),
+        (status = 404, description = "Unknown job or a job of another client"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[get("/jobs/{id}/events")]
+pub async fn job_events(jobs: Data<JobManager>, id: Path<String>, keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    let (job, receiver) = match jobs.subscribe(&id) {
+        Some((job, receiver)) if job.is_visible_to(client.as_deref(), &keys) => (job, receiver),
+        _ => return not_found(&id),
+    };
+    let events = EventStream {
+        jobs: jobs.into_inner(),
+        id: id.into_inner(),
+        receiver,
+        next: Some(JobEvent::Snapshot { job }),
+        finished: false,
+    };
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream::unfold(events, EventStream::next_message))
+}
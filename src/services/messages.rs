@@ -0,0 +1,49 @@
+use crate::get_browsers;
+use crate::services::auth::charge;
+use actix_web::web::{Data, ReqData};
+use actix_web::{post, HttpResponse};
+use omicron_crawler::auth::{ApiKey, ApiKeys};
+use omicron_crawler::linkedin::web_driver::messages::{MessageDelivery, MessageRequest};
+use omicron_crawler::linkedin::web_driver::sales_crawler::SalesCrawler;
+use omicron_crawler::validation::{Valid, ValidationErrors};
+
+/// Sessions are held for a whole request, a client retrying sooner would most likely find them busy still
+const BUSY_RETRY_AFTER_SECS: u64 = 30;
+
+/// Sends each InMail from a single browser session, in order.
+#[utoipa::path(
+    tag = "messages",
+    request_body = Vec<MessageRequest>,
+    responses(
+        (status = 200, description = "One delivery record per message, failed ones carry their error", body = Vec<MessageDelivery>),
+        (status = 400, description = "Invalid messages", body = ValidationErrors),
+        (status = 429, description = "Quota exceeded"),
+        (status = 503, description = "Messaging is disabled or every browser session is busy"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+#[post("/messages")]
+pub async fn send_messages(messages: Valid<Vec<MessageRequest>>, keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    let browsers = match get_browsers() {
+        Some(browsers) => browsers,
+        None => return HttpResponse::ServiceUnavailable().body("Messaging is disabled, DRIVER_SESSION_COUNT is 0"),
+    };
+    let proxy = match browsers.pool.acquire() {
+        Some(proxy) => proxy,
+        None => {
+            return HttpResponse::ServiceUnavailable()
+                .insert_header(("Retry-After", BUSY_RETRY_AFTER_SECS.to_string()))
+                .body("Every browser session is busy")
+        }
+    };
+    // Charged only once a session is free, a busy pool costs the client nothing
+    if let Some(response) = charge(&keys, client, messages.len() as u32) {
+        return response;
+    }
+    let crawler = SalesCrawler::new(proxy).await;
+    let mut deliveries = Vec::with_capacity(messages.len());
+    for message in messages.iter() {
+        deliveries.push(crawler.deliver(message).await);
+    }
+    HttpResponse::Ok().json(deliveries)
+}
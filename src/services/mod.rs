@@ -1,6 +1,13 @@
+pub mod admin;
+pub mod auth;
+pub mod jobs;
+pub mod messages;
+pub mod openapi;
 pub mod request;
+pub mod status;
 
 use actix_web::get;
+#[utoipa::path(tag = "health", responses((status = 200, body = String)))]
 #[get("/")]
 pub async fn hello() -> &'static str {
     "Hello World!"
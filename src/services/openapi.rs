@@ -0,0 +1,58 @@
+use crate::services::{admin, jobs, messages, request};
+use actix_web::{get, HttpResponse};
+use omicron_crawler::control::ControlCommand;
+use omicron_crawler::{health, metrics};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Omicron crawler", description = "Searches and crawls LinkedIn profiles"),
+    paths(
+        crate::services::hello,
+        openapi_json,
+        request::search,
+        request::profiles,
+        jobs::submit_search,
+        jobs::submit_profiles,
+        jobs::job_status,
+        jobs::cancel_job,
+        jobs::job_events,
+        messages::send_messages,
+        admin::control_state,
+        admin::pause,
+        admin::resume,
+        admin::set_budget,
+        admin::disable_account,
+        admin::enable_account,
+        admin::reauthenticate,
+        health::healthz,
+        health::readyz,
+        health::status,
+        metrics::metrics,
+    ),
+    // Not used by any route, it documents the messages of the bus worker's control queue
+    components(schemas(ControlCommand)),
+    modifiers(&Security)
+)]
+pub struct ApiDoc;
+
+/// Keys are accepted in the `X-Api-Key` header or as a bearer token.
+struct Security;
+
+impl Modify for Security {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key"))));
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+#[utoipa::path(tag = "health", responses((status = 200, description = "This document")))]
+#[get("/openapi.json")]
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
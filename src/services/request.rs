@@ -1,15 +1,32 @@
 use crate::get_crawler;
-use actix_web::web::Json;
+use crate::services::auth::{charge, search_cost};
+use actix_web::web::{Data, ReqData};
 use actix_web::{post, HttpResponse};
 use log::error;
+use omicron_crawler::auth::{ApiKey, ApiKeys};
 use omicron_crawler::azure::json::{CrawledProfiles, ProfileIds};
-use omicron_crawler::linkedin::api::json::SearchParams;
+use omicron_crawler::linkedin::api::json::{SearchParams, SearchResult};
+use omicron_crawler::linkedin::profile::Profile;
+use omicron_crawler::validation::{Valid, ValidationErrors};
 
+#[utoipa::path(
+    tag = "crawl",
+    request_body = SearchParams,
+    responses(
+        (status = 200, description = "People found on the requested pages", body = SearchResult),
+        (status = 400, description = "Invalid search parameters", body = ValidationErrors),
+        (status = 429, description = "Quota exceeded"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
 #[post("/search")]
-pub async fn search(search_params: Json<SearchParams>) -> HttpResponse {
+pub async fn search(search_params: Valid<SearchParams>, keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    if let Some(response) = charge(&keys, client, search_cost(&search_params)) {
+        return response;
+    }
     let crawler = get_crawler().await;
     let search_params = search_params.into_inner();
-    let results = match crawler.search_people(search_params).await {
+    let results = match crawler.search_people(search_params, None).await {
         Ok(result) => result,
         Err(e) => {
             error!("Failed to search people {}", e);
@@ -19,12 +36,25 @@ pub async fn search(search_params: Json<SearchParams>) -> HttpResponse {
     HttpResponse::Ok().json(results)
 }
 
+#[utoipa::path(
+    tag = "crawl",
+    request_body = ProfileIds,
+    responses(
+        (status = 200, description = "Crawled profiles", body = CrawledProfiles),
+        (status = 400, description = "Invalid profile ids", body = ValidationErrors),
+        (status = 429, description = "Quota exceeded"),
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
 #[post("/profiles")]
-pub async fn profiles(url_requests: Json<ProfileIds>) -> HttpResponse {
+pub async fn profiles(url_requests: Valid<ProfileIds>, keys: Data<ApiKeys>, client: Option<ReqData<ApiKey>>) -> HttpResponse {
+    if let Some(response) = charge(&keys, client, url_requests.ids.len() as u32) {
+        return response;
+    }
     let crawler = get_crawler().await;
     let mut profiles_response = url_requests.into_inner();
     let profiles = match crawler.profiles(profiles_response.ids.as_slice(), None).await {
-        Ok(profiles) => profiles,
+        Ok(batch) => batch.profiles,
         Err(e) => {
             error!("Failed to parse profiles {}", e);
             return HttpResponse::InternalServerError().body(format!("Failed to perform profiles {}", e));
@@ -32,7 +62,7 @@ pub async fn profiles(url_requests: Json<ProfileIds>) -> HttpResponse {
     };
 
     let crawled_profiles = CrawledProfiles {
-        profiles,
+        profiles: profiles.into_iter().map(Profile::from).collect(),
         request_metadata: profiles_response.request_metadata.take(),
     };
     HttpResponse::Ok().json(crawled_profiles)
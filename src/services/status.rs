@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use omicron_crawler::health::{Status, StatusProvider};
+use omicron_crawler::jobs::JobManager;
+use omicron_crawler::linkedin::api::crawler::Crawler;
+use std::sync::Arc;
+
+pub struct ServerStatus {
+    pub started_at: DateTime<Utc>,
+    pub crawler: &'static Crawler,
+    pub jobs: Arc<JobManager>,
+}
+
+impl StatusProvider for ServerStatus {
+    fn status(&self) -> Status {
+        Status::new("server", self.started_at, self.crawler, self.jobs.in_flight(), None)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::azure::json::ProfileIds;
+use crate::control::{Budget, ControlCommand};
+use crate::linkedin::api::json::SearchParams;
+use crate::linkedin::api::rate_limits::MAX_PROFILES_PER_HOUR;
+use crate::linkedin::web_driver::messages::MessageRequest;
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, ResponseError};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use utoipa::ToSchema;
+
+/// LinkedIn returns at most 100 pages of people for a search
+pub const MAX_SEARCH_PAGE: u16 = 100;
+
+const SALES_LEAD_URL: &str = "https://www.linkedin.com/sales/lead/";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct FieldError {
+    /// Path of the offending field, e.g. `countries[1]`, or `body` when the body itself is malformed
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Body of every 400 response for a request that could not be parsed or validated.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<String> = self.errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+        write!(f, "Invalid request {}", errors.join(", "))
+    }
+}
+
+impl ResponseError for ValidationErrors {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::BadRequest().json(self)
+    }
+}
+
+/// Checks that go beyond what deserializing a request body already enforces.
+pub trait Validate {
+    fn validate(&self) -> Vec<FieldError>;
+}
+
+impl Validate for SearchParams {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.page > self.end {
+            errors.push(FieldError::new("page", format!("must not be greater than end ({})", self.end)));
+        }
+        if self.end > MAX_SEARCH_PAGE {
+            errors.push(FieldError::new("end", format!("must not be greater than {}", MAX_SEARCH_PAGE)));
+        }
+        errors
+    }
+}
+
+impl Validate for ProfileIds {
+    fn validate(&self) -> Vec<FieldError> {
+        if self.ids.is_empty() {
+            return vec![FieldError::new("ids", "must contain at least one id")];
+        }
+        self.ids
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| id.trim().is_empty())
+            .map(|(i, _)| FieldError::new(format!("ids[{}]", i), "must not be blank"))
+            .collect()
+    }
+}
+
+impl Validate for Vec<MessageRequest> {
+    fn validate(&self) -> Vec<FieldError> {
+        if self.is_empty() {
+            return vec![FieldError::new("body", "must contain at least one message")];
+        }
+        let mut errors = Vec::new();
+        for (i, message) in self.iter().enumerate() {
+            if !message.sales_url.starts_with(SALES_LEAD_URL) {
+                errors.push(FieldError::new(
+                    format!("[{}].sales_url", i),
+                    format!("must be a Sales Navigator lead url starting with {}", SALES_LEAD_URL),
+                ));
+            }
+            if message.body.trim().is_empty() {
+                errors.push(FieldError::new(format!("[{}].body", i), "must not be blank"));
+            }
+        }
+        errors
+    }
+}
+
+impl Validate for Budget {
+    fn validate(&self) -> Vec<FieldError> {
+        match self.profiles_per_hour {
+            // The rate limiter treats 0 as no waiting at all, pausing is the way to stop crawling
+            0 => vec![FieldError::new(
+                "profiles_per_hour",
+                "must be at least 1, pause the crawler instead",
+            )],
+            // Requests alone would take the whole hour, the crawler would stop waiting between them
+            n if n > MAX_PROFILES_PER_HOUR => vec![FieldError::new(
+                "profiles_per_hour",
+                format!("must be at most {}", MAX_PROFILES_PER_HOUR),
+            )],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Validate for ControlCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        match self {
+            ControlCommand::SetBudget { profiles_per_hour } => Budget {
+                profiles_per_hour: *profiles_per_hour,
+            }
+            .validate(),
+            ControlCommand::DisableAccount { account } | ControlCommand::EnableAccount { account } if account.trim().is_empty() => {
+                vec![FieldError::new("account", "must not be blank")]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Deserializes and validates a JSON body, reporting the path of the field that failed.
+pub fn parse<T: DeserializeOwned + Validate>(body: &[u8]) -> Result<T, ValidationErrors> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+    let value: T = match serde_path_to_error::deserialize(deserializer) {
+        Ok(value) => value,
+        Err(e) => {
+            let field = match e.path().to_string().as_str() {
+                "." => "body".to_string(),
+                path => path.to_string(),
+            };
+            // The position is of no use to clients sending generated JSON
+            let message = e.inner().to_string();
+            let message = match message.split_once(" at line ") {
+                Some((message, _)) => message.to_string(),
+                None => message,
+            };
+            return Err(ValidationErrors {
+                errors: vec![FieldError::new(field, message)],
+            });
+        }
+    };
+    let errors = value.validate();
+    match errors.is_empty() {
+        true => Ok(value),
+        false => Err(ValidationErrors { errors }),
+    }
+}
+
+/// JSON body extractor that answers 400 with a [`ValidationErrors`] body instead of serde's message.
+pub struct Valid<T>(pub T);
+
+impl<T> Valid<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for Valid<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for Valid<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let body = Bytes::from_request(req, payload);
+        Box::pin(async move {
+            let body = body.await?;
+            Ok(Valid(parse(&body)?))
+        })
+    }
+}
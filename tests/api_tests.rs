@@ -314,7 +314,7 @@ async fn test_search_people_1() {
         end: 2,
         request_metadata: None,
     };
-    let search_result = match session.search_people(params).await {
+    let search_result = match session.search_people(params, None).await {
         Ok(result) => result,
         Err(e) => panic!("Failed to search people {}", e),
     };
@@ -354,7 +354,7 @@ async fn test_search_people_2() {
         end: 100,
         request_metadata: None,
     };
-    let search_result = match session.search_people(params).await {
+    let search_result = match session.search_people(params, None).await {
         Ok(result) => result,
         Err(e) => panic!("Failed to search people {}", e),
     };
@@ -394,7 +394,7 @@ async fn test_search_people_max() {
         end: 100,
         request_metadata: None,
     };
-    let search_result = match session.search_people(params).await {
+    let search_result = match session.search_people(params, None).await {
         Ok(result) => result,
         Err(e) => panic!("Failed to search people {}", e),
     };
@@ -419,6 +419,7 @@ async fn push_to_bus_search_complete_test() {
         request_metadata: None,
         total_lookup: 0,
         total: 1,
+        resume_page: None,
     };
     match azure_client.push_to_bus(&search_result, Label::SearchComplete).await {
         Ok(_) => {}
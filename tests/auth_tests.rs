@@ -0,0 +1,65 @@
+use omicron_crawler::auth::{parse_api_keys, ApiKey, ApiKeys, QUOTA_WINDOW};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_parse_api_keys() {
+    let keys = parse_api_keys(" ui:secret-1, reports:secret-2:20 ,", 100).unwrap();
+    assert_eq!(
+        keys,
+        vec![
+            ApiKey {
+                name: "ui".to_string(),
+                key: "secret-1".to_string(),
+                quota: 100,
+            },
+            ApiKey {
+                name: "reports".to_string(),
+                key: "secret-2".to_string(),
+                quota: 20,
+            },
+        ]
+    );
+    assert!(parse_api_keys("", 100).unwrap().is_empty());
+    assert!(parse_api_keys("ui", 100).is_err());
+    assert!(parse_api_keys("ui:key:many", 100).is_err());
+    assert!(parse_api_keys(":key", 100).is_err());
+}
+
+#[test]
+fn test_quota() {
+    let keys = ApiKeys::new(parse_api_keys("ui:a:10,reports:b:5", 100).unwrap());
+    assert!(keys.is_enabled());
+    assert!(keys.authenticate("nope").is_none());
+    let ui = keys.authenticate("a").unwrap().clone();
+    let reports = keys.authenticate("b").unwrap().clone();
+
+    let start = Instant::now();
+    assert!(keys.charge_at(&ui, 6, start).is_ok());
+    // A refused request takes nothing, a smaller one still fits
+    let retry_after = keys.charge_at(&ui, 5, start + Duration::from_secs(600)).unwrap_err();
+    assert_eq!(retry_after, QUOTA_WINDOW - Duration::from_secs(600));
+    assert!(keys.charge_at(&ui, 4, start + Duration::from_secs(600)).is_ok());
+    assert!(keys.charge_at(&ui, 1, start + Duration::from_secs(601)).is_err());
+
+    // Clients have separate quotas
+    assert!(keys.charge_at(&reports, 5, start).is_ok());
+
+    // The quota renews after the window
+    assert!(keys.charge_at(&ui, 10, start + QUOTA_WINDOW).is_ok());
+    assert!(!ApiKeys::new(Vec::new()).is_enabled());
+}
+
+#[test]
+fn test_admins() {
+    let keys = ApiKeys::new(parse_api_keys("ui:a,ops:b", 100).unwrap()).with_admins(vec!["ops".to_string()]);
+    assert!(!keys.is_admin(keys.authenticate("a").unwrap()));
+    assert!(keys.is_admin(keys.authenticate("b").unwrap()));
+    assert!(keys.may_administer(keys.authenticate("b")));
+    assert!(!keys.may_administer(keys.authenticate("a")));
+    assert!(!keys.may_administer(None));
+
+    // Without keys or without ADMIN_CLIENTS nobody may change the running crawler
+    assert!(!ApiKeys::new(Vec::new()).may_administer(None));
+    let no_admins = ApiKeys::new(parse_api_keys("ops:b", 100).unwrap());
+    assert!(!no_admins.may_administer(no_admins.authenticate("b")));
+}
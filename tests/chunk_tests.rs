@@ -0,0 +1,127 @@
+use omicron_crawler::azure::chunk::{split, split_profile_job, Chunk, Reassembler};
+use omicron_crawler::azure::json::{Envelope, ProfileIds};
+use std::time::Duration;
+
+fn large_message(ids: usize) -> String {
+    let message = ProfileIds {
+        // Quotes, escapes and multi-byte characters must survive the split
+        ids: (0..ids).map(|i| format!("profile-{}-\"žluťoučký\"\\", i)).collect(),
+        request_metadata: Some("meta".to_string()),
+    };
+    serde_json::to_string(&message).unwrap()
+}
+
+#[test]
+fn test_small_message_is_not_chunked() {
+    let body = large_message(2);
+    assert_eq!(split(body.clone(), 1024), vec![body.clone()]);
+
+    let mut reassembler = Reassembler::new();
+    let message: ProfileIds = reassembler.receive(&body).unwrap().unwrap();
+    assert_eq!(message.ids.len(), 2);
+}
+
+#[test]
+fn test_split_and_reassemble() {
+    let body = large_message(500);
+    let parts = split(body.clone(), 2048);
+    assert!(parts.len() > 5);
+    assert!(parts.iter().all(|part| part.len() <= 2048));
+    let chunks: Vec<Chunk> = parts.iter().map(|part| serde_json::from_str(part).unwrap()).collect();
+    assert!(chunks.iter().all(|chunk| chunk.total == parts.len() as u32));
+    assert!(chunks.iter().all(|chunk| chunk.chunk_id == chunks[0].chunk_id));
+
+    // Same body, same chunks, so a resent message does not start a second one
+    assert_eq!(split(body.clone(), 2048), parts);
+
+    // Out of order and interleaved with an unrelated small message
+    let mut reassembler = Reassembler::new();
+    let mut received = None;
+    for (i, part) in parts.iter().enumerate().rev() {
+        if i == 2 {
+            let other: ProfileIds = reassembler.receive(&large_message(1)).unwrap().unwrap();
+            assert_eq!(other.ids.len(), 1);
+        }
+        if let Some(message) = reassembler.receive::<ProfileIds>(part).unwrap() {
+            assert_eq!(i, 0);
+            received = Some(message);
+        }
+    }
+    let received = received.unwrap();
+    assert_eq!(serde_json::to_string(&received).unwrap(), body);
+    assert_eq!(reassembler.incomplete(), 0);
+}
+
+#[test]
+fn test_invalid_chunks() {
+    let mut reassembler = Reassembler::new();
+    let chunk = Chunk {
+        chunk_id: "id".to_string(),
+        sequence: 2,
+        total: 2,
+        data: "e30=".to_string(),
+    };
+    assert!(reassembler.push(chunk.clone()).is_err());
+    assert!(reassembler
+        .push(Chunk {
+            data: "not base64!".to_string(),
+            sequence: 0,
+            ..chunk.clone()
+        })
+        .is_err());
+
+    assert!(reassembler
+        .push(Chunk {
+            sequence: 0,
+            ..chunk.clone()
+        })
+        .unwrap()
+        .is_none());
+    assert!(reassembler
+        .push(Chunk {
+            sequence: 1,
+            total: 3,
+            ..chunk.clone()
+        })
+        .is_err());
+    assert_eq!(reassembler.incomplete(), 1);
+    assert_eq!(reassembler.push(Chunk { sequence: 1, ..chunk }).unwrap().as_deref(), Some("{}{}"));
+}
+
+#[test]
+fn test_incomplete_messages_expire() {
+    let mut reassembler = Reassembler::with_timeout(Duration::from_millis(10));
+    let parts = split(large_message(200), 1024);
+    assert!(reassembler.receive::<ProfileIds>(&parts[0]).unwrap().is_none());
+    assert_eq!(reassembler.incomplete(), 1);
+    assert_eq!(reassembler.evict_expired(), 0);
+
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(reassembler.evict_expired(), 1);
+    assert_eq!(reassembler.incomplete(), 0);
+
+    // Chunks arriving after the timeout start over instead of completing a message from the leftovers
+    assert!(reassembler.receive::<ProfileIds>(&parts[1]).unwrap().is_none());
+    assert_eq!(reassembler.incomplete(), 1);
+}
+
+#[test]
+fn test_split_profile_job() {
+    let job = Envelope::new(ProfileIds {
+        ids: (0..1000).map(|i| format!("ACoAAB{:020}", i)).collect(),
+        request_metadata: Some("meta".to_string()),
+    })
+    .with_tenant("acme");
+    let parts = split_profile_job(job.clone(), 4096);
+    assert!(parts.len() > 1);
+    for part in &parts {
+        assert!(serde_json::to_string(part).unwrap().len() <= 4096);
+        assert_eq!(part.job_id, job.job_id);
+        assert_eq!(part.tenant.as_deref(), Some("acme"));
+        assert_eq!(part.payload.request_metadata.as_deref(), Some("meta"));
+    }
+    let ids: Vec<String> = parts.into_iter().flat_map(|part| part.payload.ids).collect();
+    assert_eq!(ids, job.payload.ids);
+
+    assert_eq!(split_profile_job(job.clone(), 1024 * 1024).len(), 1);
+}
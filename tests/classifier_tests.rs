@@ -0,0 +1,131 @@
+use omicron_crawler::linkedin::profile::classifier::classify_title;
+use omicron_crawler::linkedin::profile::Profile;
+use omicron_crawler::linkedin::web_driver::enums::{JobFunction, SeniorityLevel};
+use omicron_crawler::linkedin::web_driver::profiles;
+
+fn check(table: Vec<(&str, Option<SeniorityLevel>, Option<JobFunction>)>) {
+    for (title, seniority, function) in table {
+        let classification = classify_title(title);
+        assert_eq!(classification.seniority, seniority, "seniority of {}", title);
+        assert_eq!(classification.function, function, "function of {}", title);
+    }
+}
+
+#[test]
+fn test_classify_en() {
+    check(vec![
+        (
+            "Senior Software Engineer",
+            Some(SeniorityLevel::Senior),
+            Some(JobFunction::Engineering),
+        ),
+        ("Co-Founder & CEO", Some(SeniorityLevel::Owner), Some(JobFunction::Entrepreneurship)),
+        (
+            "Vice President of Sales",
+            Some(SeniorityLevel::VicePresident),
+            Some(JobFunction::Sales),
+        ),
+        (
+            "Head of Product Management",
+            Some(SeniorityLevel::Director),
+            Some(JobFunction::ProductManagement),
+        ),
+        (
+            "Senior Product Manager",
+            Some(SeniorityLevel::ExperienceManager),
+            Some(JobFunction::ProductManagement),
+        ),
+        ("QA Engineer", None, Some(JobFunction::QualityAssurance)),
+        (
+            "Team Lead / SW engineer / Architect",
+            Some(SeniorityLevel::EntryLevelManager),
+            Some(JobFunction::Engineering),
+        ),
+        ("Junior Accountant", Some(SeniorityLevel::EntryLevel), Some(JobFunction::Accounting)),
+        (
+            "Software Engineering Intern",
+            Some(SeniorityLevel::InTraining),
+            Some(JobFunction::Engineering),
+        ),
+        (
+            "Principal UX Designer",
+            Some(SeniorityLevel::Strategic),
+            Some(JobFunction::ArtsAndDesign),
+        ),
+        ("Technical Recruiter", None, Some(JobFunction::HumanResources)),
+        ("", None, None),
+    ]);
+}
+
+#[test]
+fn test_classify_sk_cz() {
+    check(vec![
+        ("Vedúci vývoja softvéru", Some(SeniorityLevel::ExperienceManager), None),
+        ("Generálny riaditeľ", Some(SeniorityLevel::CXO), None),
+        ("Vyvojar Java", None, Some(JobFunction::Engineering)),
+        ("Obchodný riaditeľ", Some(SeniorityLevel::Director), Some(JobFunction::Sales)),
+        ("Účetní", None, Some(JobFunction::Accounting)),
+        ("Jednatel společnosti", Some(SeniorityLevel::CXO), None),
+        (
+            "Stážista - marketing",
+            Some(SeniorityLevel::InTraining),
+            Some(JobFunction::Marketing),
+        ),
+        (
+            "Projektový manažér",
+            Some(SeniorityLevel::ExperienceManager),
+            Some(JobFunction::ProgramAndProjectManagement),
+        ),
+    ]);
+}
+
+#[test]
+fn test_classify_de_pl() {
+    check(vec![
+        ("Geschäftsführer", Some(SeniorityLevel::CXO), None),
+        ("Vertriebsleiter", Some(SeniorityLevel::ExperienceManager), Some(JobFunction::Sales)),
+        ("Teamleiter Softwareentwicklung", Some(SeniorityLevel::EntryLevelManager), None),
+        (
+            "Senior Softwareentwickler",
+            Some(SeniorityLevel::Senior),
+            Some(JobFunction::Engineering),
+        ),
+        (
+            "Werkstudent Marketing",
+            Some(SeniorityLevel::InTraining),
+            Some(JobFunction::Marketing),
+        ),
+        ("Starszy programista", Some(SeniorityLevel::Senior), Some(JobFunction::Engineering)),
+        ("Dyrektor finansowy", Some(SeniorityLevel::Director), Some(JobFunction::Finance)),
+        ("Właściciel", Some(SeniorityLevel::Owner), None),
+    ]);
+}
+
+#[test]
+fn test_profile_positions_are_classified() {
+    let profile = Profile::from(profiles::Profile {
+        name: "Jane Doe".to_string(),
+        url: "https://www.linkedin.com/in/jane-doe".to_string(),
+        sales_url: None,
+        profile_picture_url: "".to_string(),
+        description: "CTO at Example".to_string(),
+        about: None,
+        location: "Bratislava".to_string(),
+        experience: Some(vec![profiles::Experience {
+            position: "Senior Java Developer".to_string(),
+            interval: profiles::Interval {
+                start: "2019".to_string(),
+                end: "2021".to_string(),
+            },
+        }]),
+        education: None,
+        skills: None,
+        languages: None,
+    });
+    assert_eq!(profile.headline_classification.seniority, Some(SeniorityLevel::CXO));
+    assert_eq!(profile.positions[0].classification.seniority, Some(SeniorityLevel::Senior));
+    assert_eq!(profile.positions[0].classification.function, Some(JobFunction::Engineering));
+
+    let json = serde_json::to_value(&profile).unwrap();
+    assert_eq!(json["positions"][0]["classification"]["seniority"], "Senior");
+}
@@ -0,0 +1,91 @@
+use omicron_crawler::control::{Budget, ControlCommand, ControlState};
+use omicron_crawler::validation::{parse, FieldError, Validate};
+use serde_json::json;
+
+#[test]
+fn test_command_format() {
+    let commands = [
+        (json!({"command": "pause"}), ControlCommand::Pause),
+        (json!({"command": "resume"}), ControlCommand::Resume),
+        (
+            json!({"command": "set_budget", "profiles_per_hour": 50}),
+            ControlCommand::SetBudget { profiles_per_hour: 50 },
+        ),
+        (
+            json!({"command": "disable_account", "account": "crawler@example.com"}),
+            ControlCommand::DisableAccount {
+                account: "crawler@example.com".to_string(),
+            },
+        ),
+        (
+            json!({"command": "enable_account", "account": "crawler@example.com"}),
+            ControlCommand::EnableAccount {
+                account: "crawler@example.com".to_string(),
+            },
+        ),
+        (json!({"command": "reauthenticate"}), ControlCommand::Reauthenticate),
+    ];
+    for (value, command) in commands {
+        assert_eq!(serde_json::from_value::<ControlCommand>(value.clone()).unwrap(), command);
+        assert_eq!(serde_json::to_value(&command).unwrap(), value);
+        assert_eq!(value["command"], command.name());
+    }
+    assert!(serde_json::from_value::<ControlCommand>(json!({"command": "restart"})).is_err());
+    assert!(serde_json::from_value::<ControlCommand>(json!({"command": "set_budget"})).is_err());
+}
+
+#[test]
+fn test_control_state() {
+    let state = ControlState {
+        paused: false,
+        profiles_per_hour: 100,
+        account: "crawler@example.com".to_string(),
+        account_disabled: false,
+    };
+    assert!(state.is_active());
+    assert!(!ControlState {
+        paused: true,
+        ..state.clone()
+    }
+    .is_active());
+    assert!(!ControlState {
+        account_disabled: true,
+        ..state
+    }
+    .is_active());
+}
+
+#[test]
+fn test_budget_validation() {
+    assert_eq!(parse::<Budget>(br#"{"profiles_per_hour": 40}"#).unwrap().profiles_per_hour, 40);
+    let errors = parse::<Budget>(br#"{"profiles_per_hour": 0}"#).err().unwrap().errors;
+    assert_eq!(errors[0].field, "profiles_per_hour");
+    let errors = parse::<Budget>(br#"{"profiles_per_hour": 5000}"#).err().unwrap().errors;
+    assert_eq!(errors, vec![FieldError::new("profiles_per_hour", "must be at most 4500")]);
+    assert!(parse::<Budget>(br#"{"profiles_per_hour": 4500}"#).is_ok());
+    let errors = parse::<Budget>(br#"{"profiles_per_hour": -5}"#).err().unwrap().errors;
+    assert_eq!(errors[0].field, "profiles_per_hour");
+    let errors = parse::<Budget>(b"{}").err().unwrap().errors;
+    assert_eq!(errors, vec![FieldError::new("body", "missing field `profiles_per_hour`")]);
+}
+
+#[test]
+fn test_command_validation() {
+    assert!(ControlCommand::SetBudget { profiles_per_hour: 50 }.validate().is_empty());
+    assert!(ControlCommand::Pause.validate().is_empty());
+    let errors = ControlCommand::SetBudget { profiles_per_hour: 0 }.validate();
+    assert_eq!(errors[0].field, "profiles_per_hour");
+    assert!(!ControlCommand::SetBudget {
+        profiles_per_hour: 6_000_000
+    }
+    .validate()
+    .is_empty());
+    let errors = ControlCommand::DisableAccount { account: " ".to_string() }.validate();
+    assert_eq!(errors, vec![FieldError::new("account", "must not be blank")]);
+
+    let enable = ControlCommand::EnableAccount {
+        account: "crawler@example.com".to_string(),
+    };
+    assert_eq!(enable.account(), Some("crawler@example.com"));
+    assert_eq!(ControlCommand::Reauthenticate.account(), None);
+}
@@ -0,0 +1,93 @@
+use omicron_crawler::convert::{batches, dedupe, is_sales_url, profile_id_from_url, read_search_dump, SeenProfiles};
+use tempfile::TempDir;
+
+#[test]
+fn test_web_driver_dump() {
+    let dump = r#"[
+        { "name": "Matus Chochlik", "title": "Team Lead",
+          "sales_url": "https://www.linkedin.com/sales/lead/ACwAAAWs1dABZXg7RDqKugFxlSeo7gasFL1FPHQ,NAME_SEARCH,cypw?_ntb=KAHhkeRAT0ejheHlZJlIrg%3D%3D" },
+        { "name": "Tomas Danis", "title": "Developer", "url": "https://www.linkedin.com/in/tomas-danis/" },
+        { "name": "Nobody", "title": "", "url": "https://example.com/nobody" }
+    ]"#;
+    let found = read_search_dump(dump).unwrap();
+    assert_eq!(found.ids, vec!["tomas-danis"]);
+    assert_eq!(
+        found.sales_urls,
+        vec!["https://www.linkedin.com/sales/lead/ACwAAAWs1dABZXg7RDqKugFxlSeo7gasFL1FPHQ,NAME_SEARCH,cypw?_ntb=KAHhkeRAT0ejheHlZJlIrg%3D%3D"]
+    );
+    assert_eq!(found.unrecognized, vec!["https://example.com/nobody"]);
+    assert_eq!(found.request_metadata, None);
+}
+
+#[test]
+fn test_api_dump() {
+    let page = |urn: &str, metadata: &str| {
+        format!(
+            r#"{{ "elements": [ {{ "first_name": "Matus", "last_name": "Chochlik", "subtitle": null, "summary": null,
+                "url": "https://www.linkedin.com/in/matus", "profile_urn": "{}" }} ], "request_metadata": {}, "total": 1 }}"#,
+            urn, metadata
+        )
+    };
+    let found = read_search_dump(&page("ACoAAA1", "\"campaign-7\"")).unwrap();
+    assert_eq!(found.ids, vec!["ACoAAA1"]);
+    assert_eq!(found.request_metadata.as_deref(), Some("campaign-7"));
+
+    let pages = format!("[{}, {}]", page("ACoAAA1", "null"), page("ACoAAA2", "\"campaign-8\""));
+    let found = read_search_dump(&pages).unwrap();
+    assert_eq!(found.ids, vec!["ACoAAA1", "ACoAAA2"]);
+    assert_eq!(found.request_metadata.as_deref(), Some("campaign-8"));
+
+    assert!(read_search_dump(r#"{"profiles": []}"#).is_err());
+    assert!(read_search_dump("[{\"name\": \"No url\"}]").is_err());
+}
+
+#[test]
+fn test_profile_id_from_url() {
+    assert_eq!(
+        profile_id_from_url("https://www.linkedin.com/in/tomas-danis").as_deref(),
+        Some("tomas-danis")
+    );
+    assert_eq!(
+        profile_id_from_url("https://www.linkedin.com/in/ACoAAB?miniProfileUrn=urn").as_deref(),
+        Some("ACoAAB")
+    );
+    assert_eq!(profile_id_from_url("https://www.linkedin.com/company/omicron"), None);
+
+    // Lead ids are not ids of /identity/profiles, the leads stay urls for the web driver
+    assert_eq!(
+        profile_id_from_url("https://www.linkedin.com/sales/lead/ACwAAB,NAME_SEARCH,x"),
+        None
+    );
+    assert!(is_sales_url("https://www.linkedin.com/sales/lead/ACwAAB,NAME_SEARCH,x"));
+    assert!(is_sales_url("https://www.linkedin.com/sales/people/ACwAAB,NAME_SEARCH"));
+    assert!(!is_sales_url("https://www.linkedin.com/in/tomas-danis"));
+}
+
+#[test]
+fn test_batches() {
+    let ids: Vec<String> = ["a", "b", "a", "c", "b", "d", "e"].iter().map(|id| id.to_string()).collect();
+    let ids = dedupe(ids);
+    assert_eq!(ids, vec!["a", "b", "c", "d", "e"]);
+
+    let jobs = batches(&ids, 2, Some("campaign-7"));
+    let sizes: Vec<usize> = jobs.iter().map(|job| job.ids.len()).collect();
+    assert_eq!(sizes, vec![2, 2, 1]);
+    assert_eq!(jobs[2].ids, vec!["e"]);
+    assert!(jobs.iter().all(|job| job.request_metadata.as_deref() == Some("campaign-7")));
+    assert!(batches(&[], 2, None).is_empty());
+}
+
+#[tokio::test]
+async fn test_seen_profiles() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("seen");
+    let mut seen = SeenProfiles::open(&path).await.unwrap();
+    assert!(!seen.contains("a"));
+    seen.record(&["a".to_string(), "b".to_string()]).await.unwrap();
+    seen.record(&["b".to_string(), "c".to_string()]).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\nc\n");
+
+    let seen = SeenProfiles::open(&path).await.unwrap();
+    let ids = vec!["d".to_string(), "a".to_string(), "e".to_string(), "c".to_string()];
+    assert_eq!(seen.unseen(ids), vec!["d", "e"]);
+}
@@ -0,0 +1,179 @@
+use omicron_crawler::linkedin::profile::Date;
+use omicron_crawler::linkedin::web_driver::date_range::{parse_date_range, parse_duration, DateRange, Locale};
+use omicron_crawler::linkedin::web_driver::profiles::Interval;
+
+fn month(year: i32, month: u32) -> Option<Date> {
+    Some(Date {
+        year,
+        month: Some(month),
+        day: None,
+    })
+}
+
+fn year(year: i32) -> Option<Date> {
+    Some(Date {
+        year,
+        month: None,
+        day: None,
+    })
+}
+
+fn range(start: Option<Date>, end: Option<Date>, duration_months: Option<u32>) -> DateRange {
+    DateRange {
+        is_current: end.is_none(),
+        start,
+        end,
+        duration_months,
+    }
+}
+
+fn check(locale: Locale, table: Vec<(&str, DateRange)>) {
+    for (text, expected) in table {
+        assert_eq!(
+            parse_date_range(text, Some(locale)).as_ref(),
+            Some(&expected),
+            "{:?} {}",
+            locale,
+            text
+        );
+        assert_eq!(parse_date_range(text, None).as_ref(), Some(&expected), "autodetect {}", text);
+    }
+}
+
+#[test]
+fn test_date_range_en() {
+    check(
+        Locale::En,
+        vec![
+            ("Jan 2020 – Present · 2 yrs 3 mos", range(month(2020, 1), None, Some(27))),
+            ("Mar 2019 - Jun 2019 · 4 mos", range(month(2019, 3), month(2019, 6), Some(4))),
+            ("2015 – 2018", range(year(2015), year(2018), None)),
+            ("Sept 2012–Present 1 yr", range(month(2012, 9), None, Some(12))),
+            ("April 2016 – December 2016", range(month(2016, 4), month(2016, 12), None)),
+            ("2008", range(year(2008), year(2008), None)),
+        ],
+    );
+}
+
+#[test]
+fn test_date_range_sk() {
+    check(
+        Locale::Sk,
+        vec![
+            ("jan 2020 – súčasnosť · 2 r. 3 mes.", range(month(2020, 1), None, Some(27))),
+            (
+                "máj 2018 – júl 2019 · 1 rok 3 mesiace",
+                range(month(2018, 5), month(2019, 7), Some(15)),
+            ),
+            ("okt. 2010 – Súčasnosť · 14 rokov", range(month(2010, 10), None, Some(168))),
+            ("2005 – 2008", range(year(2005), year(2008), None)),
+        ],
+    );
+}
+
+#[test]
+fn test_date_range_cz() {
+    check(
+        Locale::Cz,
+        vec![
+            ("led 2020 – současnost · 2 roky 3 měsíce", range(month(2020, 1), None, Some(27))),
+            (
+                "bře 2017 – říj 2019 · 2 roky 8 měs.",
+                range(month(2017, 3), month(2019, 10), Some(32)),
+            ),
+            (
+                "červenec 2015 – prosinec 2015 · 6 měsíců",
+                range(month(2015, 7), month(2015, 12), Some(6)),
+            ),
+            ("čvn 2011 – Současnost · 13 let", range(month(2011, 6), None, Some(156))),
+        ],
+    );
+}
+
+#[test]
+fn test_date_range_de() {
+    check(
+        Locale::De,
+        vec![
+            ("Jan. 2020 – Heute · 2 J. 3 Mon.", range(month(2020, 1), None, Some(27))),
+            (
+                "März 2016 – Dez. 2018 · 2 Jahre 10 Monate",
+                range(month(2016, 3), month(2018, 12), Some(34)),
+            ),
+            ("Mai 2021 – Okt. 2021 · 6 Mon.", range(month(2021, 5), month(2021, 10), Some(6))),
+        ],
+    );
+}
+
+#[test]
+fn test_date_range_pl() {
+    check(
+        Locale::Pl,
+        vec![
+            ("sty 2020 – obecnie · 2 lata 3 mies.", range(month(2020, 1), None, Some(27))),
+            (
+                "lut 2014 – wrz 2019 · 5 lat 8 mies.",
+                range(month(2014, 2), month(2019, 9), Some(68)),
+            ),
+            ("paź 2022 – Obecnie · 1 rok", range(month(2022, 10), None, Some(12))),
+        ],
+    );
+}
+
+#[test]
+fn test_date_range_numeric() {
+    assert_eq!(
+        parse_date_range("04/2016 – 11.2018", None),
+        Some(range(month(2016, 4), month(2018, 11), None))
+    );
+}
+
+#[test]
+fn test_date_range_invalid() {
+    assert_eq!(parse_date_range("", None), None);
+    assert_eq!(parse_date_range("sometime – later", None), None);
+    assert_eq!(parse_date_range("led 2020 – současnost", Some(Locale::En)), None);
+
+    // An unreadable duration keeps the dates
+    for text in ["Jan 2020 – Present · a while", "Jan 2020 – Present · less than a year"] {
+        assert_eq!(
+            parse_date_range(text, Some(Locale::En)),
+            Some(range(month(2020, 1), None, None)),
+            "{}",
+            text
+        );
+        assert_eq!(parse_date_range(text, None), Some(range(month(2020, 1), None, None)), "{}", text);
+    }
+    assert_eq!(
+        parse_date_range("2019 – 2021 · 2 roky", None),
+        Some(range(year(2019), year(2021), Some(24)))
+    );
+}
+
+#[test]
+fn test_duration() {
+    assert_eq!(parse_duration("2 yrs 3 mos", Locale::En), Some(27));
+    assert_eq!(parse_duration("1 mo", Locale::En), Some(1));
+    assert_eq!(parse_duration("3 roky", Locale::Cz), Some(36));
+    assert_eq!(parse_duration("3", Locale::En), None);
+}
+
+#[test]
+fn test_interval_to_date_range() {
+    let interval = Interval::from_str("Apr 2016–Present 9 yrs", "–").unwrap();
+    let range = interval.to_date_range(None).unwrap();
+    assert_eq!(range.start, month(2016, 4));
+    assert!(range.is_current);
+    assert_eq!(range.duration_months, Some(108));
+}
+
+#[test]
+fn test_interval_without_delimiter() {
+    let interval = Interval::from_text("2015 - 2019", "–");
+    assert_eq!(interval.to_date_range(None), Some(range(year(2015), year(2019), None)));
+    let interval = Interval::from_text("2019", "–");
+    assert_eq!(interval.to_date_range(None), Some(range(year(2019), year(2019), None)));
+    // Nothing to parse, but nothing panics either
+    assert_eq!(Interval::from_text("Present", "–").to_date_range(None), None);
+    assert_eq!(Interval::from_text("", "–").to_date_range(None), None);
+}
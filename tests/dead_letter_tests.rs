@@ -0,0 +1,98 @@
+use omicron_crawler::azure::json::{Envelope, ProfileIds, ProfileJob};
+use omicron_crawler::queue::dead_letter::{dead_letter, DeadLetterPolicy, DeadLetterStore};
+use omicron_crawler::queue::file::FileQueue;
+use omicron_crawler::queue::memory::MemoryQueue;
+use omicron_crawler::queue::{QueueKind, WorkQueue};
+use std::time::Duration;
+use tempfile::TempDir;
+
+const NO_WAIT: Duration = Duration::ZERO;
+
+fn ids(id: &str) -> ProfileJob {
+    Envelope::new(ProfileIds {
+        ids: vec![id.to_string()],
+        request_metadata: None,
+    })
+}
+
+#[tokio::test]
+async fn test_policy_counts_deliveries() {
+    let queue = MemoryQueue::new();
+    queue.push_profiles(ids("a"));
+    let policy = DeadLetterPolicy { max_attempts: 2 };
+    for delivery_count in 1..=2 {
+        let delivery = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+        assert_eq!(delivery.receipt.delivery_count, delivery_count);
+        assert!(!policy.exceeded(&delivery.receipt));
+        queue.abandon(&delivery.receipt).await.unwrap();
+    }
+    let delivery = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+    assert!(policy.exceeded(&delivery.receipt));
+}
+
+#[tokio::test]
+async fn test_dead_letter_and_replay() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = MemoryQueue::new();
+    let store = DeadLetterStore::open(dir.join("dead")).await.unwrap();
+    queue.push_profiles(ids("a"));
+
+    let delivery = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+    let payload = serde_json::to_string(delivery.message.as_ref().unwrap()).unwrap();
+    dead_letter(&queue, &store, &delivery.receipt, payload, "Failed 5 deliveries")
+        .await
+        .unwrap();
+    assert_eq!(queue.locked_messages(), 0);
+    assert!(queue.dequeue_profiles(NO_WAIT).await.unwrap().is_none());
+
+    let letters = store.list().await.unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].queue, QueueKind::Profiles);
+    assert_eq!(letters[0].reason, "Failed 5 deliveries");
+    assert_eq!(letters[0].delivery_count, 1);
+
+    store.replay(&letters[0].id, &queue).await.unwrap();
+    assert!(store.list().await.unwrap().is_empty());
+    let replayed = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+    let replayed_job = replayed.message.unwrap();
+    assert_eq!(replayed_job.payload.ids, vec!["a"]);
+    assert_eq!(replayed.receipt.delivery_count, 1);
+}
+
+#[tokio::test]
+async fn test_malformed_message_is_kept() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    std::fs::create_dir_all(dir.join("queue")).unwrap();
+    std::fs::write(dir.join("queue").join("searches.jsonl"), "{\"keywords\": \n").unwrap();
+    let queue = FileQueue::new(dir.join("queue")).await.unwrap();
+    let store = DeadLetterStore::open(dir.join("dead")).await.unwrap();
+
+    let delivery = queue.dequeue_search(NO_WAIT).await.unwrap().unwrap();
+    let malformed = delivery.message.err().unwrap();
+    dead_letter(&queue, &store, &delivery.receipt, malformed.raw, &malformed.error)
+        .await
+        .unwrap();
+    assert!(queue.dequeue_search(NO_WAIT).await.unwrap().is_none());
+
+    let letter = store.list().await.unwrap().remove(0);
+    assert_eq!(letter.queue, QueueKind::Search);
+    assert_eq!(letter.payload, "{\"keywords\": ");
+
+    // Replaying an unfixed payload fails and keeps the letter
+    assert!(store.replay(&letter.id, &queue).await.is_err());
+    assert!(store.get(&letter.id).await.unwrap().is_some());
+
+    // Fixed by hand, it goes back on the queue
+    let mut fixed = letter.clone();
+    fixed.payload = "{\"keywords\":\"rust\",\"page\":0,\"end\":1}".to_string();
+    store.store(&fixed).await.unwrap();
+    store.replay(&letter.id, &queue).await.unwrap();
+    let job = queue.dequeue_search(NO_WAIT).await.unwrap().unwrap().message.unwrap();
+    assert_eq!(job.payload.keywords.as_deref(), Some("rust"));
+
+    store.store(&fixed).await.unwrap();
+    store.remove(&fixed.id).await.unwrap();
+    assert!(store.get(&fixed.id).await.unwrap().is_none());
+}
@@ -0,0 +1,94 @@
+use omicron_crawler::azure::json::{CrawledProfiles, Envelope, Priority, ProfileJob, SearchJob, ENVELOPE_SCHEMA_VERSION};
+
+#[test]
+fn test_bare_payload_is_wrapped() {
+    let job: ProfileJob = serde_json::from_str(r#"{"ids":["a","b"],"request_metadata":"meta"}"#).unwrap();
+    assert_eq!(job.schema_version, ENVELOPE_SCHEMA_VERSION);
+    assert_eq!(job.payload.ids, vec!["a", "b"]);
+    assert_eq!(job.payload.request_metadata.as_deref(), Some("meta"));
+    assert_eq!(job.priority, Priority::Normal);
+    assert!(job.correlation_id.is_none());
+    assert!(!job.job_id.is_empty());
+
+    let search: SearchJob = serde_json::from_str(r#"{"keywords":"rust","page":0,"end":1}"#).unwrap();
+    assert_eq!(search.payload.keywords.as_deref(), Some("rust"));
+}
+
+#[test]
+fn test_bare_payload_job_id_is_stable() {
+    // A redelivered bare message keeps its job id, so logs and results of both deliveries line up
+    let raw = r#"{"ids":["a","b"],"request_metadata":"meta"}"#;
+    let first: ProfileJob = serde_json::from_str(raw).unwrap();
+    let again: ProfileJob = serde_json::from_str(raw).unwrap();
+    assert_eq!(first.job_id, again.job_id);
+    assert!(uuid::Uuid::parse_str(&first.job_id).is_ok());
+
+    let other: ProfileJob = serde_json::from_str(r#"{"ids":["c"],"request_metadata":"meta"}"#).unwrap();
+    assert_ne!(first.job_id, other.job_id);
+}
+
+#[test]
+fn test_envelope_roundtrip() {
+    let raw = r#"{
+        "schema_version": 1,
+        "job_id": "job-1",
+        "correlation_id": "corr-1",
+        "tenant": "acme",
+        "created_at": "2024-05-01T10:00:00Z",
+        "priority": "high",
+        "payload": {"ids": ["a"], "request_metadata": null}
+    }"#;
+    let job: ProfileJob = serde_json::from_str(raw).unwrap();
+    assert_eq!(job.job_id, "job-1");
+    assert_eq!(job.correlation_id.as_deref(), Some("corr-1"));
+    assert_eq!(job.tenant.as_deref(), Some("acme"));
+    assert_eq!(job.priority, Priority::High);
+    assert_eq!(job.created_at.to_rfc3339(), "2024-05-01T10:00:00+00:00");
+
+    let again: ProfileJob = serde_json::from_str(&serde_json::to_string(&job).unwrap()).unwrap();
+    assert_eq!(again.job_id, "job-1");
+    assert_eq!(again.created_at, job.created_at);
+    assert_eq!(again.payload.ids, vec!["a"]);
+}
+
+#[test]
+fn test_newer_version_is_rejected() {
+    let raw = r#"{"schema_version":2,"job_id":"j","correlation_id":null,"tenant":null,
+        "created_at":"2024-05-01T10:00:00Z","payload":{"ids":[],"request_metadata":null}}"#;
+    let error = serde_json::from_str::<ProfileJob>(raw).err().unwrap();
+    assert!(error.to_string().contains("Unsupported envelope version 2"));
+}
+
+#[test]
+fn test_invalid_payload_is_rejected() {
+    assert!(serde_json::from_str::<ProfileJob>(r#"{"ids":"a"}"#).is_err());
+    let raw = r#"{"schema_version":1,"job_id":"j","correlation_id":null,"tenant":null,
+        "created_at":"2024-05-01T10:00:00Z","payload":{"keywords":"rust"}}"#;
+    assert!(serde_json::from_str::<ProfileJob>(raw).is_err());
+}
+
+#[test]
+fn test_reply_keeps_job() {
+    let job = Envelope::new(())
+        .with_correlation_id("corr-1")
+        .with_tenant("acme")
+        .with_priority(Priority::Low);
+    let reply = job.reply(CrawledProfiles {
+        profiles: vec![],
+        request_metadata: Some("meta".to_string()),
+    });
+    assert_eq!(reply.job_id, job.job_id);
+    assert_eq!(reply.correlation_id.as_deref(), Some("corr-1"));
+    assert_eq!(reply.tenant.as_deref(), Some("acme"));
+    assert_eq!(reply.priority, Priority::Low);
+
+    let json = serde_json::to_value(&reply).unwrap();
+    assert_eq!(json["schema_version"], ENVELOPE_SCHEMA_VERSION);
+    assert_eq!(json["job_id"], job.job_id.as_str());
+    assert_eq!(json["payload"]["request_metadata"], "meta");
+
+    let mapped = job.clone().map(|_| 5);
+    assert_eq!(mapped.job_id, job.job_id);
+    assert_eq!(mapped.created_at, job.created_at);
+    assert_eq!(mapped.payload, 5);
+}
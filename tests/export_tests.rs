@@ -0,0 +1,77 @@
+use chrono::NaiveDate;
+use omicron_crawler::azure::json::CrawledProfiles;
+use omicron_crawler::linkedin::api::json;
+use omicron_crawler::linkedin::profile::export::{csv_escape, export_profiles, read_profiles, ExportFormat};
+use omicron_crawler::linkedin::profile::Profile;
+
+fn profile() -> Profile {
+    let raw = r#"{
+        "patentView": { "profileId": "matus-chochlik-154a7827" },
+        "educationView": { "elements": [] },
+        "organizationView": {},
+        "projectView": { "elements": [] },
+        "positionView": { "elements": [
+            { "title": "Team Lead, Architect", "companyName": "Asseco CEIT",
+              "timePeriod": { "startDate": { "year": 2016, "month": 4 } } },
+            { "title": "SW engineer", "companyName": "University of Zilina",
+              "timePeriod": { "startDate": { "year": 2012, "month": 4 }, "endDate": { "year": 2016, "month": 3 } } }
+        ] },
+        "profile": {
+            "firstName": "Matus", "lastName": "Chochlik", "geoCountryName": "Slovakia",
+            "headline": "Senior \"C++\" engineer", "locationName": "Slovak Republic", "miniProfile": {}
+        },
+        "languageView": { "elements": [ { "name": "English" }, { "name": "Slovak" } ] },
+        "certificationView": { "elements": [] },
+        "testScoreView": { "elements": [] },
+        "courseView": { "elements": [] },
+        "honorView": { "elements": [] },
+        "skillView": { "elements": [ { "name": "C++", "entityUrn": "urn:li:fs_skill:(x,1)" } ] },
+        "volunteerExperienceView": { "elements": [] },
+        "publicationView": { "elements": [] }
+    }"#;
+    Profile::from(serde_json::from_str::<json::Profile>(raw).unwrap())
+}
+
+#[test]
+fn test_read_profiles() {
+    let profiles = vec![profile(), profile()];
+    let crawled = CrawledProfiles {
+        profiles: profiles.clone(),
+        request_metadata: Some("batch-1".to_string()),
+    };
+    let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+    let inputs = [
+        serde_json::to_string(&crawled).unwrap(),
+        export_profiles(&profiles, ExportFormat::Json, today),
+        export_profiles(&profiles, ExportFormat::Jsonl, today),
+    ];
+    for input in inputs {
+        let read = read_profiles(&input).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].profile_id.as_deref(), Some("matus-chochlik-154a7827"));
+    }
+    assert!(read_profiles("{\"first_name\": 1}\n").is_err());
+    assert!(read_profiles("").unwrap().is_empty());
+}
+
+#[test]
+fn test_export_csv() {
+    let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+    let csv = export_profiles(&[profile()], ExportFormat::Csv, today);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("profile_id,first_name,last_name,headline,seniority,function,current_title"));
+    assert!(lines[1].starts_with("matus-chochlik-154a7827,Matus,Chochlik,\"Senior \"\"C++\"\" engineer\",Senior,Engineering,"));
+    // Current position only, experience from April 2012 to March 2024
+    assert!(lines[1].contains(",\"Team Lead, Architect\",Asseco CEIT,12.0,"));
+    assert!(lines[1].contains(",English; Slovak,"));
+}
+
+#[test]
+fn test_export_format() {
+    assert_eq!("jsonl".parse::<ExportFormat>(), Ok(ExportFormat::Jsonl));
+    assert!("xml".parse::<ExportFormat>().is_err());
+    assert_eq!(ExportFormat::Csv.to_string(), "csv");
+    assert_eq!(csv_escape("plain"), "plain");
+    assert_eq!(csv_escape("a\nb"), "\"a\nb\"");
+}
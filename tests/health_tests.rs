@@ -0,0 +1,102 @@
+use actix_web::{test, web, App};
+use chrono::Utc;
+use omicron_crawler::control::ControlState;
+use omicron_crawler::health::{self, LastError, QueueHealth, SessionStatus, Status, StatusProvider};
+use omicron_crawler::linkedin::api::rate_limits::{RateBudget, RateLimiter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn status(authenticated: bool, remaining: u32, queue: Option<&QueueHealth>) -> Status {
+    Status {
+        service: "test".to_string(),
+        started_at: Utc::now(),
+        uptime_secs: 0,
+        session: SessionStatus {
+            authenticated,
+            cookie_age_secs: Some(60),
+        },
+        rate_budget: RateBudget {
+            profiles_per_hour: 10,
+            used: 10 - remaining,
+            remaining,
+        },
+        in_flight_jobs: 1,
+        queue: queue.map(QueueHealth::status),
+        last_error: Some(LastError::now("boom")),
+        control: ControlState {
+            paused: false,
+            profiles_per_hour: 10,
+            account: "crawler@example.com".to_string(),
+            account_disabled: false,
+        },
+    }
+}
+
+struct Fixed(Status);
+
+impl StatusProvider for Fixed {
+    fn status(&self) -> Status {
+        self.0.clone()
+    }
+}
+
+#[actix_web::test]
+async fn test_rate_budget() {
+    let limiter = RateLimiter::new(3, 800);
+    let start = Instant::now();
+    limiter.next();
+    limiter.next();
+    let budget = limiter.budget_at(start);
+    assert_eq!((budget.used, budget.remaining), (2, 1));
+    limiter.next();
+    limiter.next();
+    assert_eq!(limiter.budget().remaining, 0);
+    // Waits older than an hour no longer count
+    assert_eq!(limiter.budget_at(start + Duration::from_secs(3601)).used, 0);
+}
+
+#[actix_web::test]
+async fn test_readiness() {
+    let queue = QueueHealth::new();
+    assert!(status(true, 1, Some(&queue)).readiness().ready);
+    assert!(!status(false, 1, None).readiness().ready);
+    assert!(!status(true, 0, None).readiness().ready);
+
+    queue.failed("Queue unreachable");
+    let readiness = status(true, 1, Some(&queue)).readiness();
+    assert!(!readiness.ready);
+    assert_eq!(
+        readiness
+            .checks
+            .iter()
+            .filter(|check| !check.ok)
+            .map(|check| check.name)
+            .collect::<Vec<_>>(),
+        ["queue"]
+    );
+    assert_eq!(queue.status().last_error.unwrap().message, "Queue unreachable");
+
+    queue.received();
+    assert!(queue.status().reachable && queue.status().last_receive_at.is_some());
+}
+
+#[actix_web::test]
+async fn test_routes() {
+    let provider: Arc<dyn StatusProvider> = Arc::new(Fixed(status(true, 0, None)));
+    let app = test::init_service(App::new().app_data(web::Data::from(provider)).configure(health::configure)).await;
+
+    let response = test::call_service(&app, test::TestRequest::get().uri("/healthz").to_request()).await;
+    assert!(response.status().is_success());
+
+    let response = test::call_service(&app, test::TestRequest::get().uri("/readyz").to_request()).await;
+    assert_eq!(response.status(), 503);
+    let body: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(body["checks"][1], serde_json::json!({ "name": "rate_budget", "ok": false }));
+
+    let body: serde_json::Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/status").to_request()).await;
+    assert_eq!(body["session"]["cookie_age_secs"], 60);
+    assert_eq!(body["rate_budget"]["remaining"], 0);
+    assert_eq!(body["in_flight_jobs"], 1);
+    assert_eq!(body["last_error"]["message"], "boom");
+    assert!(body["queue"].is_null());
+}
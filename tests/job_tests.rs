@@ -0,0 +1,302 @@
+use omicron_crawler::auth::{parse_api_keys, ApiKeys};
+use omicron_crawler::azure::json::ProfileIds;
+use omicron_crawler::errors::CrawlerError::LinkedinError;
+use omicron_crawler::errors::CrawlerResult;
+use omicron_crawler::jobs::{Job, JobEvent, JobExecutor, JobKind, JobManager, JobRequest, JobStatus};
+use omicron_crawler::linkedin::api::json::{SearchItem, SearchParams, SearchResult};
+use omicron_crawler::linkedin::profile::{Profile, ProfileSource};
+use std::sync::Arc;
+use std::time::Duration;
+
+const STEP: Duration = Duration::from_millis(20);
+
+/// Takes one step per profile or search page, failing profiles named "bad" and searches for "fail".
+struct FakeExecutor;
+
+impl JobExecutor for FakeExecutor {
+    async fn search_page(&self, params: SearchParams) -> CrawlerResult<SearchResult> {
+        tokio::time::sleep(STEP).await;
+        if params.keywords.as_deref() == Some("fail") {
+            return Err(LinkedinError("Search failed".to_string()));
+        }
+        // Runs out of people on page 5
+        let elements = match params.page < 5 {
+            true => vec![SearchItem {
+                first_name: format!("page-{}", params.page),
+                last_name: String::new(),
+                subtitle: None,
+                summary: None,
+                url: String::new(),
+                profile_urn: String::new(),
+            }],
+            false => Vec::new(),
+        };
+        Ok(SearchResult {
+            elements,
+            request_metadata: params.request_metadata,
+            total_lookup: 0,
+            total: 0,
+            resume_page: None,
+        })
+    }
+
+    async fn profile(&self, id: &str) -> CrawlerResult<Profile> {
+        tokio::time::sleep(STEP).await;
+        if id == "bad" {
+            return Err(LinkedinError("Profile not found".to_string()));
+        }
+        Ok(Profile {
+            schema_version: 1,
+            source: ProfileSource::Api,
+            profile_id: Some(id.to_string()),
+            first_name: id.to_string(),
+            last_name: String::new(),
+            headline: None,
+            headline_classification: Default::default(),
+            summary: None,
+            location: None,
+            country: None,
+            industry: None,
+            picture_url: None,
+            url: None,
+            sales_url: None,
+            positions: Vec::new(),
+            education: Vec::new(),
+            skills: Vec::new(),
+            languages: Vec::new(),
+            certifications: Vec::new(),
+            projects: Vec::new(),
+        })
+    }
+}
+
+fn manager() -> Arc<JobManager> {
+    manager_keeping(Duration::from_secs(3600))
+}
+
+fn manager_keeping(retention: Duration) -> Arc<JobManager> {
+    let manager = JobManager::new(retention);
+    manager.spawn(Box::leak(Box::new(FakeExecutor))).unwrap();
+    manager
+}
+
+fn profiles(ids: &[&str]) -> JobRequest {
+    JobRequest::Profiles(ProfileIds {
+        ids: ids.iter().map(|id| id.to_string()).collect(),
+        request_metadata: Some("meta".to_string()),
+    })
+}
+
+fn search(pages: u16) -> JobRequest {
+    search_for("rust", pages)
+}
+
+fn search_for(keywords: &str, pages: u16) -> JobRequest {
+    JobRequest::Search(SearchParams {
+        countries: None,
+        keywords: Some(keywords.to_string()),
+        keyword_first_name: None,
+        keyword_last_name: None,
+        keyword_title: None,
+        keyword_company: None,
+        keyword_school: None,
+        profile_language: None,
+        request_metadata: None,
+        network_depth: None,
+        page: 0,
+        end: pages,
+    })
+}
+
+async fn wait_until(manager: &JobManager, id: &str, condition: impl Fn(&Job) -> bool) -> Job {
+    for _ in 0..500 {
+        let job = manager.get(id).unwrap();
+        if condition(&job) {
+            return job;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("Job {} did not reach the expected state", id);
+}
+
+#[tokio::test]
+async fn test_profiles_job() {
+    let manager = manager();
+    let job = manager.submit(profiles(&["a", "bad", "b"]), None);
+    assert_eq!(job.kind, JobKind::Profiles);
+    assert_eq!(job.total, 3);
+    assert_eq!(job.request_metadata.as_deref(), Some("meta"));
+
+    // Partial results are visible while the job runs
+    let running = wait_until(&manager, &job.id, |job| job.done == 1).await;
+    assert_eq!(running.status, JobStatus::Running);
+    assert_eq!(running.results.len(), 1);
+    assert_eq!(running.results[0]["profile_id"], "a");
+
+    let finished = wait_until(&manager, &job.id, |job| job.status.is_finished()).await;
+    assert_eq!(finished.status, JobStatus::Completed);
+    assert_eq!(finished.done, 3);
+    assert_eq!(finished.results.len(), 2);
+    assert_eq!(finished.errors.len(), 1);
+    assert_eq!(finished.errors[0].id, "bad");
+    assert!(finished.started_at.is_some() && finished.finished_at.is_some());
+}
+
+#[tokio::test]
+async fn test_search_job() {
+    let manager = manager();
+    let job = manager.submit(search(3), None);
+    assert_eq!(job.kind, JobKind::Search);
+    assert_eq!(job.status, JobStatus::Queued);
+    let running = wait_until(&manager, &job.id, |job| job.done == 1).await;
+    assert_eq!(running.results[0]["elements"][0]["first_name"], "page-0");
+    let finished = wait_until(&manager, &job.id, |job| job.status.is_finished()).await;
+    assert_eq!(finished.status, JobStatus::Completed);
+    assert_eq!(finished.done, 3);
+    assert_eq!(finished.results.len(), 3);
+
+    // Stops at the first empty page
+    let job = manager.submit(search(10), None);
+    let finished = wait_until(&manager, &job.id, |job| job.status.is_finished()).await;
+    assert_eq!(finished.status, JobStatus::Completed);
+    assert_eq!((finished.done, finished.total), (6, 6));
+
+    let job = manager.submit(search_for("fail", 3), None);
+    let failed = wait_until(&manager, &job.id, |job| job.status.is_finished()).await;
+    assert_eq!(failed.status, JobStatus::Failed);
+    assert!(failed.error.unwrap().contains("Search failed"));
+}
+
+#[tokio::test]
+async fn test_cancel_jobs() {
+    let manager = manager();
+    let running = manager.submit(profiles(&["a", "b", "c", "d", "e", "f"]), None);
+    let queued = manager.submit(search(3), None);
+
+    let cancelled = manager.cancel(&queued.id).unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+
+    wait_until(&manager, &running.id, |job| job.done >= 1).await;
+    assert_eq!(manager.cancel(&running.id).unwrap().status, JobStatus::Running);
+    let stopped = wait_until(&manager, &running.id, |job| job.status.is_finished()).await;
+    assert_eq!(stopped.status, JobStatus::Cancelled);
+    assert!(stopped.done < 6);
+    assert_eq!(stopped.results.len(), stopped.done);
+
+    // The cancelled search never ran and the worker moves on to later jobs
+    let next = manager.submit(search(1), None);
+    wait_until(&manager, &next.id, |job| job.status == JobStatus::Completed).await;
+    let cancelled = manager.get(&queued.id).unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+    assert!(cancelled.started_at.is_none());
+
+    // Finished jobs stay as they are
+    assert_eq!(manager.cancel(&next.id).unwrap().status, JobStatus::Completed);
+}
+
+#[tokio::test]
+async fn test_cancel_running_search() {
+    let manager = manager();
+    let job = manager.submit(search(5), None);
+    wait_until(&manager, &job.id, |job| job.done >= 1).await;
+    manager.cancel(&job.id);
+    let stopped = wait_until(&manager, &job.id, |job| job.status.is_finished()).await;
+    assert_eq!(stopped.status, JobStatus::Cancelled);
+    assert!(stopped.done > 0 && stopped.done < 5);
+    assert_eq!(stopped.results.len(), stopped.done);
+}
+
+#[tokio::test]
+async fn test_unknown_job() {
+    let manager = manager();
+    assert!(manager.get("missing").is_none());
+    assert!(manager.cancel("missing").is_none());
+    // The worker is only started once
+    assert!(manager.spawn(Box::leak(Box::new(FakeExecutor))).is_none());
+}
+
+#[tokio::test]
+async fn test_jobs_belong_to_their_client() {
+    let keys = ApiKeys::new(parse_api_keys("ui:a,crm:b,ops:c", 100).unwrap()).with_admins(vec!["ops".to_string()]);
+    let manager = manager();
+    let job = manager.submit(search(1), Some("ui".to_string()));
+    assert_eq!(job.owner.as_deref(), Some("ui"));
+
+    // The handlers answer 404 for a job the client may not see, exactly as for an unknown job
+    let (ui, crm, ops) = (keys.authenticate("a"), keys.authenticate("b"), keys.authenticate("c"));
+    assert!(job.is_visible_to(ui, &keys));
+    assert!(!job.is_visible_to(crm, &keys));
+    assert!(job.is_visible_to(ops, &keys));
+    assert!(!job.is_visible_to(None, &keys));
+
+    // Without keys nobody owns a job and everybody sees it
+    let open = manager.submit(search(1), None);
+    assert!(open.is_visible_to(None, &ApiKeys::new(Vec::new())));
+    assert!(!open.is_visible_to(crm, &keys));
+}
+
+#[tokio::test]
+async fn test_finished_jobs_expire() {
+    let manager = manager_keeping(Duration::from_millis(100));
+    let finished = manager.submit(profiles(&["a"]), None);
+    wait_until(&manager, &finished.id, |job| job.status.is_finished()).await;
+    let running = manager.submit(profiles(&["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"]), None);
+    assert!(manager.get(&finished.id).is_some());
+
+    // Once past the retention the finished job and its results are gone, the running one stays
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(manager.get(&finished.id).is_none());
+    assert!(manager.subscribe(&finished.id).is_none());
+    assert_eq!(manager.get(&running.id).unwrap().status, JobStatus::Running);
+}
+
+#[tokio::test]
+async fn test_job_events() {
+    let manager = manager();
+    let job = manager.submit(profiles(&["a", "bad"]), None);
+    let (snapshot, mut events) = manager.subscribe(&job.id).unwrap();
+    assert_eq!(snapshot.status, JobStatus::Queued);
+
+    let mut names = Vec::new();
+    loop {
+        let event = events.recv().await.unwrap();
+        names.push(event.name());
+        match &event {
+            JobEvent::Profile { id, profile } => assert_eq!((id.as_str(), &profile["profile_id"]), ("a", &serde_json::json!("a"))),
+            JobEvent::Error(error) => assert_eq!(error.id, "bad"),
+            _ => {}
+        }
+        if event.is_last() {
+            break;
+        }
+    }
+    assert_eq!(names, vec!["status", "profile", "error", "status"]);
+
+    // Subscribing to a finished job yields only its final state
+    let (snapshot, _) = manager.subscribe(&job.id).unwrap();
+    let event = JobEvent::Snapshot { job: snapshot };
+    assert!(event.is_last());
+    let message = event.to_sse();
+    assert!(message.starts_with("event: snapshot\ndata: {\"event\":\"snapshot\",\"job\":{"));
+    assert!(message.ends_with("}}\n\n"));
+    assert!(manager.subscribe("missing").is_none());
+}
+
+#[tokio::test]
+async fn test_search_events() {
+    let manager = manager();
+    let job = manager.submit(search(2), None);
+    let (_, mut events) = manager.subscribe(&job.id).unwrap();
+    let mut pages = Vec::new();
+    loop {
+        match events.recv().await.unwrap() {
+            JobEvent::SearchPage { page, .. } => pages.push(page),
+            JobEvent::Status { status, done, .. } if status.is_finished() => {
+                assert_eq!((status, done), (JobStatus::Completed, 2));
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(pages, vec![0, 1]);
+}
@@ -0,0 +1,155 @@
+use omicron_crawler::azure::json::ProfileIds;
+use omicron_crawler::azure::manager::{signature, ManagerClient, ManagerError, REQUEST_ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use omicron_crawler::azure::Label;
+use omicron_crawler::env::ManagerAuth;
+use omicron_crawler::errors::CrawlerError;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+struct Request {
+    path: String,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+/// Answers each request with the next queued status and records what it received.
+struct Manager {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+async fn manager(statuses: &[u16]) -> Manager {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let mut statuses: VecDeque<u16> = statuses.iter().copied().collect();
+    let recorded = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let path = line.split_whitespace().nth(1).unwrap_or_default().to_string();
+            let mut headers = HashMap::new();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).await.unwrap();
+                match line.trim_end().split_once(": ") {
+                    Some((name, value)) => headers.insert(name.to_lowercase(), value.to_string()),
+                    None => break,
+                };
+            }
+            let length = headers.get("content-length").map(|l| l.parse().unwrap()).unwrap_or(0);
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await.unwrap();
+            recorded.lock().unwrap().push(Request {
+                path,
+                headers,
+                body: String::from_utf8(body).unwrap(),
+            });
+            let status = statuses.pop_front().unwrap_or(200);
+            let response = format!("HTTP/1.1 {} Status\r\ncontent-length: 4\r\nconnection: close\r\n\r\nnope", status);
+            reader.into_inner().write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    Manager { url, requests }
+}
+
+fn client(manager: &Manager, auth: ManagerAuth) -> ManagerClient {
+    ManagerClient::new(format!("{}/search", manager.url), format!("{}/profiles", manager.url), auth)
+}
+
+fn ids() -> ProfileIds {
+    ProfileIds {
+        ids: vec!["a".to_string()],
+        request_metadata: None,
+    }
+}
+
+#[tokio::test]
+async fn test_bearer_submission() {
+    let manager = manager(&[200, 409]).await;
+    let client = client(&manager, ManagerAuth::Bearer("token".to_string()));
+    let first = client.submit(&ids(), Label::ProfilesComplete).await.unwrap();
+    let retried = client.submit(&ids(), Label::ProfilesComplete).await.unwrap();
+    assert!(!first.duplicate);
+    assert!(retried.duplicate);
+    // A retried submission keeps its request id so the manager can dedupe it
+    assert_eq!(first.request_id, retried.request_id);
+
+    let requests = manager.requests.lock().unwrap();
+    assert_eq!(requests[0].path, "/profiles");
+    assert_eq!(requests[0].headers["authorization"], "Bearer token");
+    assert_eq!(requests[0].headers["content-type"], "application/json");
+    assert_eq!(requests[0].headers[&REQUEST_ID_HEADER.to_lowercase()], first.request_id);
+    assert_eq!(requests[0].body, r#"{"ids":["a"],"request_metadata":null}"#);
+}
+
+#[tokio::test]
+async fn test_hmac_submission() {
+    let manager = manager(&[204]).await;
+    let client = client(&manager, ManagerAuth::Hmac("secret".to_string()));
+    client.submit(&ids(), Label::SearchComplete).await.unwrap();
+
+    let requests = manager.requests.lock().unwrap();
+    let request = &requests[0];
+    assert_eq!(request.path, "/search");
+    assert!(!request.headers.contains_key("authorization"));
+    let timestamp: u64 = request.headers[&TIMESTAMP_HEADER.to_lowercase()].parse().unwrap();
+    let request_id = &request.headers[&REQUEST_ID_HEADER.to_lowercase()];
+    assert_eq!(
+        request.headers[&SIGNATURE_HEADER.to_lowercase()],
+        signature("secret", timestamp, request_id, &request.body)
+    );
+}
+
+#[tokio::test]
+async fn test_typed_errors() {
+    let manager = manager(&[401, 422, 503, 429]).await;
+    let client = client(&manager, ManagerAuth::None);
+    let error = client.submit(&ids(), Label::SearchComplete).await.err().unwrap();
+    assert!(matches!(error, ManagerError::Unauthorized { status: 401 }));
+    assert!(error.is_retryable());
+
+    let error = client.submit(&ids(), Label::SearchComplete).await.err().unwrap();
+    assert!(matches!(&error, ManagerError::Rejected { status: 422, body } if body == "nope"));
+    assert!(!error.is_retryable());
+
+    let error = client.submit(&ids(), Label::SearchComplete).await.err().unwrap();
+    assert!(matches!(error, ManagerError::Unavailable { status: 503, .. }));
+    assert!(error.is_retryable());
+    let error = client.submit(&ids(), Label::SearchComplete).await.err().unwrap();
+    assert!(matches!(error, ManagerError::Unavailable { status: 429, .. }));
+
+    let unreachable = ManagerClient::new("http://127.0.0.1:1/search", "http://127.0.0.1:1/profiles", ManagerAuth::None);
+    let error = unreachable.submit(&ids(), Label::SearchComplete).await.err().unwrap();
+    assert!(matches!(error, ManagerError::Transport(_)));
+    assert!(error.is_retryable());
+}
+
+#[test]
+fn test_crawler_error_keeps_manager_error() {
+    // Callers of push_to_manager only see a CrawlerError, it has to tell a rejection from an outage
+    let rejected = CrawlerError::from(ManagerError::Rejected {
+        status: 422,
+        body: "nope".to_string(),
+    });
+    assert!(matches!(
+        &rejected,
+        CrawlerError::ManagerError(ManagerError::Rejected { status: 422, .. })
+    ));
+    assert!(!rejected.is_retryable());
+    assert!(CrawlerError::from(ManagerError::Transport("reset".to_string())).is_retryable());
+    assert!(CrawlerError::BusError("timeout".to_string()).is_retryable());
+}
+
+#[test]
+fn test_signature() {
+    // Computed independently: HMAC-SHA256 of "1700000000\nid\n{}" keyed with "secret"
+    assert_eq!(
+        signature("secret", 1700000000, "id", "{}"),
+        "6ddc2822c7c0c2a19494e2f218db431241ac75a2747563bfa44a193d7fb19433"
+    );
+}
@@ -0,0 +1,55 @@
+use actix_web::middleware::from_fn;
+use actix_web::{test, web, App, HttpResponse};
+use omicron_crawler::errors::CrawlerError::QueueError;
+use omicron_crawler::errors::CrawlerResult;
+use omicron_crawler::metrics::{self, linkedin_response, queue_receive, track_http, PROFILES_CRAWLED};
+use omicron_crawler::queue::QueueKind;
+
+async fn item() -> HttpResponse {
+    HttpResponse::Ok().finish()
+}
+
+#[actix_web::test]
+async fn test_metrics_endpoint() {
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn(track_http))
+            .configure(metrics::configure)
+            .route("/items/{id}", web::get().to(item)),
+    )
+    .await;
+    for id in ["a", "b"] {
+        test::call_service(&app, test::TestRequest::get().uri(&format!("/items/{}", id)).to_request()).await;
+    }
+    test::call_service(&app, test::TestRequest::get().uri("/missing").to_request()).await;
+
+    let response = test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+    assert!(response.status().is_success());
+    let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+    // Ids in the path do not make a series each
+    assert!(body.contains(r#"http_requests_total{method="GET",route="/items/{id}",status="200"} 2"#));
+    assert!(body.contains(r#"http_requests_total{method="GET",route="unmatched",status="404"} 1"#));
+    // Registered up front even though nothing crawled yet
+    assert!(body.contains("# TYPE profiles_crawled_total counter"));
+    assert!(body.contains("# TYPE rate_limit_sleep_seconds histogram"));
+    assert!(body.contains("# TYPE manager_push_retries_total counter"));
+}
+
+#[actix_web::test]
+async fn test_recorders() {
+    let empty: CrawlerResult<Option<()>> = Ok(None);
+    let failed: CrawlerResult<Option<()>> = Err(QueueError("down".to_string()));
+    queue_receive(QueueKind::Search, &empty);
+    queue_receive(QueueKind::Search, &empty);
+    queue_receive(QueueKind::Profiles, &failed);
+
+    let response = reqwest::Response::from(http::Response::builder().status(429).body("").unwrap());
+    linkedin_response("profile", &Ok(response));
+    PROFILES_CRAWLED.inc();
+
+    let body = metrics::render();
+    assert!(body.contains(r#"queue_receives_total{outcome="empty",queue="searches"} 2"#));
+    assert!(body.contains(r#"queue_receives_total{outcome="error",queue="profiles"} 1"#));
+    assert!(body.contains(r#"linkedin_requests_total{endpoint="profile",status="429"} 1"#));
+    assert!(body.contains("profiles_crawled_total 1"));
+}
@@ -0,0 +1,269 @@
+use omicron_crawler::azure::json::{ProfileIds, ProfileJob, SearchJob};
+use omicron_crawler::azure::manager::ManagerError;
+use omicron_crawler::azure::Label;
+use omicron_crawler::control::ControlCommand;
+use omicron_crawler::errors::CrawlerError::BusError;
+use omicron_crawler::errors::CrawlerResult;
+use omicron_crawler::queue::dead_letter::{DeadLetterPolicy, DeadLetterStore};
+use omicron_crawler::queue::memory::MemoryQueue;
+use omicron_crawler::queue::outbox::{Backoff, Outbox};
+use omicron_crawler::queue::{Delivery, QueueKind, Receipt, WorkQueue};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Memory queue whose manager side can be switched off, made to refuse the credentials or to reject
+/// everything.
+#[derive(Default)]
+struct FlakyQueue {
+    inner: MemoryQueue,
+    down: AtomicBool,
+    unauthorized: AtomicBool,
+    rejecting: AtomicBool,
+}
+
+impl WorkQueue for FlakyQueue {
+    async fn dequeue_search(&self, wait: Duration) -> CrawlerResult<Option<Delivery<SearchJob>>> {
+        self.inner.dequeue_search(wait).await
+    }
+
+    async fn dequeue_profiles(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ProfileJob>>> {
+        self.inner.dequeue_profiles(wait).await
+    }
+
+    async fn dequeue_control(&self, wait: Duration) -> CrawlerResult<Option<Delivery<ControlCommand>>> {
+        self.inner.dequeue_control(wait).await
+    }
+
+    async fn complete(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.inner.complete(receipt).await
+    }
+
+    async fn abandon(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.inner.abandon(receipt).await
+    }
+
+    async fn renew_lock(&self, receipt: &Receipt) -> CrawlerResult<()> {
+        self.inner.renew_lock(receipt).await
+    }
+
+    async fn requeue_search(&self, job: &SearchJob) -> CrawlerResult<()> {
+        self.inner.requeue_search(job).await
+    }
+
+    async fn requeue_profiles(&self, job: &ProfileJob) -> CrawlerResult<()> {
+        self.inner.requeue_profiles(job).await
+    }
+
+    async fn requeue_control(&self, command: &ControlCommand) -> CrawlerResult<()> {
+        self.inner.requeue_control(command).await
+    }
+
+    async fn publish_result<T>(&self, data: &T, label: Label) -> CrawlerResult<()>
+    where
+        T: Serialize + Send + Sync,
+    {
+        if self.down.load(Relaxed) {
+            return Err(BusError("manager is down".to_string()));
+        }
+        if self.unauthorized.load(Relaxed) {
+            return Err(ManagerError::Unauthorized { status: 401 }.into());
+        }
+        if self.rejecting.load(Relaxed) {
+            return Err(ManagerError::Rejected {
+                status: 422,
+                body: "nope".to_string(),
+            }
+            .into());
+        }
+        self.inner.publish_result(data, label).await
+    }
+}
+
+fn ids(id: &str) -> ProfileIds {
+    ProfileIds {
+        ids: vec![id.to_string()],
+        request_metadata: None,
+    }
+}
+
+fn fast_backoff() -> Backoff {
+    Backoff {
+        initial: Duration::from_millis(5),
+        max: Duration::from_millis(20),
+    }
+}
+
+async fn open(dir: &Path, queue: Arc<FlakyQueue>, max_attempts: u32) -> Outbox<FlakyQueue> {
+    let dead_letters = DeadLetterStore::open(dir.join("dead_letters")).await.unwrap();
+    Outbox::open(dir, queue, dead_letters, DeadLetterPolicy { max_attempts }, fast_backoff())
+        .await
+        .unwrap()
+}
+
+#[test]
+fn test_backoff() {
+    let backoff = Backoff {
+        initial: Duration::from_secs(1),
+        max: Duration::from_secs(10),
+    };
+    assert_eq!(backoff.delay(1), Duration::from_secs(1));
+    assert_eq!(backoff.delay(2), Duration::from_secs(2));
+    assert_eq!(backoff.delay(4), Duration::from_secs(8));
+    assert_eq!(backoff.delay(5), Duration::from_secs(10));
+    assert_eq!(backoff.delay(100), Duration::from_secs(10));
+}
+
+#[tokio::test]
+async fn test_outbox_retries_until_acknowledged() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = Arc::new(FlakyQueue::default());
+    queue.down.store(true, Relaxed);
+    let outbox = open(dir, queue.clone(), 100).await;
+
+    outbox.enqueue(&ids("a"), Label::ProfilesComplete).await.unwrap();
+    outbox.enqueue(&ids("b"), Label::SearchComplete).await.unwrap();
+    assert_eq!(outbox.drain().await.unwrap(), 2);
+    assert_eq!(outbox.drain().await.unwrap(), 2);
+    let pending = outbox.pending().await.unwrap();
+    assert_eq!(pending.len(), 2);
+    assert_eq!(pending[0].attempts, 2);
+    assert_eq!(pending[1].attempts, 0);
+    assert!(queue.inner.results().is_empty());
+
+    queue.down.store(false, Relaxed);
+    assert_eq!(outbox.drain().await.unwrap(), 0);
+    assert!(outbox.pending().await.unwrap().is_empty());
+    let results = queue.inner.results();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].data["ids"][0], "a");
+    assert_eq!(results[1].label, Label::SearchComplete);
+}
+
+#[tokio::test]
+async fn test_outbox_survives_restart() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = Arc::new(FlakyQueue::default());
+    queue.down.store(true, Relaxed);
+    let outbox = open(dir, queue.clone(), 100).await;
+    outbox.enqueue(&ids("a"), Label::ProfilesComplete).await.unwrap();
+    drop(outbox);
+
+    let queue = Arc::new(FlakyQueue::default());
+    let outbox = Arc::new(open(dir, queue.clone(), 100).await);
+    assert_eq!(outbox.pending().await.unwrap().len(), 1);
+    let sender = outbox.clone().spawn();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(queue.inner.results().len(), 1);
+
+    // New entries wake the sender
+    outbox.enqueue(&ids("b"), Label::ProfilesComplete).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(queue.inner.results().len(), 2);
+    sender.abort();
+}
+
+#[tokio::test]
+async fn test_outbox_flush_deadline() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = Arc::new(FlakyQueue::default());
+    queue.down.store(true, Relaxed);
+    let outbox = open(dir, queue.clone(), 100).await;
+    outbox.enqueue(&ids("a"), Label::ProfilesComplete).await.unwrap();
+    assert_eq!(outbox.flush(Duration::from_millis(30)).await.unwrap(), 1);
+
+    queue.down.store(false, Relaxed);
+    assert_eq!(outbox.flush(Duration::from_millis(30)).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_outbox_dead_letters_rejected_results() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = Arc::new(FlakyQueue::default());
+    queue.rejecting.store(true, Relaxed);
+    let outbox = open(dir, queue.clone(), 100).await;
+    outbox.enqueue(&ids("a"), Label::ProfilesComplete).await.unwrap();
+    queue.rejecting.store(false, Relaxed);
+    outbox.enqueue(&ids("b"), Label::ProfilesComplete).await.unwrap();
+
+    // A rejection is not retried and does not hold up the next result
+    queue.rejecting.store(true, Relaxed);
+    assert_eq!(outbox.drain().await.unwrap(), 0);
+    let store = DeadLetterStore::open(dir.join("dead_letters")).await.unwrap();
+    let letters = store.list().await.unwrap();
+    assert_eq!(letters.len(), 2);
+    assert_eq!(letters[0].queue, QueueKind::Results);
+    assert!(letters[0].reason.contains("422"));
+
+    // Replaying publishes the result once the manager accepts it
+    queue.rejecting.store(false, Relaxed);
+    store.replay(&letters[0].id, queue.as_ref()).await.unwrap();
+    let results = queue.inner.results();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].data["ids"][0], "a");
+    assert_eq!(results[0].label, Label::ProfilesComplete);
+}
+
+#[tokio::test]
+async fn test_outbox_retries_refused_credentials() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = Arc::new(FlakyQueue::default());
+    queue.unauthorized.store(true, Relaxed);
+    let outbox = open(dir, queue.clone(), 100).await;
+    outbox.enqueue(&ids("a"), Label::ProfilesComplete).await.unwrap();
+
+    // A 401 while a token is rotated keeps the result for the next attempt
+    assert_eq!(outbox.drain().await.unwrap(), 1);
+    assert_eq!(outbox.pending().await.unwrap()[0].attempts, 1);
+    let store = DeadLetterStore::open(dir.join("dead_letters")).await.unwrap();
+    assert!(store.list().await.unwrap().is_empty());
+
+    queue.unauthorized.store(false, Relaxed);
+    assert_eq!(outbox.drain().await.unwrap(), 0);
+    assert_eq!(queue.inner.results().len(), 1);
+}
+
+#[tokio::test]
+async fn test_outbox_caps_attempts() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = Arc::new(FlakyQueue::default());
+    queue.down.store(true, Relaxed);
+    let outbox = open(dir, queue.clone(), 2).await;
+    outbox.enqueue(&ids("a"), Label::ProfilesComplete).await.unwrap();
+    outbox.enqueue(&ids("b"), Label::ProfilesComplete).await.unwrap();
+    assert_eq!(outbox.drain().await.unwrap(), 2);
+
+    // The second failure of the oldest entry gives up on it, the next one is tried right away
+    assert_eq!(outbox.drain().await.unwrap(), 1);
+    let pending = outbox.pending().await.unwrap();
+    assert_eq!(pending[0].data["ids"][0], "b");
+    assert_eq!(pending[0].attempts, 1);
+    let letters = DeadLetterStore::open(dir.join("dead_letters")).await.unwrap().list().await.unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].delivery_count, 2);
+}
+
+#[tokio::test]
+async fn test_outbox_skips_unreadable_entries() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = Arc::new(FlakyQueue::default());
+    let outbox = open(dir, queue.clone(), 100).await;
+    std::fs::write(dir.join("0000.json"), "{\"id\":").unwrap();
+    outbox.enqueue(&ids("a"), Label::ProfilesComplete).await.unwrap();
+
+    assert_eq!(outbox.drain().await.unwrap(), 0);
+    assert_eq!(queue.inner.results().len(), 1);
+    assert!(dir.join("0000.json.invalid").exists());
+    assert!(outbox.pending().await.unwrap().is_empty());
+}
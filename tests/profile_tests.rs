@@ -0,0 +1,208 @@
+use chrono::NaiveDate;
+use omicron_crawler::linkedin::api::json;
+use omicron_crawler::linkedin::profile::classifier::Classification;
+use omicron_crawler::linkedin::profile::dates::DatePrecision;
+use omicron_crawler::linkedin::profile::{Date, Position, Profile, ProfileSource, TimePeriod, PROFILE_SCHEMA_VERSION};
+use omicron_crawler::linkedin::web_driver::profiles;
+
+fn api_profile() -> json::Profile {
+    let raw = r#"{
+        "patentView": { "profileId": "matus-chochlik-154a7827" },
+        "educationView": { "elements": [
+            { "schoolName": "University of Zilina", "degreeName": "PhD", "fieldOfStudy": "Applied Computer Science",
+              "timePeriod": { "startDate": { "year": 2005 }, "endDate": { "year": 2008 } } }
+        ] },
+        "organizationView": {},
+        "projectView": { "elements": [ { "title": "OGLplus" } ] },
+        "positionView": { "elements": [
+            { "title": "Team Lead / SW engineer / Architect", "companyName": "Asseco CEIT",
+              "timePeriod": { "startDate": { "year": 2016, "month": 4 } } }
+        ] },
+        "profile": {
+            "firstName": "Matus", "lastName": "Chochlik", "geoCountryName": "Slovakia",
+            "headline": "SW engineer C++/Python", "locationName": "Slovak Republic", "miniProfile": {}
+        },
+        "languageView": { "elements": [ { "name": "English", "proficiency": "FULL_PROFESSIONAL" } ] },
+        "certificationView": { "elements": [] },
+        "testScoreView": { "elements": [] },
+        "courseView": { "elements": [] },
+        "honorView": { "elements": [] },
+        "skillView": { "elements": [ { "name": "C++", "entityUrn": "urn:li:fs_skill:(x,1)" } ] },
+        "volunteerExperienceView": { "elements": [] },
+        "publicationView": { "elements": [] }
+    }"#;
+    serde_json::from_str(raw).unwrap()
+}
+
+fn web_profile() -> profiles::Profile {
+    profiles::Profile {
+        name: "Matus Chochlik".to_string(),
+        url: "https://www.linkedin.com/in/matus-chochlik-154a7827".to_string(),
+        sales_url: Some("https://www.linkedin.com/sales/lead/ACwAAAWs1dA".to_string()),
+        profile_picture_url: "".to_string(),
+        description: "SW engineer C++/Python".to_string(),
+        about: None,
+        location: "Slovakia".to_string(),
+        experience: Some(vec![profiles::Experience {
+            position: "Team Lead / SW engineer / Architect".to_string(),
+            interval: profiles::Interval {
+                start: "Apr 2016".to_string(),
+                end: "Present".to_string(),
+            },
+        }]),
+        education: None,
+        skills: Some(vec![profiles::Skill {
+            name: "C++".to_string(),
+            endorsements: 12,
+        }]),
+        languages: None,
+    }
+}
+
+#[test]
+fn test_profile_from_api() {
+    let profile = Profile::from(api_profile());
+    assert_eq!(profile.schema_version, PROFILE_SCHEMA_VERSION);
+    assert_eq!(profile.source, ProfileSource::Api);
+    assert_eq!(profile.profile_id.as_deref(), Some("matus-chochlik-154a7827"));
+    assert_eq!(profile.first_name, "Matus");
+    assert_eq!(profile.last_name, "Chochlik");
+    assert_eq!(profile.country.as_deref(), Some("Slovakia"));
+    assert_eq!(profile.positions.len(), 1);
+    assert_eq!(profile.positions[0].company.as_deref(), Some("Asseco CEIT"));
+    let period = profile.positions[0].time_period.as_ref().unwrap();
+    assert_eq!(
+        period.start,
+        Some(Date {
+            year: 2016,
+            month: Some(4),
+            day: None
+        })
+    );
+    assert_eq!(period.end, None);
+    assert_eq!(profile.education[0].school.as_deref(), Some("University of Zilina"));
+    assert_eq!(profile.skills[0].name, "C++");
+    assert_eq!(profile.skills[0].endorsements, None);
+    assert_eq!(profile.projects[0].title, "OGLplus");
+}
+
+#[test]
+fn test_profile_from_web_driver() {
+    let profile = Profile::from(web_profile());
+    assert_eq!(profile.source, ProfileSource::WebDriver);
+    assert_eq!(profile.profile_id.as_deref(), Some("matus-chochlik-154a7827"));
+    assert_eq!(profile.first_name, "Matus");
+    assert_eq!(profile.last_name, "Chochlik");
+    assert_eq!(profile.headline.as_deref(), Some("SW engineer C++/Python"));
+    assert_eq!(profile.picture_url, None);
+    assert_eq!(profile.positions[0].title, "Team Lead / SW engineer / Architect");
+    assert_eq!(
+        profile.positions[0].time_period.as_ref().unwrap().raw.as_deref(),
+        Some("Apr 2016 – Present")
+    );
+    assert!(profile.positions[0].is_current());
+    assert_eq!(
+        profile.positions[0].time_period.as_ref().unwrap().start,
+        Some(Date {
+            year: 2016,
+            month: Some(4),
+            day: None
+        })
+    );
+    assert_eq!(profile.skills[0].endorsements, Some(12));
+    assert!(profile.education.is_empty());
+}
+
+#[test]
+fn test_profile_same_shape() {
+    let api = serde_json::to_value(Profile::from(api_profile())).unwrap();
+    let web = serde_json::to_value(Profile::from(web_profile())).unwrap();
+    let api_keys: Vec<&String> = api.as_object().unwrap().keys().collect();
+    let web_keys: Vec<&String> = web.as_object().unwrap().keys().collect();
+    assert_eq!(api_keys, web_keys);
+
+    let round_trip: Profile = serde_json::from_value(api).unwrap();
+    assert_eq!(round_trip.first_name, "Matus");
+}
+
+fn date(year: i32, month: Option<u32>) -> Option<Date> {
+    Some(Date { year, month, day: None })
+}
+
+fn position(title: &str, start: Option<Date>, end: Option<Date>) -> Position {
+    Position {
+        title: title.to_string(),
+        company: None,
+        location: None,
+        description: None,
+        time_period: Some(TimePeriod { start, end, raw: None }),
+        classification: Classification::default(),
+    }
+}
+
+#[test]
+fn test_date_normalization() {
+    let year = Date {
+        year: 2020,
+        month: None,
+        day: None,
+    };
+    assert_eq!(year.precision(), DatePrecision::Year);
+    assert_eq!(year.first_day(), NaiveDate::from_ymd_opt(2020, 1, 1));
+    assert_eq!(year.last_day(), NaiveDate::from_ymd_opt(2020, 12, 31));
+
+    let month = Date {
+        year: 2024,
+        month: Some(2),
+        day: None,
+    };
+    assert_eq!(month.precision(), DatePrecision::Month);
+    assert_eq!(month.last_day(), NaiveDate::from_ymd_opt(2024, 2, 29));
+
+    let day = Date {
+        year: 2021,
+        month: Some(12),
+        day: Some(24),
+    };
+    let normalized = day.normalize().unwrap();
+    assert_eq!(normalized.precision, DatePrecision::Day);
+    assert_eq!(normalized.date, NaiveDate::from_ymd_opt(2021, 12, 24).unwrap());
+
+    let invalid = Date {
+        year: 2021,
+        month: Some(13),
+        day: None,
+    };
+    assert_eq!(invalid.normalize(), None);
+}
+
+#[test]
+fn test_position_duration() {
+    let today = NaiveDate::from_ymd_opt(2025, 6, 15).unwrap();
+    assert_eq!(
+        position("a", date(2020, Some(1)), date(2020, Some(3))).duration_months(today),
+        Some(3)
+    );
+    assert_eq!(position("a", date(2018, None), date(2019, None)).duration_months(today), Some(24));
+    assert_eq!(position("a", date(2024, Some(7)), None).duration_months(today), Some(12));
+    assert_eq!(position("a", date(2025, None), date(2025, None)).duration_months(today), Some(6));
+    assert_eq!(position("a", date(2021, Some(5)), date(2020, Some(1))).duration_months(today), None);
+    assert_eq!(position("a", None, date(2020, Some(1))).duration_months(today), None);
+}
+
+#[test]
+fn test_total_experience_with_overlaps() {
+    let today = NaiveDate::from_ymd_opt(2025, 1, 10).unwrap();
+    let mut profile = Profile::from(web_profile());
+    profile.positions = vec![
+        position("Senior Engineer", date(2022, Some(1)), None),
+        position("Freelance Consultant", date(2023, Some(3)), date(2023, Some(8))),
+        position("Engineer", date(2019, Some(1)), date(2021, Some(12))),
+        position("Intern", date(2016, Some(7)), date(2016, Some(9))),
+    ];
+    assert_eq!(profile.total_experience_months(today), 36 + 37 + 3);
+    assert_eq!(profile.years_of_experience(today), 76f32 / 12f32);
+
+    let current: Vec<&str> = profile.current_positions().iter().map(|p| p.title.as_str()).collect();
+    assert_eq!(current, vec!["Senior Engineer"]);
+}
@@ -0,0 +1,336 @@
+use omicron_crawler::azure::json::{Envelope, ProfileIds, ProfileJob, SearchJob};
+use omicron_crawler::azure::Label;
+use omicron_crawler::control::ControlCommand;
+use omicron_crawler::linkedin::api::json::{GeoUrnMap, NetworkDepth, SearchParams};
+use omicron_crawler::queue::file::FileQueue;
+use omicron_crawler::queue::memory::MemoryQueue;
+use omicron_crawler::queue::{spawn_lock_renewal, QueueKind, WorkQueue};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const NO_WAIT: Duration = Duration::ZERO;
+
+fn search(keywords: &str) -> SearchJob {
+    Envelope::new(SearchParams {
+        countries: Some(vec![GeoUrnMap::Slovakia]),
+        keywords: Some(keywords.to_string()),
+        keyword_first_name: None,
+        keyword_last_name: None,
+        keyword_title: None,
+        keyword_company: None,
+        keyword_school: None,
+        profile_language: None,
+        request_metadata: Some("meta".to_string()),
+        network_depth: Some(vec![NetworkDepth::Two]),
+        page: 0,
+        end: 2,
+    })
+}
+
+fn ids(ids: &[&str]) -> ProfileJob {
+    Envelope::new(ProfileIds {
+        ids: ids.iter().map(|id| id.to_string()).collect(),
+        request_metadata: None,
+    })
+}
+
+/// Exercises a queue purely through the trait, the way the worker loop uses it.
+async fn exercise<Q: WorkQueue>(queue: &Q) {
+    assert!(queue.dequeue_search(NO_WAIT).await.unwrap().is_none());
+    assert!(queue.dequeue_profiles(NO_WAIT).await.unwrap().is_none());
+
+    queue.requeue_search(&search("rust")).await.unwrap();
+    queue.requeue_search(&search("java")).await.unwrap();
+    queue.requeue_profiles(&ids(&["a", "b"])).await.unwrap();
+
+    let first = queue.dequeue_search(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(first.message.as_ref().unwrap().payload.keywords.as_deref(), Some("rust"));
+    assert_eq!(first.receipt.queue, QueueKind::Search);
+    assert_eq!(first.receipt.delivery_count, 1);
+    let second = queue.dequeue_search(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(second.message.as_ref().unwrap().payload.keywords.as_deref(), Some("java"));
+    assert_eq!(second.message.as_ref().unwrap().payload.request_metadata.as_deref(), Some("meta"));
+    assert!(matches!(
+        second.message.as_ref().unwrap().payload.countries.as_deref(),
+        Some([GeoUrnMap::Slovakia])
+    ));
+    assert!(queue.dequeue_search(NO_WAIT).await.unwrap().is_none());
+
+    // Abandoned messages are delivered again before anything else
+    queue.renew_lock(&first.receipt).await.unwrap();
+    queue.abandon(&first.receipt).await.unwrap();
+    queue.complete(&second.receipt).await.unwrap();
+    let redelivered = queue.dequeue_search(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(redelivered.message.as_ref().unwrap().payload.keywords.as_deref(), Some("rust"));
+    assert_eq!(redelivered.receipt.delivery_count, 2);
+    assert_eq!(redelivered.message.as_ref().unwrap().job_id, first.message.as_ref().unwrap().job_id);
+    queue.complete(&redelivered.receipt).await.unwrap();
+    assert!(queue.dequeue_search(NO_WAIT).await.unwrap().is_none());
+
+    // A settled lock cannot be used again
+    assert!(queue.complete(&first.receipt).await.is_err());
+    assert!(queue.abandon(&second.receipt).await.is_err());
+    assert!(queue.renew_lock(&second.receipt).await.is_err());
+
+    let profiles = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(profiles.message.as_ref().unwrap().payload.ids, vec!["a", "b"]);
+    assert_eq!(profiles.receipt.queue, QueueKind::Profiles);
+    queue.complete(&profiles.receipt).await.unwrap();
+    assert!(queue.dequeue_profiles(NO_WAIT).await.unwrap().is_none());
+
+    queue.publish_result(&ids(&["c"]).payload, Label::ProfilesComplete).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_memory_queue() {
+    let queue = MemoryQueue::new();
+    exercise(&queue).await;
+    assert_eq!(queue.pending_searches(), 0);
+    assert_eq!(queue.locked_messages(), 0);
+    let results = queue.results();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].label, Label::ProfilesComplete);
+    assert_eq!(results[0].data["ids"][0], "c");
+}
+
+#[tokio::test]
+async fn test_file_queue() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = FileQueue::new(dir).await.unwrap();
+    exercise(&queue).await;
+    let results = queue.results().await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].label, Label::ProfilesComplete);
+
+    let raw = std::fs::read_to_string(dir.join("results.jsonl")).unwrap();
+    assert!(raw.starts_with(r#"{"label":"profiles_complete""#));
+}
+
+#[tokio::test]
+async fn test_file_queue_reads_hand_written_jobs() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    std::fs::write(
+        dir.join("profiles.jsonl"),
+        "not json\n{\"ids\":[\"x\"],\"request_metadata\":\"r\"}\n\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("searches.jsonl"),
+        "{\"keywords\":\"go\",\"countries\":[\"czechia\"],\"network_depth\":[\"one\"],\"page\":1,\"end\":3}\n",
+    )
+    .unwrap();
+    let queue = FileQueue::new(dir).await.unwrap();
+
+    // The malformed line is still delivered, with its raw payload for dead-lettering
+    let malformed = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(malformed.message.as_ref().err().unwrap().raw, "not json");
+    queue.complete(&malformed.receipt).await.unwrap();
+    let profiles = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap().message.unwrap().payload;
+    assert_eq!(profiles.ids, vec!["x"]);
+    assert_eq!(profiles.request_metadata.as_deref(), Some("r"));
+    // The rest of the queue file replaces it whole, nothing is left aside
+    assert_eq!(std::fs::read_to_string(dir.join("profiles.jsonl")).unwrap(), "");
+    assert!(!dir.join("profiles.jsonl.tmp").exists());
+
+    let params = queue.dequeue_search(NO_WAIT).await.unwrap().unwrap().message.unwrap().payload;
+    assert_eq!(params.keywords.as_deref(), Some("go"));
+    assert_eq!((params.page, params.end), (1, 3));
+}
+
+#[tokio::test]
+async fn test_file_queue_recovers_locked_messages() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = FileQueue::new(dir).await.unwrap();
+    queue.push_profiles(&ids(&["a"])).await.unwrap();
+    let delivery = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(delivery.receipt.delivery_count, 1);
+    drop(queue);
+
+    // A worker that died mid-crawl never settled its message, the next one picks it up
+    let queue = FileQueue::new(dir).await.unwrap();
+    let delivery = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(delivery.message.as_ref().unwrap().payload.ids, vec!["a"]);
+    assert_eq!(delivery.receipt.delivery_count, 2);
+    queue.complete(&delivery.receipt).await.unwrap();
+
+    let queue = FileQueue::new(dir).await.unwrap();
+    assert!(queue.dequeue_profiles(NO_WAIT).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_file_queue_delivers_corrupt_abandoned_messages() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = FileQueue::new(dir).await.unwrap();
+    std::fs::write(dir.join("profiles.abandoned").join("0001.json"), "{\"delivery_count\":2,\"pay").unwrap();
+
+    // Handed to the worker as malformed so it ends up in the dead-letter store instead of vanishing
+    let delivery = queue.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(delivery.message.as_ref().err().unwrap().raw, "{\"delivery_count\":2,\"pay");
+    assert_eq!(std::fs::read_dir(dir.join("profiles.abandoned")).unwrap().count(), 0);
+    assert_eq!(std::fs::read_dir(dir.join("profiles.locked")).unwrap().count(), 1);
+    queue.complete(&delivery.receipt).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_file_queue_shared_between_processes() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let worker = FileQueue::new(dir).await.unwrap();
+    worker.push_profiles(&ids(&["a"])).await.unwrap();
+    let delivery = worker.dequeue_profiles(NO_WAIT).await.unwrap().unwrap();
+
+    // Opening the queue elsewhere, e.g. for `queue push`, leaves messages of a live worker alone
+    let cli = FileQueue::new(dir).await.unwrap();
+    let pushes = (0..20).map(|i| {
+        let (worker, cli) = (&worker, &cli);
+        async move {
+            let queue = if i % 2 == 0 { worker } else { cli };
+            queue.push_profiles(&ids(&[&i.to_string()])).await.unwrap()
+        }
+    });
+    futures_util::future::join_all(pushes).await;
+    worker.renew_lock(&delivery.receipt).await.unwrap();
+    let mut pushed = Vec::new();
+    while let Some(delivery) = cli.dequeue_profiles(NO_WAIT).await.unwrap() {
+        pushed.extend(delivery.message.unwrap().payload.ids);
+        cli.complete(&delivery.receipt).await.unwrap();
+    }
+    pushed.sort_by_key(|id| id.parse::<u32>().unwrap());
+    assert_eq!(pushed, (0..20).map(|i| i.to_string()).collect::<Vec<_>>());
+    worker.complete(&delivery.receipt).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lock_renewal() {
+    let queue = Arc::new(MemoryQueue::new());
+    queue.push_search(search("rust"));
+    let delivery = queue.dequeue_search(NO_WAIT).await.unwrap().unwrap();
+    let renewal = spawn_lock_renewal(queue.clone(), delivery.receipt.clone(), Duration::from_millis(5));
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    renewal.abort();
+    queue.complete(&delivery.receipt).await.unwrap();
+    assert_eq!(queue.locked_messages(), 0);
+}
+
+#[tokio::test]
+async fn test_memory_queue_long_poll() {
+    let queue = Arc::new(MemoryQueue::new());
+    let started = Instant::now();
+    assert!(queue.dequeue_search(Duration::from_millis(30)).await.unwrap().is_none());
+    assert!(started.elapsed() >= Duration::from_millis(30));
+
+    // A waiting receive is woken by the push instead of running out its wait
+    let pusher = queue.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        pusher.push_profiles(ids(&["a"]));
+    });
+    let started = Instant::now();
+    let delivery = queue.dequeue_profiles(Duration::from_secs(5)).await.unwrap().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(delivery.message.unwrap().payload.ids, vec!["a"]);
+}
+
+#[tokio::test]
+async fn test_file_queue_long_poll() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let queue = FileQueue::new(dir).await.unwrap();
+    assert!(queue.dequeue_search(Duration::from_millis(30)).await.unwrap().is_none());
+
+    // Jobs appended by another process are picked up while waiting
+    let file = dir.join("searches.jsonl");
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        std::fs::write(file, "{\"keywords\":\"go\",\"page\":0,\"end\":1}\n").unwrap();
+    });
+    let delivery = queue.dequeue_search(Duration::from_secs(5)).await.unwrap().unwrap();
+    assert_eq!(delivery.message.unwrap().payload.keywords.as_deref(), Some("go"));
+}
+
+async fn exercise_control<Q: WorkQueue>(queue: &Q) {
+    assert!(queue.dequeue_control(NO_WAIT).await.unwrap().is_none());
+    queue.requeue_control(&ControlCommand::Pause).await.unwrap();
+    queue
+        .requeue_control(&ControlCommand::SetBudget { profiles_per_hour: 50 })
+        .await
+        .unwrap();
+
+    let pause = queue.dequeue_control(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(pause.receipt.queue, QueueKind::Control);
+    assert_eq!(pause.message.as_ref().unwrap(), &ControlCommand::Pause);
+    queue.abandon(&pause.receipt).await.unwrap();
+    let again = queue.dequeue_control(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(again.receipt.delivery_count, 2);
+    queue.complete(&again.receipt).await.unwrap();
+
+    let budget = queue.dequeue_control(NO_WAIT).await.unwrap().unwrap();
+    assert_eq!(budget.message.unwrap(), ControlCommand::SetBudget { profiles_per_hour: 50 });
+    queue.complete(&budget.receipt).await.unwrap();
+    // Control messages do not mix with jobs
+    assert!(queue.dequeue_search(NO_WAIT).await.unwrap().is_none());
+    assert!(queue.dequeue_control(NO_WAIT).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_control_queue() {
+    let queue = MemoryQueue::new();
+    exercise_control(&queue).await;
+    assert_eq!(queue.locked_messages(), 0);
+
+    let tmp = TempDir::new().unwrap();
+
+    let dir = tmp.path();
+    let queue = FileQueue::new(dir).await.unwrap();
+    exercise_control(&queue).await;
+    std::fs::write(
+        dir.join("control.jsonl"),
+        "{\"command\":\"disable_account\",\"account\":\"crawler@example.com\"}\n",
+    )
+    .unwrap();
+    let command = queue.dequeue_control(NO_WAIT).await.unwrap().unwrap().message.unwrap();
+    assert_eq!(
+        command,
+        ControlCommand::DisableAccount {
+            account: "crawler@example.com".to_string()
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_file_queue_control_subscriptions() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+    let first = FileQueue::new(dir).await.unwrap().subscribe("first").await.unwrap();
+    let second = FileQueue::new(dir).await.unwrap().subscribe("second").await.unwrap();
+    let pusher = FileQueue::new(dir).await.unwrap();
+    assert!(FileQueue::new(dir).await.unwrap().subscribe("../escape").await.is_err());
+
+    // Every subscriber receives each command
+    pusher.push_control(&ControlCommand::Pause).await.unwrap();
+    for queue in [&first, &second] {
+        let delivery = queue.dequeue_control(NO_WAIT).await.unwrap().unwrap();
+        assert_eq!(delivery.message.unwrap(), ControlCommand::Pause);
+        queue.complete(&delivery.receipt).await.unwrap();
+        assert!(queue.dequeue_control(NO_WAIT).await.unwrap().is_none());
+    }
+    assert!(pusher.dequeue_control(NO_WAIT).await.unwrap().is_none());
+
+    // Commands pushed while a subscriber is stopped wait for it, abandoned ones come back to it only
+    drop(second);
+    pusher.push_control(&ControlCommand::Resume).await.unwrap();
+    let delivery = first.dequeue_control(NO_WAIT).await.unwrap().unwrap();
+    first.abandon(&delivery.receipt).await.unwrap();
+    let second = FileQueue::new(dir).await.unwrap().subscribe("second").await.unwrap();
+    assert_eq!(
+        second.dequeue_control(NO_WAIT).await.unwrap().unwrap().message.unwrap(),
+        ControlCommand::Resume
+    );
+    assert!(second.dequeue_control(NO_WAIT).await.unwrap().is_none());
+    assert_eq!(first.dequeue_control(NO_WAIT).await.unwrap().unwrap().receipt.delivery_count, 2);
+}
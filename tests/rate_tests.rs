@@ -1,4 +1,4 @@
-use omicron_crawler::linkedin::api::rate_limits::RateLimiter;
+use omicron_crawler::linkedin::api::rate_limits::{RateLimiter, AVG_RESPONSE_TIME_MS, MAX_PROFILES_PER_HOUR};
 
 #[test]
 fn test_rate_limits1() {
@@ -57,3 +57,28 @@ fn test_rate_limits4() {
         count -= 1;
     }
 }
+
+#[test]
+fn test_set_profiles_per_hour() {
+    let rate_limits = RateLimiter::new(100, 800);
+    rate_limits.next().unwrap();
+    rate_limits.set_profiles_per_hour(400);
+    assert_eq!(rate_limits.profiles_per_hour(), 400);
+    let budget = rate_limits.budget();
+    // Profiles already crawled still count against the new budget
+    assert_eq!((budget.profiles_per_hour, budget.used, budget.remaining), (400, 1, 399));
+
+    // Waits come from the smaller set generated for the higher budget
+    let max_wait = *RateLimiter::generate_random_waits(400, 800).iter().max().unwrap();
+    for _ in 0..500 {
+        assert!(rate_limits.next().unwrap().as_secs() <= max_wait);
+    }
+}
+
+#[test]
+fn test_budget_beyond_the_hour() {
+    // The requests alone take longer than the hour, nothing is left to wait and nothing overflows
+    assert_eq!(RateLimiter::generate_random_waits(5_000, 800), vec![0]);
+    assert_eq!(RateLimiter::generate_random_waits(u32::MAX, 800), vec![0]);
+    assert!(RateLimiter::generate_random_waits(MAX_PROFILES_PER_HOUR - 100, AVG_RESPONSE_TIME_MS).len() > 1);
+}
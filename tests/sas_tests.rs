@@ -0,0 +1,66 @@
+use omicron_crawler::azure::sas::{sign, SasKey, SasResource, SasSigner};
+use omicron_crawler::queue::QueueKind;
+use std::time::Duration;
+
+const SEARCH_URI: &str = "https://omicron.servicebus.windows.net/searches";
+const MANAGER_URI: &str = "https://omicron.servicebus.windows.net/manager";
+
+fn key(resource_uri: &str, key_name: &str, key: &str) -> SasKey {
+    SasKey {
+        resource_uri: resource_uri.to_string(),
+        key_name: key_name.to_string(),
+        key: key.to_string(),
+    }
+}
+
+fn signer() -> SasSigner {
+    SasSigner::new(Duration::from_secs(3600), Duration::from_secs(300))
+        .with_key(
+            SasResource::SearchQueue,
+            key(SEARCH_URI, "RootManageSharedAccessKey", "c2VjcmV0LWtleQ=="),
+        )
+        .with_key(SasResource::ManagerBus, key(MANAGER_URI, "send", "another key"))
+}
+
+#[test]
+fn test_known_vectors() {
+    // Signatures computed independently with the reference algorithm from the Service Bus docs
+    assert_eq!(
+        sign(SEARCH_URI, "RootManageSharedAccessKey", "c2VjcmV0LWtleQ==", 1700000000).unwrap(),
+        "SharedAccessSignature sr=https%3A%2F%2Fomicron.servicebus.windows.net%2Fsearches\
+         &sig=%2Bnxa14CTI14hvl%2FaDBk4xMIYOYpKMdJjNtW43Vw6n2c%3D&se=1700000000&skn=RootManageSharedAccessKey"
+    );
+    assert_eq!(
+        sign(MANAGER_URI, "send", "another key", 1700003600).unwrap(),
+        "SharedAccessSignature sr=https%3A%2F%2Fomicron.servicebus.windows.net%2Fmanager\
+         &sig=19EvfmJ0K6o8PHSBPxoV8Lyhy3B5qjFapE5da446vow%3D&se=1700003600&skn=send"
+    );
+}
+
+#[test]
+fn test_signer_uses_resource_key() {
+    let signer = signer();
+    assert_eq!(
+        signer.token_at(SasResource::ManagerBus, 1700000000).unwrap(),
+        sign(MANAGER_URI, "send", "another key", 1700003600).unwrap()
+    );
+    assert_eq!(
+        signer.token_at(QueueKind::Search.into(), 1700000000).unwrap(),
+        sign(SEARCH_URI, "RootManageSharedAccessKey", "c2VjcmV0LWtleQ==", 1700003600).unwrap()
+    );
+    assert!(signer.token_at(SasResource::ProfileQueue, 1700000000).is_err());
+}
+
+#[test]
+fn test_signer_caches_until_refresh() {
+    let signer = signer();
+    let first = signer.token_at(SasResource::SearchQueue, 1700000000).unwrap();
+    assert_eq!(signer.token_at(SasResource::SearchQueue, 1700000010).unwrap(), first);
+    // Still more than the refresh margin left
+    assert_eq!(signer.token_at(SasResource::SearchQueue, 1700003299).unwrap(), first);
+
+    let refreshed = signer.token_at(SasResource::SearchQueue, 1700003300).unwrap();
+    assert_ne!(refreshed, first);
+    assert!(refreshed.ends_with("&se=1700006900&skn=RootManageSharedAccessKey"));
+    assert_eq!(signer.token_at(SasResource::SearchQueue, 1700003400).unwrap(), refreshed);
+}
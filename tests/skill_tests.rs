@@ -0,0 +1,161 @@
+use omicron_crawler::linkedin::api::json;
+use omicron_crawler::linkedin::profile::skills::{normalize_skill_name, skill_taxonomy, SkillCategory, SkillTaxonomy};
+use omicron_crawler::linkedin::profile::Profile;
+use omicron_crawler::linkedin::web_driver::profiles;
+
+#[test]
+fn test_bundled_aliases() {
+    let taxonomy = SkillTaxonomy::bundled();
+    for (raw, id) in [
+        ("JS", "javascript"),
+        ("Javascript", "javascript"),
+        ("JavaScript ES6", "javascript"),
+        ("  java   script ", "javascript"),
+        ("C++", "cpp"),
+        ("Golang", "go"),
+        ("Amazon Web Services (AWS)", "aws"),
+        ("Python (Programming Language)", "python"),
+        ("Spring Boot", "spring"),
+        ("Vedenie tímu", "leadership"),
+    ] {
+        assert_eq!(taxonomy.lookup(raw).map(|skill| skill.id.as_str()), Some(id), "{}", raw);
+    }
+    assert!(taxonomy.lookup("Underwater basket weaving").is_none());
+}
+
+#[test]
+fn test_distinct_skills_stay_apart() {
+    let taxonomy = SkillTaxonomy::bundled();
+    for (raw, id) in [
+        ("Git", "git"),
+        ("GitHub", "github"),
+        ("GitLab", "gitlab"),
+        ("SVN", "svn"),
+        ("Subversion", "svn"),
+        ("Scrum", "scrum"),
+        ("Kanban", "kanban"),
+        ("Unit Testing", "unit_testing"),
+        ("TDD", "tdd"),
+        ("Jenkins", "jenkins"),
+        ("GitLab CI", "gitlab_ci"),
+        ("Continuous Integration", "ci_cd"),
+        ("MariaDB", "mariadb"),
+        ("MySQL", "mysql"),
+        ("Confluence", "confluence"),
+        ("Jira", "jira"),
+        ("Ubuntu", "ubuntu"),
+        ("Debian", "debian"),
+        ("Unix", "unix"),
+        ("Linux", "linux"),
+        ("SCSS", "sass"),
+        ("Less", "less"),
+        ("CSS", "css"),
+        ("PowerPoint", "powerpoint"),
+        ("Microsoft Word", "word"),
+        ("Microsoft Office", "ms_office"),
+    ] {
+        assert_eq!(taxonomy.lookup(raw).map(|skill| skill.id.as_str()), Some(id), "{}", raw);
+    }
+    // Every name maps to a single entry, otherwise aggregating by canonical id merges different skills
+    let skills = taxonomy.skills();
+    for skill in skills {
+        assert_eq!(taxonomy.lookup(&skill.name).map(|found| found.id.as_str()), Some(skill.id.as_str()));
+        for alias in &skill.aliases {
+            assert_eq!(
+                taxonomy.lookup(alias).map(|found| found.id.as_str()),
+                Some(skill.id.as_str()),
+                "{}",
+                alias
+            );
+        }
+    }
+}
+
+#[test]
+fn test_canonical_entry() {
+    let skill = SkillTaxonomy::bundled().lookup("reactjs").cloned().unwrap();
+    assert_eq!(skill.name, "React");
+    assert_eq!(skill.category, SkillCategory::Framework);
+}
+
+#[test]
+fn test_normalize_skill_name() {
+    assert_eq!(normalize_skill_name("  Node.JS  "), "node.js");
+    assert_eq!(normalize_skill_name("Java (Programming Language)"), "java");
+    assert_eq!(normalize_skill_name("(Unbalanced"), "(unbalanced");
+}
+
+#[test]
+fn test_extend_taxonomy() {
+    let mut taxonomy = SkillTaxonomy::bundled();
+    let path = std::env::temp_dir().join("omicron_skill_taxonomy_test.json");
+    std::fs::write(
+        &path,
+        r#"[
+            { "id": "javascript", "name": "JavaScript", "category": "programming_language", "aliases": ["ecma"] },
+            { "id": "abap", "name": "ABAP", "category": "programming_language", "aliases": ["sap abap"] }
+        ]"#,
+    )
+    .unwrap();
+    assert!(taxonomy.extend_from_file(path.to_str().unwrap()).is_ok());
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(taxonomy.lookup("ecma").unwrap().id, "javascript");
+    assert_eq!(taxonomy.lookup("JS").unwrap().id, "javascript");
+    assert_eq!(taxonomy.lookup("SAP ABAP").unwrap().name, "ABAP");
+    assert_eq!(taxonomy.skills().iter().filter(|skill| skill.id == "javascript").count(), 1);
+
+    assert!(taxonomy.extend_from_json("not json").is_err());
+    assert!(taxonomy.extend_from_file("/nonexistent/taxonomy.json").is_err());
+}
+
+#[test]
+fn test_source_skills_canonical() {
+    let api_skill = json::Skill {
+        name: "Postgres".to_string(),
+        entity_urn: "urn:li:fsd_skill:(ACoAAA,1)".to_string(),
+    };
+    assert_eq!(api_skill.canonical().unwrap().id, "postgresql");
+
+    let web_skill = profiles::Skill {
+        name: "K8s".to_string(),
+        endorsements: 3,
+    };
+    assert_eq!(web_skill.canonical().unwrap().id, "kubernetes");
+    assert!(skill_taxonomy().lookup("kubernetes").is_some());
+}
+
+#[test]
+fn test_profile_skills_are_normalized() {
+    let profile = Profile::from(profiles::Profile {
+        name: "Jane Doe".to_string(),
+        url: "https://www.linkedin.com/in/jane-doe".to_string(),
+        sales_url: None,
+        profile_picture_url: "".to_string(),
+        description: "".to_string(),
+        about: None,
+        location: "".to_string(),
+        experience: None,
+        education: None,
+        skills: Some(vec![
+            profiles::Skill {
+                name: "Javascript".to_string(),
+                endorsements: 12,
+            },
+            profiles::Skill {
+                name: "Knitting".to_string(),
+                endorsements: 1,
+            },
+        ]),
+        languages: None,
+    });
+    let skill = &profile.skills[0];
+    assert_eq!(skill.name, "Javascript");
+    assert_eq!(skill.canonical_id.as_deref(), Some("javascript"));
+    assert_eq!(skill.canonical_name.as_deref(), Some("JavaScript"));
+    assert_eq!(skill.category, Some(SkillCategory::ProgrammingLanguage));
+    assert!(profile.skills[1].canonical_id.is_none());
+
+    let json = serde_json::to_value(&profile).unwrap();
+    assert_eq!(json["skills"][0]["category"], "programming_language");
+}
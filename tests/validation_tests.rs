@@ -0,0 +1,125 @@
+use actix_web::http::StatusCode;
+use actix_web::{web, App, HttpResponse};
+use omicron_crawler::azure::json::ProfileIds;
+use omicron_crawler::linkedin::api::json::SearchParams;
+use omicron_crawler::linkedin::web_driver::messages::{DeliveryStatus, MessageDelivery, MessageRequest};
+use omicron_crawler::validation::{parse, FieldError, Valid, ValidationErrors};
+
+fn search_errors(body: &str) -> Vec<FieldError> {
+    parse::<SearchParams>(body.as_bytes())
+        .err()
+        .expect("body should be rejected")
+        .errors
+}
+
+#[test]
+fn test_valid_search() {
+    let params: SearchParams =
+        parse(br#"{"keywords": "rust", "countries": ["czechia"], "network_depth": ["two"], "page": 0, "end": 3}"#).unwrap();
+    assert_eq!((params.page, params.end), (0, 3));
+}
+
+#[test]
+fn test_search_field_errors() {
+    assert_eq!(
+        search_errors(r#"{"page": 5, "end": 2}"#),
+        vec![FieldError::new("page", "must not be greater than end (2)")]
+    );
+    assert_eq!(search_errors(r#"{"page": 0, "end": 101}"#)[0].field, "end");
+
+    let errors = search_errors(r#"{"countries": ["czechia", "austria"], "page": 0, "end": 1}"#);
+    assert_eq!(errors[0].field, "countries[1]");
+    assert_eq!(errors[0].message, "unknown variant `austria`, expected `czechia` or `slovakia`");
+
+    let errors = search_errors(r#"{"network_depth": ["four"], "page": 0, "end": 1}"#);
+    assert_eq!(errors[0].field, "network_depth[0]");
+    assert!(errors[0].message.starts_with("unknown variant `four`"));
+
+    let errors = search_errors(r#"{"page": -1, "end": 1}"#);
+    assert_eq!(errors[0].field, "page");
+
+    let errors = search_errors(r#"{"page": 0}"#);
+    assert_eq!(errors[0], FieldError::new("body", "missing field `end`"));
+    assert_eq!(search_errors("not json")[0].field, "body");
+}
+
+#[test]
+fn test_profile_ids_errors() {
+    let errors = parse::<ProfileIds>(br#"{"ids": []}"#).err().unwrap().errors;
+    assert_eq!(errors, vec![FieldError::new("ids", "must contain at least one id")]);
+
+    let errors = parse::<ProfileIds>(br#"{"ids": ["a", " ", ""]}"#).err().unwrap().errors;
+    let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+    assert_eq!(fields, vec!["ids[1]", "ids[2]"]);
+
+    assert!(parse::<ProfileIds>(br#"{"ids": ["a"], "request_metadata": "meta"}"#).is_ok());
+}
+
+#[actix_web::test]
+async fn test_valid_extractor() {
+    let app = actix_web::test::init_service(App::new().route(
+        "/profiles",
+        web::post().to(|ids: Valid<ProfileIds>| async move { HttpResponse::Ok().body(ids.ids.join(",")) }),
+    ))
+    .await;
+
+    let request = actix_web::test::TestRequest::post()
+        .uri("/profiles")
+        .set_payload(r#"{"ids": ["a", "b"]}"#)
+        .to_request();
+    let response = actix_web::test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(actix_web::test::read_body(response).await, "a,b");
+
+    let request = actix_web::test::TestRequest::post()
+        .uri("/profiles")
+        .set_payload(r#"{"ids": 3}"#)
+        .to_request();
+    let response = actix_web::test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: ValidationErrors = actix_web::test::read_body_json(response).await;
+    assert_eq!(body.errors.len(), 1);
+    assert_eq!(body.errors[0].field, "ids");
+}
+
+#[test]
+fn test_message_errors() {
+    let messages: Vec<MessageRequest> =
+        parse(br#"[{"sales_url": "https://www.linkedin.com/sales/lead/ACwAABpJtzo", "subject": "Hi", "body": "Hello"}]"#).unwrap();
+    assert_eq!(messages.len(), 1);
+
+    let errors = parse::<Vec<MessageRequest>>(b"[]").err().unwrap().errors;
+    assert_eq!(errors, vec![FieldError::new("body", "must contain at least one message")]);
+
+    let body = br#"[
+        {"sales_url": "https://www.linkedin.com/sales/lead/ACwAABpJtzo", "subject": "", "body": "Hello"},
+        {"sales_url": "https://www.linkedin.com/in/someone", "subject": "Hi", "body": " "}
+    ]"#;
+    let errors = parse::<Vec<MessageRequest>>(body).err().unwrap().errors;
+    let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+    assert_eq!(fields, vec!["[1].sales_url", "[1].body"]);
+
+    let errors = parse::<Vec<MessageRequest>>(br#"[{"sales_url": "https://www.linkedin.com/sales/lead/a", "body": "Hello"}]"#)
+        .err()
+        .unwrap()
+        .errors;
+    assert_eq!(errors[0], FieldError::new("[0]", "missing field `subject`"));
+}
+
+#[test]
+fn test_delivery_record() {
+    let message = MessageRequest {
+        sales_url: "https://www.linkedin.com/sales/lead/a".to_string(),
+        subject: "Hi".to_string(),
+        body: "Hello".to_string(),
+    };
+    let sent = MessageDelivery::new(&message, None);
+    assert_eq!(sent.status, DeliveryStatus::Sent);
+    assert_eq!(sent.sales_url, message.sales_url);
+    let failed = MessageDelivery::new(&message, Some("Failed to find message button".to_string()));
+    assert_eq!(failed.status, DeliveryStatus::Failed);
+    assert_ne!(sent.id, failed.id);
+    let json = serde_json::to_value(&failed).unwrap();
+    assert_eq!(json["status"], "failed");
+    assert_eq!(json["error"], "Failed to find message button");
+}